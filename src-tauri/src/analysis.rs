@@ -0,0 +1,293 @@
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+use crate::ffmpeg::FFmpegProcessor;
+use crate::models::{get_temp_dir, AudioTrack, VideoClip};
+
+const DEFAULT_SILENCE_NOISE_DB: f64 = -35.0;
+const DEFAULT_SILENCE_MIN_DURATION: f64 = 0.5;
+// Silence this close to an edge still counts as touching it
+const EDGE_TOLERANCE: f64 = 0.05;
+const DEFAULT_LOUDNESS_TARGET: f64 = -16.0;
+// Beyond this the track is probably near-silent and boosting it only adds noise
+const MAX_LOUDNESS_GAIN: f64 = 20.0;
+const DEFAULT_WAVEFORM_COLOR: &str = "0x3b82f6";
+const MAX_WAVEFORM_SIZE: i32 = 8192;
+const DEFAULT_SCENE_THRESHOLD: f64 = 0.4;
+// Shortest black stretch taken as a cut, and closest two cuts may be
+const MIN_BLACK_DURATION: f64 = 0.1;
+const MIN_SCENE_LENGTH: f64 = 0.5;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SilenceRange {
+    pub start: f64,
+    // None when the silence runs until the end of the file
+    pub end: Option<f64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TrimSuggestion {
+    pub start: f64,
+    pub end: Option<f64>,
+}
+
+pub fn run_silencedetect(path: &str, noise_db: f64, min_duration: f64) -> Result<Vec<SilenceRange>, String> {
+    let output = Command::new("ffmpeg")
+        .args(["-hide_banner", "-nostats", "-i", path, "-vn"])
+        .args(["-af", &format!("silencedetect=noise={}dB:d={}", noise_db, min_duration)])
+        .args(["-f", "null", "-"])
+        .stdout(Stdio::null())
+        .output()
+        .map_err(|e| format!("Impossible de lancer ffmpeg: {}", e))?;
+
+    let start_regex = Regex::new(r"silence_start: (-?[\d.]+)").unwrap();
+    let end_regex = Regex::new(r"silence_end: (-?[\d.]+)").unwrap();
+
+    let mut ranges: Vec<SilenceRange> = Vec::new();
+    for line in String::from_utf8_lossy(&output.stderr).lines() {
+        if let Some(caps) = start_regex.captures(line) {
+            if let Ok(start) = caps[1].parse::<f64>() {
+                ranges.push(SilenceRange { start: start.max(0.0), end: None });
+            }
+        } else if let Some(caps) = end_regex.captures(line) {
+            if let (Some(last), Ok(end)) = (ranges.last_mut(), caps[1].parse::<f64>()) {
+                last.end = Some(end);
+            }
+        }
+    }
+
+    Ok(ranges)
+}
+
+// Cut points of a long recording: frames whose scene score exceeds `threshold`
+// (0..1, the share of the picture that changed), plus the middle of each black
+// stretch. Sorted, and closer than MIN_SCENE_LENGTH to the start or to the
+// previous cut are dropped.
+pub fn run_scene_detect(path: &str, threshold: f64) -> Result<Vec<f64>, String> {
+    let filter = format!(
+        "blackdetect=d={}:pix_th=0.1,select='gt(scene,{})',showinfo",
+        MIN_BLACK_DURATION,
+        threshold.clamp(0.0, 1.0)
+    );
+    let output = Command::new("ffmpeg")
+        .args(["-hide_banner", "-nostats", "-i", path, "-an"])
+        .args(["-vf", &filter])
+        .args(["-f", "null", "-"])
+        .stdout(Stdio::null())
+        .output()
+        .map_err(|e| format!("Impossible de lancer ffmpeg: {}", e))?;
+    if !output.status.success() {
+        return Err(format!("La detection des scenes a echoue pour {}", path));
+    }
+
+    let scene_regex = Regex::new(r"Parsed_showinfo.*\bpts_time:(-?[\d.]+)").unwrap();
+    let black_regex = Regex::new(r"black_start:\s*(-?[\d.]+)\s+black_end:\s*(-?[\d.]+)").unwrap();
+
+    let mut cuts: Vec<f64> = Vec::new();
+    for line in String::from_utf8_lossy(&output.stderr).lines() {
+        if let Some(caps) = scene_regex.captures(line) {
+            cuts.extend(caps[1].parse::<f64>().ok());
+        } else if let Some(caps) = black_regex.captures(line) {
+            if let (Ok(start), Ok(end)) = (caps[1].parse::<f64>(), caps[2].parse::<f64>()) {
+                cuts.push((start + end) / 2.0);
+            }
+        }
+    }
+
+    cuts.sort_by(f64::total_cmp);
+    let mut kept: Vec<f64> = Vec::new();
+    for cut in cuts {
+        if cut - kept.last().copied().unwrap_or(0.0) >= MIN_SCENE_LENGTH {
+            kept.push(cut);
+        }
+    }
+    Ok(kept)
+}
+
+// Integrated loudness (LUFS) from the ebur128 summary printed at the end of the run
+pub fn measure_integrated_loudness(path: &str) -> Result<Option<f64>, String> {
+    let output = Command::new("ffmpeg")
+        .args(["-hide_banner", "-nostats", "-i", path, "-vn"])
+        .args(["-af", "ebur128=framelog=quiet"])
+        .args(["-f", "null", "-"])
+        .stdout(Stdio::null())
+        .output()
+        .map_err(|e| format!("Impossible de lancer ffmpeg: {}", e))?;
+
+    let integrated_regex = Regex::new(r"I:\s+(-?[\d.]+) LUFS").unwrap();
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    Ok(integrated_regex
+        .captures_iter(&stderr)
+        .last()
+        .and_then(|caps| caps[1].parse::<f64>().ok())
+        .filter(|lufs| lufs.is_finite() && *lufs > -70.0))
+}
+
+// Gain (dB) bringing each measured loudness to the target; unmeasurable tracks are left as is
+pub fn suggest_loudness_gains(loudness: &[Option<f64>], target: f64) -> Vec<f64> {
+    loudness
+        .iter()
+        .map(|l| l.map_or(0.0, |lufs| (target - lufs).clamp(-MAX_LOUDNESS_GAIN, MAX_LOUDNESS_GAIN)))
+        .collect()
+}
+
+// Leading silence moves the start forward, trailing silence pulls the end back
+pub fn suggest_trim(ranges: &[SilenceRange], duration: f64) -> TrimSuggestion {
+    let start = ranges
+        .first()
+        .filter(|r| r.start <= EDGE_TOLERANCE)
+        .and_then(|r| r.end)
+        .unwrap_or(0.0);
+
+    let end = ranges
+        .last()
+        .filter(|r| r.start > start)
+        .filter(|r| match r.end {
+            None => true,
+            Some(end) => duration > 0.0 && end >= duration - EDGE_TOLERANCE,
+        })
+        .map(|r| r.start);
+
+    TrimSuggestion { start, end }
+}
+
+// ffmpeg color syntax: a name or 0xRRGGBB / #RRGGBB, optionally @alpha
+fn is_valid_color(color: &str) -> bool {
+    !color.is_empty() && color.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '#' | '@' | '.'))
+}
+
+// PNG of the whole file's waveform. Rendered once per file version and size into
+// the temp dir, so the key covers the file contents (see `content_hash`), dimensions and color.
+pub fn render_waveform(path: &str, width: i32, height: i32, color: &str) -> Result<String, String> {
+    if !is_valid_color(color) {
+        return Err(format!("Couleur invalide: {}", color));
+    }
+    let (width, height) = (width.clamp(16, MAX_WAVEFORM_SIZE), height.clamp(16, MAX_WAVEFORM_SIZE));
+
+    let mut hasher = DefaultHasher::new();
+    let file_key = FFmpegProcessor::content_hash(path).unwrap_or_else(|| FFmpegProcessor::get_cache_key(path));
+    (file_key, width, height, color).hash(&mut hasher);
+    let image = get_temp_dir().join(format!("waveform_{:016x}.png", hasher.finish()));
+    let image_str = image.to_string_lossy().to_string();
+    if image.exists() {
+        return Ok(image_str);
+    }
+
+    if !Path::new(path).exists() {
+        return Err(format!("Fichier introuvable: {}", path));
+    }
+    let output = Command::new("ffmpeg")
+        .args(["-y", "-hide_banner", "-nostdin", "-v", "error", "-i", path])
+        .args(["-filter_complex", &format!("[0:a:0]showwavespic=s={}x{}:colors={}[w]", width, height, color)])
+        .args(["-map", "[w]", "-frames:v", "1", &image_str])
+        .stdout(Stdio::null())
+        .output()
+        .map_err(|e| format!("Impossible de lancer ffmpeg: {}", e))?;
+
+    if !output.status.success() || !image.exists() {
+        let _ = std::fs::remove_file(&image);
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let reason = stderr.lines().map(str::trim).rfind(|l| !l.is_empty()).unwrap_or("aucune piste audio");
+        return Err(format!("Impossible de generer la forme d'onde: {}", reason));
+    }
+    Ok(image_str)
+}
+
+// Tauri commands
+
+#[tauri::command]
+pub async fn detect_silence(
+    path: String,
+    noise_db: Option<f64>,
+    min_duration: Option<f64>,
+) -> Result<Vec<SilenceRange>, String> {
+    run_silencedetect(
+        &path,
+        noise_db.unwrap_or(DEFAULT_SILENCE_NOISE_DB),
+        min_duration.unwrap_or(DEFAULT_SILENCE_MIN_DURATION),
+    )
+}
+
+#[tauri::command]
+pub async fn trim_silence_from_video(
+    clip: VideoClip,
+    noise_db: Option<f64>,
+    min_duration: Option<f64>,
+) -> Result<TrimSuggestion, String> {
+    let ranges = run_silencedetect(
+        &clip.path,
+        noise_db.unwrap_or(DEFAULT_SILENCE_NOISE_DB),
+        min_duration.unwrap_or(DEFAULT_SILENCE_MIN_DURATION),
+    )?;
+    Ok(suggest_trim(&ranges, clip.duration))
+}
+
+// Timestamps (seconds) to split a long recording at, see `run_scene_detect`
+#[tauri::command]
+pub async fn detect_scenes(path: String, threshold: Option<f64>) -> Result<Vec<f64>, String> {
+    run_scene_detect(&path, threshold.unwrap_or(DEFAULT_SCENE_THRESHOLD))
+}
+
+// Returns one gain offset (dB) per track, to be stored as `AudioTrack::loudness_gain`
+#[tauri::command]
+pub async fn analyze_track_loudness(tracks: Vec<AudioTrack>, target_lufs: Option<f64>) -> Result<Vec<f64>, String> {
+    let loudness = tracks
+        .iter()
+        .map(|t| measure_integrated_loudness(&t.path))
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(suggest_loudness_gains(&loudness, target_lufs.unwrap_or(DEFAULT_LOUDNESS_TARGET)))
+}
+
+#[tauri::command]
+pub async fn render_waveform_image(
+    path: String,
+    width: i32,
+    height: i32,
+    color: Option<String>,
+) -> Result<String, String> {
+    render_waveform(&path, width, height, color.as_deref().unwrap_or(DEFAULT_WAVEFORM_COLOR))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn silence(start: f64, end: Option<f64>) -> SilenceRange {
+        SilenceRange { start, end }
+    }
+
+    #[test]
+    fn silence_at_the_edges_becomes_a_trim() {
+        let ranges = [silence(0.0, Some(1.5)), silence(4.0, Some(5.0)), silence(8.0, None)];
+        let trim = suggest_trim(&ranges, 10.0);
+        assert_eq!((trim.start, trim.end), (1.5, Some(8.0)));
+
+        // Silence in the middle only leaves the clip as is
+        let trim = suggest_trim(&[silence(3.0, Some(4.0))], 10.0);
+        assert_eq!((trim.start, trim.end), (0.0, None));
+        // A trailing range must reach the end of the file, within the tolerance
+        let trim = suggest_trim(&[silence(7.0, Some(9.98))], 10.0);
+        assert_eq!(trim.end, Some(7.0));
+        assert_eq!(suggest_trim(&[], 10.0).end, None);
+    }
+
+    #[test]
+    fn loudness_gains_reach_the_target_within_limits() {
+        let gains = suggest_loudness_gains(&[Some(-20.0), Some(-10.5), None, Some(-60.0)], DEFAULT_LOUDNESS_TARGET);
+        assert_eq!(gains, vec![4.0, -5.5, 0.0, MAX_LOUDNESS_GAIN]);
+    }
+
+    #[test]
+    fn waveform_colors_use_ffmpeg_syntax() {
+        for color in ["0x3b82f6", "#ff0000", "white", "red@0.5"] {
+            assert!(is_valid_color(color), "{}", color);
+        }
+        for color in ["", "red:x", "a;b", "0x00 00"] {
+            assert!(!is_valid_color(color), "{}", color);
+        }
+    }
+}
@@ -0,0 +1,320 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, ExitStatus};
+use std::time::Instant;
+use tauri::{AppHandle, Emitter, State};
+
+use crate::ffmpeg::{run_ffmpeg_quiet, ExportResult, FFmpegProcessor, FfmpegOutcome};
+use crate::models::{get_transition_durations, OverwritePolicy, VideoClip};
+use crate::naming::overwrite_flag;
+use crate::process::{new_command, ExportGuard};
+use crate::AppState;
+
+// What a clip must match to be stream-copied after the base video
+#[derive(Debug, Clone, PartialEq)]
+struct StreamFormat {
+    video_codec: String,
+    width: i64,
+    height: i64,
+    fps: f64,
+    audio_codec: Option<String>,
+    sample_rate: Option<i64>,
+    channels: Option<i64>,
+}
+
+impl StreamFormat {
+    fn probe(path: &str) -> Option<Self> {
+        let output = Command::new("ffprobe")
+            .args([
+                "-v", "error",
+                "-show_entries", "stream=codec_type,codec_name,width,height,r_frame_rate,sample_rate,channels",
+                "-print_format", "json", path,
+            ])
+            .output()
+            .ok()?;
+
+        let json: serde_json::Value = serde_json::from_slice(&output.stdout).ok()?;
+        let streams = json.get("streams")?.as_array()?;
+        let of_type = |t: &str| streams.iter().find(|s| s.get("codec_type").and_then(|c| c.as_str()) == Some(t));
+        let video = of_type("video")?;
+        let audio = of_type("audio");
+
+        Some(Self {
+            video_codec: video.get("codec_name")?.as_str()?.to_string(),
+            width: video.get("width")?.as_i64()?,
+            height: video.get("height")?.as_i64()?,
+            fps: video
+                .get("r_frame_rate")
+                .and_then(|r| r.as_str())
+                .and_then(FFmpegProcessor::parse_frame_rate)
+                .unwrap_or(0.0),
+            audio_codec: audio.and_then(|a| a.get("codec_name")).and_then(|c| c.as_str()).map(String::from),
+            sample_rate: audio.and_then(|a| a.get("sample_rate")).and_then(|r| r.as_str()).and_then(|r| r.parse().ok()),
+            channels: audio.and_then(|a| a.get("channels")).and_then(|c| c.as_i64()),
+        })
+    }
+
+    fn matches(&self, other: &Self) -> bool {
+        self.video_codec == other.video_codec
+            && self.width == other.width
+            && self.height == other.height
+            && (self.fps - other.fps).abs() < 0.01
+            && self.audio_codec == other.audio_codec
+            && self.sample_rate == other.sample_rate
+            && self.channels == other.channels
+    }
+
+    fn video_encoder(&self) -> Result<&'static str, String> {
+        match self.video_codec.as_str() {
+            "h264" => Ok("libx264"),
+            "hevc" => Ok("libx265"),
+            "vp9" => Ok("libvpx-vp9"),
+            other => Err(format!("Codec video de la video de base non supporte pour l'ajout: {}", other)),
+        }
+    }
+
+    // Encoder arguments producing streams the concat demuxer can join to the base
+    fn encoder_args(&self) -> Result<Vec<String>, String> {
+        let encoder = self.video_encoder()?;
+        let quality: &[&str] = match encoder {
+            "libvpx-vp9" => &["-b:v", "0", "-crf", "30"],
+            _ => &["-preset", "medium", "-crf", "18"],
+        };
+        let mut args = vec!["-c:v".to_string(), encoder.to_string()];
+        args.extend(quality.iter().map(|a| a.to_string()));
+        args.extend(["-pix_fmt".to_string(), "yuv420p".to_string(), "-r".to_string(), self.fps.to_string()]);
+
+        let Some(ref codec) = self.audio_codec else {
+            return Ok(args);
+        };
+        let encoder = match codec.as_str() {
+            "aac" => "aac",
+            "opus" => "libopus",
+            "vorbis" => "libvorbis",
+            "mp3" => "libmp3lame",
+            "flac" => "flac",
+            "ac3" => "ac3",
+            other => return Err(format!("Codec audio de la video de base non supporte pour l'ajout: {}", other)),
+        };
+        args.extend(["-c:a".to_string(), encoder.to_string()]);
+        if let Some(rate) = self.sample_rate {
+            args.extend(["-ar".to_string(), rate.to_string()]);
+        }
+        if let Some(channels) = self.channels {
+            args.extend(["-ac".to_string(), channels.to_string()]);
+        }
+        Ok(args)
+    }
+}
+
+// Every clip is conformed to the base frame size and rate before the xfade chain
+fn build_tail_filter(clips: &[VideoClip], crossfade: f64, format: &StreamFormat) -> (String, String, Option<String>) {
+    let mut parts = Vec::new();
+    let has_audio = format.audio_codec.is_some();
+
+    for (i, clip) in clips.iter().enumerate() {
+        let range = match clip.end {
+            Some(end) => format!("start={}:end={}", clip.start, end),
+            None => format!("start={}", clip.start),
+        };
+        parts.push(format!(
+            "[{i}:v]trim={range},setpts=PTS-STARTPTS,scale={w}:{h}:force_original_aspect_ratio=decrease,pad={w}:{h}:(ow-iw)/2:(oh-ih)/2,fps={fps},format=yuv420p,setsar=1[v{i}]",
+            w = format.width,
+            h = format.height,
+            fps = format.fps,
+        ));
+        if has_audio {
+            parts.push(format!("[{i}:a]atrim={range},asetpts=PTS-STARTPTS[a{i}]"));
+        }
+    }
+
+    let durations = get_transition_durations(clips, crossfade);
+    let mut acc = clips[0].get_effective_duration();
+    let mut prev_v = "v0".to_string();
+    let mut prev_a = "a0".to_string();
+    for j in 1..clips.len() {
+        let d = durations[j - 1];
+        let (vo, ao) = (format!("vx{}", j), format!("ax{}", j));
+        if d > 0.0 {
+            parts.push(format!("[{}][v{}]xfade=transition=fade:duration={}:offset={}[{}]", prev_v, j, d, (acc - d).max(0.0), vo));
+            if has_audio {
+                parts.push(format!("[{}][a{}]acrossfade=d={}:c1=qsin:c2=qsin[{}]", prev_a, j, d, ao));
+            }
+        } else {
+            parts.push(format!("[{}][v{}]concat=n=2:v=1:a=0[{}]", prev_v, j, vo));
+            if has_audio {
+                parts.push(format!("[{}][a{}]concat=n=2:v=0:a=1[{}]", prev_a, j, ao));
+            }
+        }
+        prev_v = vo;
+        prev_a = ao;
+        acc += (clips[j].get_effective_duration() - d).max(0.0);
+    }
+
+    (parts.join(";"), format!("[{}]", prev_v), has_audio.then(|| format!("[{}]", prev_a)))
+}
+
+fn concat_list_line(path: &Path) -> String {
+    format!("file '{}'\n", path.to_string_lossy().replace('\'', "'\\''"))
+}
+
+fn sibling(base: &Path, suffix: &str) -> PathBuf {
+    let stem = base.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default();
+    let ext = base.extension().map(|e| e.to_string_lossy().to_string()).unwrap_or_else(|| "mkv".to_string());
+    base.with_file_name(format!(".{}_{}.{}", stem, suffix, ext))
+}
+
+// None when cancelled; a failed step aborts the whole append
+fn run_step(state: &State<'_, AppState>, cmd: &[String], output: &Path) -> Result<Option<ExitStatus>, String> {
+    let output = output.to_string_lossy();
+    match run_ffmpeg_quiet(&state.export_process, new_command(cmd, false), &output)? {
+        FfmpegOutcome::Finished(status) if status.success() => Ok(Some(status)),
+        FfmpegOutcome::Finished(status) => {
+            Err(format!("FFmpeg a termine avec le code {} ({})", status.code().unwrap_or(-1), output))
+        }
+        FfmpegOutcome::Cancelled { .. } => Ok(None),
+    }
+}
+
+// Appends clips to an already exported video, replacing it once everything succeeded.
+// Matching clips with no crossfade are stream-copied. Otherwise only the end of the
+// base (from the last keyframe before the crossfade) and the new clips are
+// re-encoded in the base's format, then joined to the untouched start of the base.
+#[tauri::command]
+pub async fn append_to_video(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    base_path: String,
+    clips: Vec<VideoClip>,
+    crossfade: f64,
+) -> Result<ExportResult, String> {
+    let start_time = Instant::now();
+    let _guard = ExportGuard::acquire(&state.is_exporting)?;
+    state.export_process.reset();
+
+    if clips.is_empty() {
+        return Err("Aucun clip a ajouter".to_string());
+    }
+    let base = Path::new(&base_path);
+    let format = StreamFormat::probe(&base_path).ok_or_else(|| format!("Video de base illisible: {}", base_path))?;
+    let base_duration = state.ffmpeg.lock().unwrap().get_duration(&base_path);
+    if base_duration <= 0.0 {
+        return Err(format!("Duree inconnue pour la video de base: {}", base_path));
+    }
+
+    let crossfade = crossfade.clamp(0.0, base_duration.min(clips[0].get_effective_duration()));
+    let copyable = crossfade <= 0.0
+        && clips.iter().all(|c| {
+            !c.is_trimmed() && !c.needs_filtering() && StreamFormat::probe(&c.path).is_some_and(|f| f.matches(&format))
+        });
+
+    let list_path = sibling(base, "append_list").with_extension("txt");
+    let tail_path = sibling(base, "append_tail");
+    let prefix_path = sibling(base, "append_prefix");
+    let joined_path = sibling(base, "append");
+    let mut list = String::new();
+    let mut temp_files = vec![list_path.clone()];
+    // The steps write hidden siblings of the base, which the command may replace.
+    // The base itself is replaced by design, whatever the overwrite policy.
+    let steps_flag = overwrite_flag(OverwritePolicy::Always, &joined_path.to_string_lossy());
+
+    let mut finished = (|| -> Result<Option<ExitStatus>, String> {
+        if copyable {
+            list.push_str(&concat_list_line(base));
+            for clip in &clips {
+                list.push_str(&concat_list_line(Path::new(&clip.path)));
+            }
+        } else {
+            // The base is copied up to a keyframe; what follows is re-encoded with the new clips
+            let cut = if crossfade > 0.0 {
+                FFmpegProcessor::probe_keyframe_before(&base_path, base_duration - crossfade).unwrap_or(0.0)
+            } else {
+                base_duration
+            };
+
+            let mut tail_clips = clips.clone();
+            if cut < base_duration {
+                let base_tail = VideoClip {
+                    path: base_path.clone(),
+                    duration: base_duration,
+                    start: cut,
+                    ..VideoClip::default()
+                };
+                tail_clips.insert(0, base_tail);
+                tail_clips[1].crossfade_in = Some(crossfade);
+            }
+
+            if cut >= base_duration {
+                list.push_str(&concat_list_line(base));
+            } else if cut > 0.0 {
+                let prefix_cmd: Vec<String> = ["ffmpeg", steps_flag, "-i", &base_path, "-t", &cut.to_string(), "-map", "0", "-c", "copy"]
+                    .iter()
+                    .map(|a| a.to_string())
+                    .chain([prefix_path.to_string_lossy().to_string()])
+                    .collect();
+                temp_files.push(prefix_path.clone());
+                if run_step(&state, &prefix_cmd, &prefix_path)?.is_none() {
+                    return Ok(None);
+                }
+                list.push_str(&concat_list_line(&prefix_path));
+            }
+            let _ = app.emit("export-progress", 20.0);
+
+            let (fc, tag_v, tag_a) = build_tail_filter(&tail_clips, crossfade, &format);
+            let mut tail_cmd = vec!["ffmpeg".to_string(), steps_flag.to_string()];
+            for clip in &tail_clips {
+                tail_cmd.extend(["-i".to_string(), clip.path.clone()]);
+            }
+            tail_cmd.extend(["-filter_complex".to_string(), fc, "-map".to_string(), tag_v]);
+            match tag_a {
+                Some(tag) => tail_cmd.extend(["-map".to_string(), tag]),
+                None => tail_cmd.push("-an".to_string()),
+            }
+            tail_cmd.extend(format.encoder_args()?);
+            tail_cmd.push(tail_path.to_string_lossy().to_string());
+            temp_files.push(tail_path.clone());
+            if run_step(&state, &tail_cmd, &tail_path)?.is_none() {
+                return Ok(None);
+            }
+            list.push_str(&concat_list_line(&tail_path));
+        }
+        let _ = app.emit("export-progress", 80.0);
+
+        fs::write(&list_path, &list).map_err(|e| format!("Impossible d'ecrire la liste de concatenation: {}", e))?;
+        let joined = joined_path.to_string_lossy().to_string();
+        let concat_cmd: Vec<String> = ["ffmpeg", steps_flag, "-f", "concat", "-safe", "0", "-i", &list_path.to_string_lossy(), "-map", "0", "-c", "copy", &joined]
+            .iter()
+            .map(|a| a.to_string())
+            .collect();
+        let Some(status) = run_step(&state, &concat_cmd, &joined_path)? else {
+            return Ok(None);
+        };
+        fs::rename(&joined_path, base).map_err(|e| format!("Impossible de remplacer {}: {}", base_path, e))?;
+        let _ = app.emit("export-progress", 100.0);
+        Ok(Some(status))
+    })();
+
+    // The steps of a failed append are kept for inspection when asked to
+    if finished.is_err() && crate::support::keeps_failed() {
+        let mut kept = crate::support::take_kept_files();
+        kept.extend(temp_files.iter().chain([&joined_path]).filter(|f| f.exists()).map(|f| f.to_string_lossy().to_string()));
+        finished = finished.map_err(|e| format!("{} (fichiers conserves: {})", e, kept.join(", ")));
+    } else {
+        for file in &temp_files {
+            let _ = fs::remove_file(file);
+        }
+        if finished.is_err() {
+            let _ = fs::remove_file(&joined_path);
+        }
+    }
+
+    let encoder = if copyable { "copy" } else { format.video_encoder()? };
+    Ok(match finished? {
+        Some(status) => {
+            let mut result = ExportResult::finished(status, encoder, false, start_time);
+            result.output_path = Some(base_path);
+            result
+        }
+        None => ExportResult::cancelled(encoder, false, start_time, false),
+    })
+}
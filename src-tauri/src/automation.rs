@@ -0,0 +1,94 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::models::{Project, VolumeKeyframe};
+
+// Optional `<project>.automation.json` next to a project file:
+// {
+//   "tracks": [ { "track": 0, "keyframes": [ { "time": 0.0, "volume": 0.2 }, { "time": 4.0, "volume": 1.0 } ] } ],
+//   "transitions": [ { "boundary": 0, "duration": 2.5 } ]
+// }
+// Track indices refer to `audio_tracks`; boundary N is the cut between videos N and N+1.
+// When both files describe the same track or boundary, the sidecar wins.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Automation {
+    #[serde(default)]
+    pub tracks: Vec<TrackAutomation>,
+    #[serde(default)]
+    pub transitions: Vec<TransitionAutomation>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TrackAutomation {
+    pub track: usize,
+    #[serde(default)]
+    pub keyframes: Vec<VolumeKeyframe>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TransitionAutomation {
+    pub boundary: usize,
+    pub duration: f64,
+}
+
+pub fn get_sidecar_path(project_path: &str) -> PathBuf {
+    Path::new(project_path).with_extension("automation.json")
+}
+
+pub fn load_sidecar(project_path: &str) -> Result<Option<Automation>, String> {
+    let path = get_sidecar_path(project_path);
+    if !path.exists() {
+        return Ok(None);
+    }
+
+    let content = fs::read_to_string(&path).map_err(|e| format!("Impossible de lire l'automatisation: {}", e))?;
+    serde_json::from_str(&content)
+        .map(Some)
+        .map_err(|e| format!("Format d'automatisation invalide: {}", e))
+}
+
+impl Automation {
+    // Entries pointing past the end of the project are ignored
+    pub fn apply_to(&self, project: &mut Project) {
+        for automation in &self.tracks {
+            if let Some(track) = project.audio_tracks.get_mut(automation.track) {
+                track.volume_keyframes = automation.keyframes.clone();
+            }
+        }
+
+        for transition in &self.transitions {
+            if let Some(clip) = project.videos.get_mut(transition.boundary + 1) {
+                clip.crossfade_in = Some(transition.duration.max(0.0));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{AudioTrack, VideoClip};
+
+    #[test]
+    fn sidecar_entries_override_the_project() {
+        let automation: Automation = serde_json::from_str(
+            r#"{
+                "tracks": [ { "track": 0, "keyframes": [ { "time": 0.0, "volume": 0.2 } ] }, { "track": 5 } ],
+                "transitions": [ { "boundary": 0, "duration": 2.5 }, { "boundary": 1, "duration": -1.0 }, { "boundary": 9, "duration": 1.0 } ]
+            }"#,
+        )
+        .unwrap();
+        let mut project = Project {
+            videos: vec![VideoClip::default(), VideoClip::default(), VideoClip::default()],
+            audio_tracks: vec![AudioTrack::default()],
+            ..Project::default()
+        };
+        automation.apply_to(&mut project);
+
+        assert_eq!(project.audio_tracks[0].volume_keyframes.len(), 1);
+        let crossfades: Vec<Option<f64>> = project.videos.iter().map(|v| v.crossfade_in).collect();
+        assert_eq!(crossfades, vec![None, Some(2.5), Some(0.0)]);
+        assert_eq!(get_sidecar_path("/p/show.vmproj"), PathBuf::from("/p/show.automation.json"));
+    }
+}
@@ -0,0 +1,180 @@
+use std::fs;
+use std::path::Path;
+
+use crate::ffmpeg::{is_pipe_output, FFmpegProcessor};
+use crate::models::Project;
+
+// Bits per pixel per frame of x264 at CRF 20 on typical footage, on the high side
+const CRF_BITS_PER_PIXEL: f64 = 0.1;
+const LOSSLESS_AUDIO_BITRATE: f64 = 1_000_000.0;
+// The estimate is rough, the muxer adds overhead, and a full disk is worse than a refusal
+const SAFETY_FACTOR: f64 = 1.25;
+const SAFETY_MARGIN_BYTES: u64 = 50 * 1024 * 1024;
+
+// "8M", "192k" or plain bits per second
+fn parse_bitrate(value: &str) -> Option<f64> {
+    let value = value.trim();
+    let (number, multiplier) = match value.chars().last()? {
+        'k' | 'K' => (&value[..value.len() - 1], 1e3),
+        'm' | 'M' => (&value[..value.len() - 1], 1e6),
+        'g' | 'G' => (&value[..value.len() - 1], 1e9),
+        _ => (value, 1.0),
+    };
+    number.parse::<f64>().ok().filter(|n| *n > 0.0).map(|n| n * multiplier)
+}
+
+// Bytes per second of video. A lone unfiltered clip is probably stream-copied,
+// so its own rate is used; otherwise the target bitrate or a CRF guess from the frame.
+fn video_rate(project: &Project) -> f64 {
+    let settings = &project.settings;
+    if let Some(bitrate) = settings.video_bitrate.as_deref().and_then(parse_bitrate) {
+        return bitrate / 8.0;
+    }
+
+    let clips = project.get_enabled_clips().1;
+    let first = clips.first();
+    if let [clip] = clips.as_slice() {
+        if !clip.needs_filtering() && clip.duration > 0.0 {
+            if let Ok(meta) = fs::metadata(&clip.path) {
+                return meta.len() as f64 / clip.duration;
+            }
+        }
+    }
+
+    let props = first.and_then(|clip| FFmpegProcessor::probe_video_properties(&clip.path));
+    let (w, h) = settings
+        .get_output_resolution()
+        .or_else(|| props.as_ref().map(|p| p.frame_size(settings.auto_rotate)))
+        .unwrap_or((1920, 1080));
+    let fps = settings
+        .get_output_fps()
+        .or_else(|| props.map(|p| p.fps).filter(|f| *f > 0.0))
+        .unwrap_or(30.0);
+    w as f64 * h as f64 * fps * CRF_BITS_PER_PIXEL / 8.0
+}
+
+fn audio_rate(project: &Project) -> f64 {
+    let settings = &project.settings;
+    if settings.mute_all {
+        return 0.0;
+    }
+    let bitrate = match settings.get_audio_bitrate() {
+        Some(bitrate) => parse_bitrate(bitrate).unwrap_or(192_000.0),
+        None => LOSSLESS_AUDIO_BITRATE,
+    };
+    let streams = if settings.separate_audio_stems { 2.0 } else { 1.0 };
+    bitrate * streams / 8.0
+}
+
+// Expected size of the exported file in bytes, without the safety margin
+pub fn estimate_output_bytes(project: &Project) -> u64 {
+    let duration = project.get_export_duration().max(0.0);
+    ((video_rate(project) + audio_rate(project)) * duration) as u64
+}
+
+#[cfg(unix)]
+fn available_space(dir: &Path) -> Option<u64> {
+    use std::os::unix::ffi::OsStrExt;
+    let path = std::ffi::CString::new(dir.as_os_str().as_bytes()).ok()?;
+    let mut stat: libc::statvfs = unsafe { std::mem::zeroed() };
+    // SAFETY: `path` is NUL-terminated and `stat` is a valid out-pointer
+    if unsafe { libc::statvfs(path.as_ptr(), &mut stat) } != 0 {
+        return None;
+    }
+    Some(stat.f_bavail as u64 * stat.f_frsize as u64)
+}
+
+#[cfg(windows)]
+fn available_space(dir: &Path) -> Option<u64> {
+    use std::os::windows::ffi::OsStrExt;
+    #[link(name = "kernel32")]
+    extern "system" {
+        fn GetDiskFreeSpaceExW(dir: *const u16, free_to_caller: *mut u64, total: *mut u64, total_free: *mut u64) -> i32;
+    }
+
+    let wide: Vec<u16> = dir.as_os_str().encode_wide().chain(std::iter::once(0)).collect();
+    let mut free = 0u64;
+    // SAFETY: `wide` is NUL-terminated; the unused totals may be null
+    let ok = unsafe { GetDiskFreeSpaceExW(wide.as_ptr(), &mut free, std::ptr::null_mut(), std::ptr::null_mut()) };
+    (ok != 0).then_some(free)
+}
+
+#[cfg(not(any(unix, windows)))]
+fn available_space(_dir: &Path) -> Option<u64> {
+    None
+}
+
+fn format_bytes(bytes: u64) -> String {
+    const GB: f64 = 1024.0 * 1024.0 * 1024.0;
+    const MB: f64 = 1024.0 * 1024.0;
+    if bytes as f64 >= GB {
+        format!("{:.1} Go", bytes as f64 / GB)
+    } else {
+        format!("{:.0} Mo", bytes as f64 / MB)
+    }
+}
+
+// Refuses an export that won't fit on the output volume. Pipes, and volumes
+// whose free space can't be read, aren't checked. A file being replaced counts
+// as free space since ffmpeg truncates it first.
+pub fn check_disk_space(project: &Project, output_path: &str) -> Result<(), String> {
+    if is_pipe_output(output_path) {
+        return Ok(());
+    }
+    let output = Path::new(output_path);
+    let dir = match output.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    let Some(free) = available_space(dir) else {
+        return Ok(());
+    };
+
+    let available = free + fs::metadata(output).map(|m| m.len()).unwrap_or(0);
+    let needed = (estimate_output_bytes(project) as f64 * SAFETY_FACTOR) as u64 + SAFETY_MARGIN_BYTES;
+    if needed > available {
+        return Err(format!(
+            "Espace disque insuffisant: environ {} necessaires, {} disponibles",
+            format_bytes(needed),
+            format_bytes(available)
+        ));
+    }
+    Ok(())
+}
+
+// Tauri commands
+
+#[tauri::command]
+pub fn estimate_output_size(project: Project) -> u64 {
+    estimate_output_bytes(&project.exported_project())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::clip_at;
+
+    #[test]
+    fn bitrates_and_sizes_are_estimated_from_the_settings() {
+        assert_eq!(parse_bitrate("8M"), Some(8e6));
+        assert_eq!(parse_bitrate(" 192k "), Some(192e3));
+        assert_eq!(parse_bitrate("1500000"), Some(1.5e6));
+        assert_eq!(parse_bitrate("fast"), None);
+        assert_eq!(parse_bitrate("-2M"), None);
+
+        // 10 s at 8 Mb/s of video and 192 kb/s of audio
+        let mut project = Project {
+            videos: vec![clip_at("missing.mp4", 10.0)],
+            ..Project::default()
+        };
+        project.settings.video_bitrate = Some("8M".to_string());
+        project.settings.audio_bitrate = Some("192k".to_string());
+        assert_eq!(estimate_output_bytes(&project), 10_240_000);
+        project.settings.mute_all = true;
+        assert_eq!(estimate_output_bytes(&project), 10_000_000);
+
+        assert_eq!(format_bytes(3 * 1024 * 1024), "3 Mo");
+        assert_eq!(format_bytes(1536 * 1024 * 1024), "1.5 Go");
+        assert_eq!(check_disk_space(&project, "pipe:1"), Ok(()));
+    }
+}
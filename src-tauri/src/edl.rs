@@ -0,0 +1,117 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+use tauri::State;
+
+use crate::models::{Project, VideoClip};
+use crate::AppState;
+
+// Simple JSON cut list:
+// { "clips": [ { "path": "/media/a.mp4", "in": 2.5, "out": 10.0, "order": 1 } ] }
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EdlEntry {
+    pub path: String,
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(rename = "in", default)]
+    pub in_point: f64,
+    #[serde(rename = "out", default)]
+    pub out_point: Option<f64>,
+    #[serde(default)]
+    pub order: Option<i32>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Edl {
+    #[serde(default)]
+    pub clips: Vec<EdlEntry>,
+}
+
+fn file_name(path: &str) -> String {
+    Path::new(path)
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_else(|| path.to_string())
+}
+
+impl Edl {
+    pub fn missing_files(&self) -> Vec<String> {
+        self.clips
+            .iter()
+            .filter(|c| !Path::new(&c.path).exists())
+            .map(|c| c.path.clone())
+            .collect()
+    }
+
+    // Entries without an explicit order keep their position in the file
+    pub fn ordered_entries(&self) -> Vec<&EdlEntry> {
+        let mut entries: Vec<(usize, &EdlEntry)> = self.clips.iter().enumerate().collect();
+        entries.sort_by_key(|(i, c)| (c.order.unwrap_or(*i as i32), *i));
+        entries.into_iter().map(|(_, c)| c).collect()
+    }
+}
+
+// Tauri commands
+
+#[tauri::command]
+pub fn import_edl(state: State<'_, AppState>, path: String) -> Result<Project, String> {
+    let content = fs::read_to_string(&path).map_err(|e| format!("Impossible de lire l'EDL: {}", e))?;
+    let edl: Edl = serde_json::from_str(&content).map_err(|e| format!("Format d'EDL invalide: {}", e))?;
+
+    let missing = edl.missing_files();
+    if !missing.is_empty() {
+        return Err(format!("Fichiers introuvables: {}", missing.join(", ")));
+    }
+
+    let mut ffmpeg = state.ffmpeg.lock().unwrap();
+    let videos = edl
+        .ordered_entries()
+        .into_iter()
+        .map(|entry| {
+            if let Some(out) = entry.out_point {
+                if out <= entry.in_point {
+                    return Err(format!("Point de sortie invalide pour {}", entry.path));
+                }
+            }
+            Ok(VideoClip {
+                path: entry.path.clone(),
+                name: entry.name.clone().unwrap_or_else(|| file_name(&entry.path)),
+                duration: ffmpeg.get_duration(&entry.path),
+                start: entry.in_point.max(0.0),
+                end: entry.out_point,
+                ..VideoClip::default()
+            })
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+
+    Ok(Project {
+        videos,
+        ..Project::default()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn entries_are_sorted_by_order_then_position() {
+        let edl: Edl = serde_json::from_str(
+            r#"{ "clips": [
+                { "path": "/a.mp4", "in": 2.5, "out": 10.0, "order": 3 },
+                { "path": "/b.mp4" },
+                { "path": "/c.mp4", "order": 0 }
+            ] }"#,
+        )
+        .unwrap();
+        assert_eq!(edl.clips[0].in_point, 2.5);
+        assert_eq!(edl.clips[0].out_point, Some(10.0));
+        assert_eq!(edl.clips[1].in_point, 0.0);
+
+        // b has no order and stays at position 1, between c (0) and a (3)
+        let paths: Vec<&str> = edl.ordered_entries().iter().map(|c| c.path.as_str()).collect();
+        assert_eq!(paths, ["/c.mp4", "/b.mp4", "/a.mp4"]);
+        assert_eq!(edl.missing_files().len(), 3);
+        assert_eq!(file_name("/media/clip 1.mp4"), "clip 1.mp4");
+    }
+}
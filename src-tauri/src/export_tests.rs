@@ -0,0 +1,222 @@
+// End-to-end checks of the generated filter graphs: small testsrc/sine files are
+// rendered with lavfi, then the real export command is run on them. Skipped when
+// ffmpeg isn't installed.
+
+use std::path::Path;
+use std::process::Command;
+
+use crate::ffmpeg::{build_export_args, CrossfadeSupport};
+use crate::fixtures::work_dir;
+use crate::models::{
+    get_timeline_markers, AudioTrack, Composition, CompositionLayout, OverlayPosition, OverwritePolicy, Project, TransitionAlign,
+    TrimAccuracy, VideoClip,
+};
+
+fn ffmpeg_available() -> bool {
+    ["ffmpeg", "ffprobe"]
+        .iter()
+        .all(|tool| Command::new(tool).arg("-version").output().is_ok_and(|o| o.status.success()))
+}
+
+fn run(args: &[String]) {
+    let output = Command::new(&args[0]).args(&args[1..]).output().unwrap();
+    assert!(
+        output.status.success(),
+        "{} failed:\n{}",
+        args.join(" "),
+        String::from_utf8_lossy(&output.stderr)
+    );
+}
+
+fn to_args(args: &[&str]) -> Vec<String> {
+    args.iter().map(|a| a.to_string()).collect()
+}
+
+fn synth_video(dir: &Path, name: &str, seconds: f64, size: &str) -> VideoClip {
+    let path = dir.join(name).to_string_lossy().to_string();
+    let d = seconds.to_string();
+    run(&to_args(&[
+        "ffmpeg", "-y", "-v", "error",
+        "-f", "lavfi", "-i", &format!("testsrc=size={}:rate=25:duration={}", size, d),
+        "-f", "lavfi", "-i", &format!("sine=frequency=440:duration={}", d),
+        "-c:v", "libx264", "-preset", "ultrafast", "-pix_fmt", "yuv420p", "-c:a", "aac", "-shortest", &path,
+    ]));
+    VideoClip {
+        path,
+        name: name.to_string(),
+        duration: seconds,
+        ..VideoClip::default()
+    }
+}
+
+fn synth_audio(dir: &Path, name: &str, seconds: f64, frequency: i32) -> AudioTrack {
+    let path = dir.join(name).to_string_lossy().to_string();
+    run(&to_args(&[
+        "ffmpeg", "-y", "-v", "error",
+        "-f", "lavfi", "-i", &format!("sine=frequency={}:duration={}", frequency, seconds),
+        "-c:a", "aac", &path,
+    ]));
+    AudioTrack { path, name: name.to_string(), duration: seconds, ..AudioTrack::default() }
+}
+
+fn export(project: &Project, dir: &Path, name: &str) -> String {
+    let output = dir.join(name).to_string_lossy().to_string();
+    let mut cmd = build_export_args(project, output.clone(), None, None, "ultrafast".to_string(), CrossfadeSupport::default(), OverwritePolicy::Always);
+    cmd.insert(1, "-v".to_string());
+    cmd.insert(2, "error".to_string());
+    run(&cmd);
+    output
+}
+
+fn probe(path: &str, entries: &str) -> String {
+    let output = Command::new("ffprobe")
+        .args(["-v", "error", "-show_entries", entries, "-of", "default=noprint_wrappers=1:nokey=1", path])
+        .output()
+        .unwrap();
+    String::from_utf8_lossy(&output.stdout).trim().to_string()
+}
+
+fn duration(path: &str) -> f64 {
+    probe(path, "format=duration").parse().unwrap()
+}
+
+fn audio_stream_count(path: &str) -> usize {
+    probe(path, "stream=codec_type").lines().filter(|l| *l == "audio").count()
+}
+
+fn assert_near(actual: f64, expected: f64) {
+    assert!((actual - expected).abs() < 0.3, "duration {} instead of {}", actual, expected);
+}
+
+#[test]
+fn crossfaded_clips_mixed_with_music() {
+    if !ffmpeg_available() {
+        return;
+    }
+    let dir = work_dir("crossfade");
+    let mut project = Project {
+        videos: vec![synth_video(&dir, "a.mp4", 3.0, "320x240"), synth_video(&dir, "b.mp4", 3.0, "320x240")],
+        audio_tracks: vec![synth_audio(&dir, "m1.m4a", 4.0, 220), synth_audio(&dir, "m2.m4a", 4.0, 330)],
+        ..Project::default()
+    };
+    project.settings.video_crossfade = 1.0;
+    project.settings.audio_crossfade = 1.0;
+
+    let output = export(&project, &dir, "out.mp4");
+    assert_near(duration(&output), 5.0);
+    assert_eq!(audio_stream_count(&output), 1);
+}
+
+#[test]
+fn trimmed_clip_with_both_trim_modes() {
+    if !ffmpeg_available() {
+        return;
+    }
+    let dir = work_dir("trim");
+    let mut clip = synth_video(&dir, "a.mp4", 4.0, "320x240");
+    clip.start = 1.0;
+    clip.end = Some(3.0);
+    let mut project = Project { videos: vec![clip], ..Project::default() };
+
+    assert_near(duration(&export(&project, &dir, "accurate.mp4")), 2.0);
+    project.settings.trim_accuracy = TrimAccuracy::Fast;
+    assert_near(duration(&export(&project, &dir, "fast.mp4")), 2.0);
+}
+
+#[test]
+fn mixed_sizes_and_silent_export() {
+    if !ffmpeg_available() {
+        return;
+    }
+    let dir = work_dir("mixed");
+    let mut project = Project {
+        videos: vec![synth_video(&dir, "wide.mp4", 2.0, "320x240"), synth_video(&dir, "tall.mp4", 2.0, "240x320")],
+        ..Project::default()
+    };
+    project.settings.mute_all = true;
+    project.settings.video_fade_in = 0.5;
+    project.settings.video_fade_out = 0.5;
+
+    let output = export(&project, &dir, "out.mp4");
+    assert_eq!(audio_stream_count(&output), 0);
+    assert_eq!(probe(&output, "stream=width,height"), "320\n240");
+}
+
+#[test]
+fn picture_in_picture_composition() {
+    if !ffmpeg_available() {
+        return;
+    }
+    let dir = work_dir("pip");
+    let project = Project {
+        composition: Some(Composition {
+            layout: CompositionLayout::Pip { position: OverlayPosition::TopRight, scale: 0.3 },
+            clips: vec![synth_video(&dir, "main.mp4", 2.0, "320x240"), synth_video(&dir, "inset.mp4", 2.0, "320x240")],
+        }),
+        ..Project::default()
+    };
+
+    assert_near(duration(&export(&project, &dir, "out.mp4")), 2.0);
+}
+
+#[test]
+fn short_clip_between_long_ones() {
+    if !ffmpeg_available() {
+        return;
+    }
+    let dir = work_dir("short_clip");
+    let mut project = Project {
+        videos: vec![
+            synth_video(&dir, "a.mp4", 3.0, "320x240"),
+            synth_video(&dir, "short.mp4", 0.8, "320x240"),
+            synth_video(&dir, "b.mp4", 3.0, "320x240"),
+        ],
+        ..Project::default()
+    };
+    project.settings.video_crossfade = 1.0;
+
+    assert_near(duration(&export(&project, &dir, "out.mp4")), 6.0);
+}
+
+#[test]
+fn centered_transitions_keep_the_cuts_in_place() {
+    if !ffmpeg_available() {
+        return;
+    }
+    let dir = work_dir("centered");
+    let mut project = Project {
+        videos: vec![synth_video(&dir, "a.mp4", 3.0, "320x240"), synth_video(&dir, "b.mp4", 3.0, "320x240")],
+        ..Project::default()
+    };
+    project.settings.video_crossfade = 1.0;
+    project.settings.transition_align = TransitionAlign::Centered;
+
+    let markers = get_timeline_markers(&project.videos, 1.0, TransitionAlign::Centered);
+    assert_eq!(markers[0].crossfade_start, Some(2.5));
+    assert_near(duration(&export(&project, &dir, "out.mp4")), 6.0);
+}
+
+const IDENTITY_LUT: &str = "LUT_3D_SIZE 2\n0 0 0\n1 0 0\n0 1 0\n1 1 0\n0 0 1\n1 0 1\n0 1 1\n1 1 1\n";
+
+#[test]
+fn pathological_file_names() {
+    if !ffmpeg_available() {
+        return;
+    }
+    let dir = work_dir("odd_names");
+    let lut = dir.join("it's [a], b;c.cube");
+    std::fs::write(&lut, IDENTITY_LUT).unwrap();
+    let mut project = Project {
+        videos: vec![
+            synth_video(&dir, "clip [1], it's.mp4", 2.0, "320x240"),
+            synth_video(&dir, "clip;2 'b'.mp4", 2.0, "320x240"),
+        ],
+        audio_tracks: vec![synth_audio(&dir, "music [x], 'y'.m4a", 3.0, 220)],
+        ..Project::default()
+    };
+    project.settings.video_crossfade = 0.5;
+    project.settings.lut = Some(lut.to_string_lossy().to_string());
+
+    assert_near(duration(&export(&project, &dir, "out [final], it's.mp4")), 3.5);
+}
+
@@ -1,19 +1,30 @@
 use regex::Regex;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use serde_json::json;
+use std::borrow::Borrow;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, VecDeque};
 use std::fs;
+use std::hash::{Hash, Hasher};
+use std::io::{Read, Seek, SeekFrom};
 use std::path::Path;
 use std::process::{Command, Stdio};
-use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Mutex;
 use std::time::Instant;
 use tauri::{AppHandle, Emitter, State};
 
-use crate::models::{AudioTrack, Project, VideoClip};
+use crate::disk::check_disk_space;
+use crate::models::{
+    get_temp_dir, get_timeline_duration, get_timeline_markers, get_transition_durations, get_transition_pads, AudioTrack,
+    ColorRange, CompositionLayout, InterpMode, OverwritePolicy, Project, ProjectSettings, TransitionAlign, TrimAccuracy, VideoClip, VolumeKeyframe,
+};
+use crate::monitor::UtilizationSampler;
+use crate::naming::{apply_overwrite_policy, overwrite_flag, resolve_output_path};
+use crate::process::{new_command, CancelMode, ChildSlot, ExportGuard};
+use crate::support;
 use crate::AppState;
 
-static CANCEL_FLAG: AtomicBool = AtomicBool::new(false);
-
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct GpuInfo {
     pub available: bool,
@@ -29,6 +40,196 @@ pub struct ExportResult {
     pub encoder: Option<String>,
     pub gpu_accelerated: bool,
     pub duration_seconds: f64,
+    #[serde(default)]
+    pub verification_errors: Option<Vec<String>>,
+    // Cancelled with `CancelMode::KeepPartial`: the truncated output was kept
+    #[serde(default)]
+    pub partial: bool,
+    // Where the file was written, after expanding the filename template
+    #[serde(default)]
+    pub output_path: Option<String>,
+    // What had to be dropped to render at all, e.g. crossfades on an old ffmpeg
+    #[serde(default)]
+    pub warnings: Vec<String>,
+    // Left on disk after a failure with `Config::keep_temp_on_error`
+    #[serde(default)]
+    pub kept_files: Vec<String>,
+}
+
+impl ExportResult {
+    pub(crate) fn cancelled(encoder: &str, gpu_accelerated: bool, start_time: Instant, partial: bool) -> Self {
+        Self {
+            success: false,
+            cancelled: true,
+            error: None,
+            encoder: Some(encoder.to_string()),
+            gpu_accelerated,
+            duration_seconds: start_time.elapsed().as_secs_f64(),
+            verification_errors: None,
+            partial,
+            output_path: None,
+            warnings: Vec::new(),
+            kept_files: Vec::new(),
+        }
+    }
+
+    pub(crate) fn finished(status: std::process::ExitStatus, encoder: &str, gpu_accelerated: bool, start_time: Instant) -> Self {
+        Self {
+            success: status.success(),
+            cancelled: false,
+            error: if status.success() { None } else { Some(format!("FFmpeg a termine avec le code {}", status.code().unwrap_or(-1))) },
+            encoder: Some(encoder.to_string()),
+            gpu_accelerated,
+            duration_seconds: start_time.elapsed().as_secs_f64(),
+            verification_errors: None,
+            partial: false,
+            output_path: None,
+            warnings: Vec::new(),
+            kept_files: Vec::new(),
+        }
+    }
+
+    // A run that couldn't start, e.g. a batch item with an unusable output path
+    pub(crate) fn failed(error: String, start_time: Instant) -> Self {
+        Self {
+            success: false,
+            cancelled: false,
+            error: Some(error),
+            encoder: None,
+            gpu_accelerated: false,
+            duration_seconds: start_time.elapsed().as_secs_f64(),
+            verification_errors: None,
+            partial: false,
+            output_path: None,
+            warnings: Vec::new(),
+            kept_files: Vec::new(),
+        }
+    }
+
+    // What a failed run left behind, including the truncated output
+    pub(crate) fn keep_failed_files(&mut self, output_path: &str) {
+        if self.success || self.cancelled || !support::keeps_failed() {
+            return;
+        }
+        self.kept_files = support::take_kept_files();
+        if Path::new(output_path).exists() && !is_pipe_output(output_path) {
+            self.kept_files.push(output_path.to_string());
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct VideoProperties {
+    pub width: i32,
+    pub height: i32,
+    pub fps: f64,
+    #[serde(default)]
+    pub field_order: Option<String>,
+    // Clockwise degrees the frames are turned on display (0, 90, 180 or 270)
+    #[serde(default)]
+    pub rotation: i32,
+    // Pixel aspect ratio as "num:den", None when the stream doesn't say
+    #[serde(default)]
+    pub sample_aspect_ratio: Option<String>,
+    // Codec of the first audio stream, None without audio
+    #[serde(default)]
+    pub audio_codec: Option<String>,
+    // Luma range of the video stream, None when the file doesn't say
+    #[serde(default)]
+    pub color_range: Option<ColorRange>,
+}
+
+impl VideoProperties {
+    // "progressive" and "unknown" both count as not interlaced
+    pub fn is_interlaced(&self) -> bool {
+        matches!(self.field_order.as_deref(), Some("tt" | "bb" | "tb" | "bt"))
+    }
+
+    // Width / height of one pixel, 1.0 when unknown ("0:1")
+    pub fn get_sar(&self) -> f64 {
+        self.sample_aspect_ratio
+            .as_deref()
+            .and_then(|r| FFmpegProcessor::parse_frame_rate(&r.replace(':', "/")))
+            .filter(|r| *r > 0.0)
+            .unwrap_or(1.0)
+    }
+
+    // DVD and some camera footage: non-square pixels, stretched on display
+    pub fn is_anamorphic(&self) -> bool {
+        (self.get_sar() - 1.0).abs() > 0.01
+    }
+
+    // Displayed size with square pixels, after autorotation if enabled
+    pub fn frame_size(&self, auto_rotate: bool) -> (i32, i32) {
+        let width = (self.width as f64 * self.get_sar()).round() as i32 / 2 * 2;
+        if auto_rotate && self.rotation % 180 != 0 {
+            (self.height, width)
+        } else {
+            (width, self.height)
+        }
+    }
+}
+
+// What an export or a validation reads from one file. `needs_deep_probe` is set
+// when ffprobe, with its default probe window, finds no stream or no duration:
+// such files get DEEP_PROBE_ARGS, on export too.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct ClipProbe {
+    pub properties: Option<VideoProperties>,
+    // Channel count of the first audio stream, None without audio
+    pub audio_channels: Option<i64>,
+    pub needs_deep_probe: bool,
+}
+
+// The probes of every file of a project, keyed by path so each is probed once.
+// Paths that weren't probed read as missing.
+#[derive(Debug, Default)]
+pub(crate) struct ClipProbes(HashMap<String, ClipProbe>);
+
+impl ClipProbes {
+    pub(crate) fn of<'a>(paths: impl IntoIterator<Item = &'a str>) -> Self {
+        let mut probes = HashMap::new();
+        for path in paths {
+            probes.entry(path.to_string()).or_insert_with(|| FFmpegProcessor::probe_clip(path));
+        }
+        ClipProbes(probes)
+    }
+
+    pub(crate) fn properties(&self, path: &str) -> Option<&VideoProperties> {
+        self.0.get(path).and_then(|p| p.properties.as_ref())
+    }
+
+    pub(crate) fn audio_channels(&self, path: &str) -> Option<i64> {
+        self.0.get(path).and_then(|p| p.audio_channels)
+    }
+
+    pub(crate) fn needs_deep_probe(&self, path: &str) -> bool {
+        self.0.get(path).is_some_and(|p| p.needs_deep_probe)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EncoderInfo {
+    pub name: String,
+    // Codec the encoder produces, e.g. "h264" for libx264 and h264_nvenc
+    pub codec: String,
+    // "video", "audio" or "subtitle"
+    pub kind: String,
+    pub hardware: bool,
+}
+
+// Name suffixes of ffmpeg's hardware encoder wrappers
+const HARDWARE_ENCODER_SUFFIXES: [&str; 10] = [
+    "_nvenc", "_qsv", "_amf", "_vaapi", "_v4l2m2m", "_videotoolbox", "_mf", "_vulkan", "_omx", "_mediacodec",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MediaKind {
+    Video,
+    Audio,
+    Image,
+    Unknown,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -38,19 +239,222 @@ pub struct Dependencies {
     pub has_ffplay: bool,
 }
 
+// Crossfade filters of the installed ffmpeg; missing ones degrade to hard cuts
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct CrossfadeSupport {
+    pub xfade: bool,
+    pub acrossfade: bool,
+}
+
+impl Default for CrossfadeSupport {
+    fn default() -> Self {
+        Self { xfade: true, acrossfade: true }
+    }
+}
+
+impl CrossfadeSupport {
+    // The clips' own audio is crossfaded along with the video, so both filters are needed
+    fn video_hard_cuts(&self, with_audio: bool) -> bool {
+        !self.xfade || (with_audio && !self.acrossfade)
+    }
+
+    // Zeroes the crossfades this build can't render and says what was dropped
+    pub(crate) fn degrade(&self, project: &mut Project) -> Vec<String> {
+        let mut warnings = Vec::new();
+        let with_audio = !project.settings.mute_all;
+        let video_crossfades = project.composition.is_none()
+            && get_transition_durations(&project.get_enabled_clips().1, project.settings.video_crossfade).iter().any(|d| *d > 0.0);
+        if self.video_hard_cuts(with_audio) && video_crossfades {
+            project.settings.video_crossfade = 0.0;
+            for clip in &mut project.videos {
+                clip.crossfade_in = None;
+            }
+            let missing = if self.xfade { "acrossfade" } else { "xfade" };
+            warnings.push(format!("Cette version de ffmpeg n'a pas le filtre {}: les clips sont enchaines sans fondu", missing));
+        }
+        if !self.acrossfade && project.get_active_tracks().len() > 1 {
+            project.settings.audio_crossfade = 0.0;
+            warnings.push("Cette version de ffmpeg n'a pas le filtre acrossfade: les musiques sont enchainees sans fondu".to_string());
+        }
+        warnings
+    }
+}
+
 pub struct FFmpegProcessor {
     duration_cache: HashMap<String, f64>,
     available_gpu_encoder: Option<String>,
     gpu_checked: bool,
+    transitions: Option<Vec<String>>,
+    encoders: Option<Vec<EncoderInfo>>,
+    has_acrossfade: Option<bool>,
+    pub debug_logging: bool,
+    pub packet_count_fallback: bool,
+    probe_log: VecDeque<ProbeLogEntry>,
+}
+
+const PROBE_LOG_CAPACITY: usize = 200;
+const CONTENT_HASH_SAMPLE: u64 = 1024 * 1024;
+// Larger probe window for files whose streams or duration start late
+const DEEP_PROBE_ARGS: [&str; 4] = ["-analyzeduration", "100M", "-probesize", "100M"];
+const KEYFRAME_SEARCH_WINDOW: f64 = 30.0;
+// A keyframe this close after the requested start still counts as "at" it
+const KEYFRAME_TOLERANCE: f64 = 0.001;
+
+// What each duration probe returned for one file; only collected with `debug_logging`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProbeLogEntry {
+    pub path: String,
+    // "quick", "json", "deep", "packets" or None when every method failed
+    pub method: Option<String>,
+    pub quick: Option<f64>,
+    pub json: Option<f64>,
+    // The json probe again with DEEP_PROBE_ARGS, when both came back empty
+    pub deep: Option<f64>,
+    // Only tried when every metadata probe came back empty
+    pub packets: Option<f64>,
+    pub duration: f64,
 }
 
+// xfade transitions of ffmpeg 4.3, used when the help output can't be parsed
+const FALLBACK_TRANSITIONS: [&str; 34] = [
+    "fade", "wipeleft", "wiperight", "wipeup", "wipedown", "slideleft", "slideright", "slideup",
+    "slidedown", "circlecrop", "rectcrop", "distance", "fadeblack", "fadewhite", "radial",
+    "smoothleft", "smoothright", "smoothup", "smoothdown", "circleopen", "circleclose",
+    "vertopen", "vertclose", "horzopen", "horzclose", "dissolve", "pixelize", "diagtl",
+    "diagtr", "diagbl", "diagbr", "hlslice", "hrslice", "vuslice",
+];
+
 impl FFmpegProcessor {
     pub fn new() -> Self {
+        let config = crate::models::get_config();
         Self {
             duration_cache: HashMap::new(),
             available_gpu_encoder: None,
             gpu_checked: false,
+            transitions: None,
+            encoders: None,
+            has_acrossfade: None,
+            debug_logging: config.debug_logging,
+            packet_count_fallback: config.packet_count_fallback,
+            probe_log: VecDeque::new(),
+        }
+    }
+
+    fn record_probe(&mut self, entry: ProbeLogEntry) {
+        if self.probe_log.len() == PROBE_LOG_CAPACITY {
+            self.probe_log.pop_front();
+        }
+        self.probe_log.push_back(entry);
+    }
+
+    // Parses the values of the `transition` option from `ffmpeg -h filter=xfade`.
+    // An empty list means this ffmpeg has no xfade filter at all.
+    fn parse_xfade_transitions(help: &str) -> Vec<String> {
+        let mut names = Vec::new();
+        let mut in_transition = false;
+
+        for line in help.lines() {
+            let indent = line.len() - line.trim_start().len();
+            let mut tokens = line.split_whitespace();
+            let (Some(name), Some(value)) = (tokens.next(), tokens.next()) else { continue };
+
+            if value.starts_with('<') {
+                in_transition = name == "transition";
+            } else if in_transition && indent > 3 && value.parse::<i32>().map(|v| v >= 0).unwrap_or(false) {
+                names.push(name.to_string());
+            }
+        }
+        names
+    }
+
+    pub fn list_transitions(&mut self) -> Vec<String> {
+        if let Some(ref cached) = self.transitions {
+            return cached.clone();
+        }
+
+        let transitions = match Command::new("ffmpeg").args(["-hide_banner", "-h", "filter=xfade"]).output() {
+            Ok(output) => {
+                let help = String::from_utf8_lossy(&output.stdout);
+                let parsed = Self::parse_xfade_transitions(&help);
+                if parsed.is_empty() && help.contains("xfade") {
+                    FALLBACK_TRANSITIONS.iter().map(|t| t.to_string()).collect()
+                } else {
+                    parsed
+                }
+            }
+            Err(_) => Vec::new(),
+        };
+
+        self.transitions = Some(transitions.clone());
+        transitions
+    }
+
+    // xfade only exists since ffmpeg 4.3 (no transitions listed before that);
+    // acrossfade is much older but checked all the same
+    pub(crate) fn crossfade_support(&mut self) -> CrossfadeSupport {
+        let xfade = !self.list_transitions().is_empty();
+        let acrossfade = *self.has_acrossfade.get_or_insert_with(|| {
+            Command::new("ffmpeg")
+                .args(["-hide_banner", "-h", "filter=acrossfade"])
+                .output()
+                .is_ok_and(|o| String::from_utf8_lossy(&o.stdout).contains("Filter acrossfade"))
+        });
+        CrossfadeSupport { xfade, acrossfade }
+    }
+
+    // Parses the table of `ffmpeg -encoders`: a flags column (V/A/S first), the
+    // encoder name, then a description ending in "(codec x)" for wrappers.
+    fn parse_encoders(output: &str) -> Vec<EncoderInfo> {
+        let codec_regex = Regex::new(r"\(codec (\w+)\)").unwrap();
+        let mut encoders = Vec::new();
+        let mut in_table = false;
+
+        for line in output.lines() {
+            let trimmed = line.trim();
+            if trimmed.starts_with("------") {
+                in_table = true;
+                continue;
+            }
+            if !in_table {
+                continue;
+            }
+
+            let mut tokens = trimmed.splitn(3, char::is_whitespace);
+            let (Some(flags), Some(name)) = (tokens.next(), tokens.next()) else { continue };
+            let kind = match flags.chars().next() {
+                Some('V') => "video",
+                Some('A') => "audio",
+                Some('S') => "subtitle",
+                _ => continue,
+            };
+            let description = tokens.next().unwrap_or("");
+            let codec = codec_regex
+                .captures(description)
+                .map(|c| c[1].to_string())
+                .unwrap_or_else(|| name.to_string());
+
+            encoders.push(EncoderInfo {
+                name: name.to_string(),
+                codec,
+                kind: kind.to_string(),
+                hardware: HARDWARE_ENCODER_SUFFIXES.iter().any(|s| name.ends_with(s)),
+            });
+        }
+        encoders
+    }
+
+    pub fn list_encoders(&mut self) -> Vec<EncoderInfo> {
+        if let Some(ref cached) = self.encoders {
+            return cached.clone();
         }
+
+        let encoders = match Command::new("ffmpeg").args(["-hide_banner", "-encoders"]).output() {
+            Ok(output) => Self::parse_encoders(&String::from_utf8_lossy(&output.stdout)),
+            Err(_) => Vec::new(),
+        };
+
+        self.encoders = Some(encoders.clone());
+        encoders
     }
 
     fn check_command_exists(cmd: &str) -> bool {
@@ -69,15 +473,19 @@ impl FFmpegProcessor {
         }
     }
 
+    // Options a lavfi source needs before it can be fed to the encoder of `gpu_type`
+    fn synthetic_input_args(gpu_type: &str) -> &'static [&'static str] {
+        match gpu_type {
+            "vaapi" => &["-vaapi_device", "/dev/dri/renderD128", "-vf", "format=nv12,hwupload"],
+            "v4l2m2m" => &["-vf", "format=yuv420p", "-b:v", "1M"],
+            _ => &[],
+        }
+    }
+
     fn test_gpu_encoder(encoder: &str, gpu_type: &str) -> bool {
         let mut cmd = Command::new("ffmpeg");
         cmd.args(["-hide_banner", "-f", "lavfi", "-i", "color=black:s=256x256:d=0.1"]);
-
-        if gpu_type == "vaapi" {
-            cmd.args(["-vaapi_device", "/dev/dri/renderD128"]);
-            cmd.args(["-vf", "format=nv12,hwupload"]);
-        }
-
+        cmd.args(Self::synthetic_input_args(gpu_type));
         cmd.args(["-c:v", encoder, "-f", "null", "-"]);
         cmd.stdout(Stdio::null()).stderr(Stdio::null());
 
@@ -90,40 +498,37 @@ impl FFmpegProcessor {
         }
 
         self.gpu_checked = true;
+        self.available_gpu_encoder = self.working_gpu_types().next();
+        self.available_gpu_encoder.clone()
+    }
 
-        let output = Command::new("ffmpeg")
-            .args(["-hide_banner", "-encoders"])
-            .output()
-            .ok()?;
-
-        let encoders_output = String::from_utf8_lossy(&output.stdout);
+    // GPU types whose encoder is built in and actually runs, by priority. Lazy, so
+    // detection stops testing at the first one that works.
+    fn working_gpu_types(&mut self) -> impl Iterator<Item = String> {
+        let available = self.list_encoders();
 
-        let checks = [
+        let mut checks = vec![
             ("nvidia", "h264_nvenc"),
             ("intel", "h264_qsv"),
             ("amd", "h264_amf"),
             ("vaapi", "h264_vaapi"),
         ];
-
-        for (gpu_type, encoder_name) in checks {
-            if encoders_output.contains(encoder_name) && Self::test_gpu_encoder(encoder_name, gpu_type) {
-                self.available_gpu_encoder = Some(gpu_type.to_string());
-                return Some(gpu_type.to_string());
-            }
+        // V4L2 M2M is the hardware encoder of ARM boards such as the Raspberry Pi
+        if cfg!(all(target_os = "linux", any(target_arch = "aarch64", target_arch = "arm"))) {
+            checks.push(("v4l2m2m", "h264_v4l2m2m"));
         }
 
-        None
+        checks
+            .into_iter()
+            .filter(move |(gpu_type, encoder_name)| {
+                available.iter().any(|e| e.name == *encoder_name) && Self::test_gpu_encoder(encoder_name, gpu_type)
+            })
+            .map(|(gpu_type, _)| gpu_type.to_string())
     }
 
     pub fn get_gpu_info(&mut self) -> GpuInfo {
         let gpu = self.detect_gpu_encoder();
-        let encoder = gpu.as_ref().map(|g| match g.as_str() {
-            "nvidia" => "h264_nvenc",
-            "amd" => "h264_amf",
-            "intel" => "h264_qsv",
-            "vaapi" => "h264_vaapi",
-            _ => "libx264",
-        });
+        let encoder = gpu.as_deref().map(get_gpu_encoder_name);
 
         GpuInfo {
             available: gpu.is_some(),
@@ -132,7 +537,7 @@ impl FFmpegProcessor {
         }
     }
 
-    fn get_cache_key(path: &str) -> String {
+    pub(crate) fn get_cache_key(path: &str) -> String {
         let mtime = fs::metadata(path)
             .and_then(|m| m.modified())
             .map(|t| t.duration_since(std::time::UNIX_EPOCH).unwrap_or_default().as_secs())
@@ -140,6 +545,31 @@ impl FFmpegProcessor {
         format!("{}:{}", path, mtime)
     }
 
+    // Identifies a file by its size and its first and last megabyte, so a renamed
+    // or copied file maps to the same cache entry
+    pub(crate) fn content_hash(path: &str) -> Option<String> {
+        let mut file = fs::File::open(path).ok()?;
+        let len = file.metadata().ok()?.len();
+        let mut hasher = DefaultHasher::new();
+        len.hash(&mut hasher);
+
+        let mut head = vec![0u8; CONTENT_HASH_SAMPLE.min(len) as usize];
+        file.read_exact(&mut head).ok()?;
+        head.hash(&mut hasher);
+        if len > CONTENT_HASH_SAMPLE {
+            let mut tail = Vec::new();
+            file.seek(SeekFrom::Start(CONTENT_HASH_SAMPLE.max(len - CONTENT_HASH_SAMPLE))).ok()?;
+            file.read_to_end(&mut tail).ok()?;
+            tail.hash(&mut hasher);
+        }
+        Some(format!("{:016x}", hasher.finish()))
+    }
+
+    // Second key for the duration cache, see `content_hash`
+    fn get_content_cache_key(path: &str) -> Option<String> {
+        Self::content_hash(path).map(|hash| format!("content:{}", hash))
+    }
+
     fn duration_ffprobe_quick(path: &str) -> Option<f64> {
         let output = Command::new("ffprobe")
             .args(["-v", "error", "-show_entries", "format=duration", "-of", "default=nw=1:nk=1", path])
@@ -154,8 +584,9 @@ impl FFmpegProcessor {
         }
     }
 
-    fn duration_ffprobe_json(path: &str) -> Option<f64> {
+    fn duration_ffprobe_json(path: &str, probe_args: &[&str]) -> Option<f64> {
         let output = Command::new("ffprobe")
+            .args(probe_args)
             .args(["-v", "error", "-print_format", "json", "-show_entries", "format=duration,stream=duration", path])
             .output()
             .ok()?;
@@ -183,6 +614,234 @@ impl FFmpegProcessor {
         None
     }
 
+    // Last resort for raw or streamed files without any duration metadata: counts
+    // the video packets (one per frame), or decodes the audio and reads how far
+    // ffmpeg got. Both read the whole file, hence the config switch.
+    fn duration_packet_count(path: &str) -> Option<f64> {
+        Self::duration_video_packets(path).or_else(|| Self::duration_decode(path))
+    }
+
+    fn duration_video_packets(path: &str) -> Option<f64> {
+        let output = Command::new("ffprobe")
+            .args([
+                "-v", "error", "-select_streams", "v:0", "-count_packets",
+                "-show_entries", "stream=nb_read_packets,r_frame_rate",
+                "-print_format", "json", path,
+            ])
+            .output()
+            .ok()?;
+
+        let json: serde_json::Value = serde_json::from_slice(&output.stdout).ok()?;
+        let stream = json.get("streams")?.as_array()?.first()?;
+        let packets: f64 = stream.get("nb_read_packets")?.as_str()?.parse().ok()?;
+        let fps = stream.get("r_frame_rate").and_then(|r| r.as_str()).and_then(Self::parse_frame_rate)?;
+        (packets > 0.0 && fps > 0.0).then(|| packets / fps)
+    }
+
+    fn duration_decode(path: &str) -> Option<f64> {
+        let output = Command::new("ffmpeg")
+            .args(["-hide_banner", "-nostdin", "-i", path, "-vn", "-f", "null", "-"])
+            .stdout(Stdio::null())
+            .output()
+            .ok()?;
+
+        let time_regex = Regex::new(r"time=(\d+):(\d+):(\d+(?:\.\d+)?)").unwrap();
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let caps = time_regex.captures_iter(&stderr).last()?;
+        let (h, m, sec): (f64, f64, f64) = (caps[1].parse().ok()?, caps[2].parse().ok()?, caps[3].parse().ok()?);
+        let duration = h * 3600.0 + m * 60.0 + sec;
+        (duration > 0.0).then_some(duration)
+    }
+
+    pub(crate) fn parse_frame_rate(rate: &str) -> Option<f64> {
+        match rate.split_once('/') {
+            Some((num, den)) => {
+                let (num, den) = (num.parse::<f64>().ok()?, den.parse::<f64>().ok()?);
+                if den > 0.0 { Some(num / den) } else { None }
+            }
+            None => rate.parse().ok(),
+        }
+    }
+
+    pub fn probe_video_properties(path: &str) -> Option<VideoProperties> {
+        Self::probe_clip(path).properties
+    }
+
+    // One ffprobe run for everything an export or a validation reads from a file
+    pub(crate) fn probe_clip(path: &str) -> ClipProbe {
+        if !Path::new(path).exists() {
+            return ClipProbe::default();
+        }
+        let Ok(output) = Command::new("ffprobe")
+            .args([
+                "-v", "error",
+                "-show_entries", "format=duration:stream=codec_type,codec_name,channels,width,height,r_frame_rate,field_order,sample_aspect_ratio,color_range:stream_tags=rotate:stream_side_data=rotation",
+                "-print_format", "json", path,
+            ])
+            .output()
+        else {
+            return ClipProbe::default();
+        };
+        let Ok(json) = serde_json::from_slice::<serde_json::Value>(&output.stdout) else {
+            return ClipProbe { needs_deep_probe: true, ..ClipProbe::default() };
+        };
+
+        let streams = json.get("streams").and_then(|s| s.as_array()).map(Vec::as_slice).unwrap_or_default();
+        let of_type = |kind: &str| streams.iter().find(|s| s.get("codec_type").and_then(|t| t.as_str()) == Some(kind));
+        let no_duration = json
+            .get("format")
+            .and_then(|f| f.get("duration"))
+            .and_then(|d| d.as_str())
+            .is_none_or(|d| d == "N/A");
+        ClipProbe {
+            properties: of_type("video").map(|stream| Self::parse_video_stream(stream, of_type("audio"))),
+            audio_channels: of_type("audio").and_then(|a| a.get("channels")).and_then(|c| c.as_i64()),
+            needs_deep_probe: streams.is_empty() || no_duration,
+        }
+    }
+
+    fn parse_video_stream(stream: &serde_json::Value, audio: Option<&serde_json::Value>) -> VideoProperties {
+        // Display matrix rotation is counterclockwise, the older `rotate` tag clockwise
+        let side_data_rotation = stream
+            .get("side_data_list")
+            .and_then(|l| l.as_array())
+            .and_then(|l| l.iter().find_map(|d| d.get("rotation").and_then(|r| r.as_f64())))
+            .map(|r| -r);
+        let tag_rotation = stream
+            .get("tags")
+            .and_then(|t| t.get("rotate"))
+            .and_then(|r| r.as_str())
+            .and_then(|r| r.parse::<f64>().ok());
+        let rotation = side_data_rotation.or(tag_rotation).unwrap_or(0.0);
+
+        VideoProperties {
+            width: stream.get("width").and_then(|w| w.as_i64()).unwrap_or(0) as i32,
+            height: stream.get("height").and_then(|h| h.as_i64()).unwrap_or(0) as i32,
+            fps: stream
+                .get("r_frame_rate")
+                .and_then(|r| r.as_str())
+                .and_then(Self::parse_frame_rate)
+                .unwrap_or(0.0),
+            field_order: stream.get("field_order").and_then(|f| f.as_str()).map(String::from),
+            rotation: ((rotation / 90.0).round() as i32).rem_euclid(4) * 90,
+            sample_aspect_ratio: stream.get("sample_aspect_ratio").and_then(|r| r.as_str()).map(String::from),
+            audio_codec: audio.and_then(|a| a.get("codec_name")).and_then(|c| c.as_str()).map(String::from),
+            color_range: stream.get("color_range").and_then(|r| r.as_str()).and_then(ColorRange::from_ffprobe),
+        }
+    }
+
+    // Classifies a file from its streams. Cover art (attached_pic) doesn't make an
+    // audio file a video, and image demuxers report a single video stream.
+    pub fn probe_media_kind(path: &str) -> MediaKind {
+        let Ok(output) = Command::new("ffprobe")
+            .args([
+                "-v", "error",
+                "-show_entries", "format=format_name:stream=codec_type:stream_disposition=attached_pic",
+                "-print_format", "json", path,
+            ])
+            .output()
+        else {
+            return MediaKind::Unknown;
+        };
+
+        let Ok(json) = serde_json::from_slice::<serde_json::Value>(&output.stdout) else {
+            return MediaKind::Unknown;
+        };
+        let format = json.get("format").and_then(|f| f.get("format_name")).and_then(|f| f.as_str()).unwrap_or("");
+        let streams = json.get("streams").and_then(|s| s.as_array()).cloned().unwrap_or_default();
+
+        let is_attached_pic = |s: &serde_json::Value| {
+            s.get("disposition").and_then(|d| d.get("attached_pic")).and_then(|a| a.as_i64()) == Some(1)
+        };
+        let has_video = streams
+            .iter()
+            .any(|s| s.get("codec_type").and_then(|c| c.as_str()) == Some("video") && !is_attached_pic(s));
+        let has_audio = streams.iter().any(|s| s.get("codec_type").and_then(|c| c.as_str()) == Some("audio"));
+
+        if has_video && (format == "image2" || format.ends_with("_pipe")) {
+            MediaKind::Image
+        } else if has_video {
+            MediaKind::Video
+        } else if has_audio {
+            MediaKind::Audio
+        } else {
+            MediaKind::Unknown
+        }
+    }
+
+    // Time of the last keyframe at or before `time`. Looks in a window first and
+    // falls back to reading from the start for very long GOPs.
+    pub fn probe_keyframe_before(path: &str, time: f64) -> Option<f64> {
+        if time <= 0.0 {
+            return Some(0.0);
+        }
+
+        let probe = |from: f64| -> Option<f64> {
+            let output = Command::new("ffprobe")
+                .args(["-v", "error", "-select_streams", "v:0", "-skip_frame", "nokey"])
+                .args(["-read_intervals", &format!("{}%{}", from, time + KEYFRAME_TOLERANCE)])
+                .args(["-show_entries", "frame=best_effort_timestamp_time", "-of", "csv=p=0", path])
+                .output()
+                .ok()?;
+            String::from_utf8_lossy(&output.stdout)
+                .lines()
+                .filter_map(|l| l.trim().trim_end_matches(',').parse::<f64>().ok())
+                .filter(|t| *t <= time + KEYFRAME_TOLERANCE)
+                .reduce(f64::max)
+        };
+
+        probe((time - KEYFRAME_SEARCH_WINDOW).max(0.0)).or_else(|| probe(0.0)).map(|t| t.max(0.0))
+    }
+
+    pub fn probe_creation_time(path: &str) -> Option<String> {
+        let output = Command::new("ffprobe")
+            .args(["-v", "error", "-show_entries", "format_tags=creation_time", "-of", "default=nw=1:nk=1", path])
+            .output()
+            .ok()?;
+
+        let out = String::from_utf8_lossy(&output.stdout).trim().to_string();
+        if out.is_empty() { None } else { Some(out) }
+    }
+
+    fn probe_duration(path: &str, packet_fallback: bool) -> f64 {
+        Self::duration_ffprobe_quick(path)
+            .or_else(|| Self::duration_ffprobe_json(path, &[]))
+            .or_else(|| Self::duration_ffprobe_json(path, &DEEP_PROBE_ARGS))
+            .or_else(|| if packet_fallback { Self::duration_packet_count(path) } else { None })
+            .unwrap_or(0.0)
+    }
+
+    // Same result as `probe_duration`, but always runs both metadata methods so
+    // the log shows when they disagree (VFR streams, odd containers)
+    fn probe_duration_logged(path: &str, packet_fallback: bool) -> (f64, ProbeLogEntry) {
+        let quick = Self::duration_ffprobe_quick(path);
+        let json = Self::duration_ffprobe_json(path, &[]);
+        let deep = if quick.is_none() && json.is_none() {
+            Self::duration_ffprobe_json(path, &DEEP_PROBE_ARGS)
+        } else {
+            None
+        };
+        let packets = if packet_fallback && quick.is_none() && json.is_none() && deep.is_none() {
+            Self::duration_packet_count(path)
+        } else {
+            None
+        };
+        let method = if quick.is_some() {
+            Some("quick")
+        } else if json.is_some() {
+            Some("json")
+        } else if deep.is_some() {
+            Some("deep")
+        } else if packets.is_some() {
+            Some("packets")
+        } else {
+            None
+        };
+        let duration = quick.or(json).or(deep).or(packets).unwrap_or(0.0);
+        let entry = ProbeLogEntry { path: path.to_string(), method: method.map(String::from), quick, json, deep, packets, duration };
+        (duration, entry)
+    }
+
     pub fn get_duration(&mut self, path: &str) -> f64 {
         if !Path::new(path).exists() {
             return 0.0;
@@ -192,18 +851,107 @@ impl FFmpegProcessor {
         if let Some(&duration) = self.duration_cache.get(&cache_key) {
             return duration;
         }
+        let content_key = Self::get_content_cache_key(path);
+        if let Some(&duration) = content_key.as_ref().and_then(|k| self.duration_cache.get(k)) {
+            self.duration_cache.insert(cache_key, duration);
+            return duration;
+        }
 
-        let duration = Self::duration_ffprobe_quick(path)
-            .or_else(|| Self::duration_ffprobe_json(path))
-            .unwrap_or(0.0);
+        let duration = if self.debug_logging {
+            let (duration, entry) = Self::probe_duration_logged(path, self.packet_count_fallback);
+            self.record_probe(entry);
+            duration
+        } else {
+            Self::probe_duration(path, self.packet_count_fallback)
+        };
 
         self.duration_cache.insert(cache_key, duration);
+        if let Some(content_key) = content_key {
+            self.duration_cache.insert(content_key, duration);
+        }
         duration
     }
+}
+
+// Probes a batch of files on a small worker pool. The processor lock is only held
+// to read and fill the cache, not while ffprobe runs. `on_done` is called once per
+// path (cached or not) with its index and duration; results keep input order.
+// Once `cancel` is set no new file is started: paths left unprobed come back as
+// None, the ones already done are returned and cached.
+pub(crate) fn probe_durations<F>(processor: &Mutex<FFmpegProcessor>, paths: &[String], cancel: &AtomicBool, on_done: F) -> Vec<Option<f64>>
+where
+    F: Fn(usize, f64) + Sync,
+{
+    let keys: Vec<String> = paths.iter().map(|p| FFmpegProcessor::get_cache_key(p)).collect();
+    let (mut results, debug_logging, packet_fallback): (Vec<Option<f64>>, bool, bool) = {
+        let ffmpeg = processor.lock().unwrap();
+        (
+            keys.iter().map(|k| ffmpeg.duration_cache.get(k).copied()).collect(),
+            ffmpeg.debug_logging,
+            ffmpeg.packet_count_fallback,
+        )
+    };
+
+    let pending: Vec<usize> = (0..paths.len()).filter(|&i| results[i].is_none()).collect();
+    for (i, r) in results.iter().enumerate() {
+        if let Some(d) = r {
+            on_done(i, *d);
+        }
+    }
+
+    let next = AtomicUsize::new(0);
+    let probed = Mutex::new(Vec::with_capacity(pending.len()));
+    let workers = std::thread::available_parallelism().map(|n| n.get()).unwrap_or(4).min(pending.len());
+
+    std::thread::scope(|scope| {
+        for _ in 0..workers {
+            scope.spawn(|| loop {
+                if cancel.load(Ordering::SeqCst) {
+                    break;
+                }
+                let slot = next.fetch_add(1, Ordering::SeqCst);
+                let Some(&i) = pending.get(slot) else { break };
+                let content_key = FFmpegProcessor::get_content_cache_key(&paths[i]);
+                let cached = content_key.as_ref().and_then(|k| processor.lock().unwrap().duration_cache.get(k).copied());
+                let (duration, entry) = if let Some(duration) = cached {
+                    (duration, None)
+                } else if !Path::new(&paths[i]).exists() {
+                    (0.0, None)
+                } else if debug_logging {
+                    let (duration, entry) = FFmpegProcessor::probe_duration_logged(&paths[i], packet_fallback);
+                    (duration, Some(entry))
+                } else {
+                    (FFmpegProcessor::probe_duration(&paths[i], packet_fallback), None)
+                };
+                probed.lock().unwrap().push((i, duration, entry, content_key));
+                on_done(i, duration);
+            });
+        }
+    });
 
-    pub fn get_durations_parallel(&mut self, paths: Vec<String>) -> Vec<f64> {
-        paths.iter().map(|p| self.get_duration(p)).collect()
+    let mut ffmpeg = processor.lock().unwrap();
+    for (i, duration, entry, content_key) in probed.into_inner().unwrap() {
+        if Path::new(&paths[i]).exists() {
+            ffmpeg.duration_cache.insert(keys[i].clone(), duration);
+        }
+        if let Some(content_key) = content_key {
+            ffmpeg.duration_cache.insert(content_key, duration);
+        }
+        if let Some(entry) = entry {
+            ffmpeg.record_probe(entry);
+        }
+        results[i] = Some(duration);
     }
+
+    results
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProbeProgress {
+    pub index: usize,
+    pub completed: usize,
+    pub total: usize,
+    pub duration: f64,
 }
 
 // Tauri commands
@@ -223,9 +971,77 @@ pub fn get_duration(state: State<'_, AppState>, path: String) -> f64 {
     state.ffmpeg.lock().unwrap().get_duration(&path)
 }
 
+// Most recent probes first; empty unless `debug_logging` is on in the config
+#[tauri::command]
+pub fn get_last_probe_log(state: State<'_, AppState>) -> Vec<ProbeLogEntry> {
+    state.ffmpeg.lock().unwrap().probe_log.iter().rev().cloned().collect()
+}
+
 #[tauri::command]
 pub fn get_durations_parallel(state: State<'_, AppState>, paths: Vec<String>) -> Vec<f64> {
-    state.ffmpeg.lock().unwrap().get_durations_parallel(paths)
+    state.probe_cancelled.store(false, Ordering::SeqCst);
+    probe_durations(&state.ffmpeg, &paths, &state.probe_cancelled, |_, _| {})
+        .into_iter()
+        .map(|d| d.unwrap_or(0.0))
+        .collect()
+}
+
+// None for the files skipped after `cancel_probe`
+#[tauri::command]
+pub async fn get_durations_with_progress(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    paths: Vec<String>,
+) -> Result<Vec<Option<f64>>, String> {
+    state.probe_cancelled.store(false, Ordering::SeqCst);
+    let completed = AtomicUsize::new(0);
+    let total = paths.len();
+
+    Ok(probe_durations(&state.ffmpeg, &paths, &state.probe_cancelled, |index, duration| {
+        let done = completed.fetch_add(1, Ordering::SeqCst) + 1;
+        let _ = app.emit("probe-progress", ProbeProgress { index, completed: done, total, duration });
+    }))
+}
+
+// Stops a running batch probe; files already being probed still finish
+#[tauri::command]
+pub fn cancel_probe(state: State<'_, AppState>) {
+    state.probe_cancelled.store(true, Ordering::SeqCst);
+}
+
+#[tauri::command]
+pub fn list_transitions(state: State<'_, AppState>) -> Vec<String> {
+    state.ffmpeg.lock().unwrap().list_transitions()
+}
+
+#[tauri::command]
+pub fn get_supported_encoders(state: State<'_, AppState>) -> Vec<EncoderInfo> {
+    state.ffmpeg.lock().unwrap().list_encoders()
+}
+
+// Where a cut at `time` really starts with `snap_to_keyframes` (time itself when no keyframe is found)
+#[tauri::command]
+pub fn snap_to_keyframe(path: String, time: f64) -> f64 {
+    FFmpegProcessor::probe_keyframe_before(&path, time).unwrap_or(time)
+}
+
+#[tauri::command]
+pub fn probe_video_properties(path: String) -> Option<VideoProperties> {
+    FFmpegProcessor::probe_video_properties(&path)
+}
+
+// Same value for a file and its renamed or copied duplicates, see `FFmpegProcessor::content_hash`
+#[tauri::command]
+pub fn file_content_hash(path: String) -> Result<String, String> {
+    FFmpegProcessor::content_hash(&path).ok_or_else(|| format!("Impossible de lire le fichier: {}", path))
+}
+
+#[tauri::command]
+pub fn is_valid_media(path: String) -> MediaKind {
+    if !Path::new(&path).exists() {
+        return MediaKind::Unknown;
+    }
+    FFmpegProcessor::probe_media_kind(&path)
 }
 
 #[tauri::command]
@@ -233,6 +1049,86 @@ pub fn get_gpu_info(state: State<'_, AppState>) -> GpuInfo {
     state.ffmpeg.lock().unwrap().get_gpu_info()
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BenchResult {
+    pub encoder: String,
+    pub preset: String,
+    pub fps: f64,
+    // Seconds of video encoded per second of wall time
+    pub realtime_ratio: f64,
+}
+
+const BENCHMARK_FPS: f64 = 30.0;
+
+// Wall time of one 1080p testsrc2 encode, None when the encoder fails. The
+// source is generated on the fly, so very fast encoders are capped by lavfi.
+fn time_encode(gpu_type: &str, preset: Option<(&str, &str)>, duration: f64) -> Option<f64> {
+    let (encoder, _, _) = get_encoder_config(gpu_type);
+    let source = format!("testsrc2=size=1920x1080:rate={}:duration={}", BENCHMARK_FPS, duration);
+
+    let mut cmd = Command::new("ffmpeg");
+    cmd.args(["-hide_banner", "-f", "lavfi", "-i", &source]);
+    cmd.args(FFmpegProcessor::synthetic_input_args(gpu_type));
+    cmd.args(["-c:v", encoder]);
+    if let Some((flag, value)) = preset {
+        cmd.args([flag, value]);
+    }
+    cmd.args(["-f", "null", "-"]);
+    cmd.stdout(Stdio::null()).stderr(Stdio::null());
+
+    let start = Instant::now();
+    let status = cmd.status().ok()?;
+    status.success().then(|| start.elapsed().as_secs_f64())
+}
+
+// Encodes the same synthetic clip with the CPU encoder and every working GPU
+// one, at each speed preset, to compare throughput on this machine
+#[tauri::command]
+pub async fn benchmark_encoders(state: State<'_, AppState>, duration_seconds: Option<f64>) -> Result<Vec<BenchResult>, String> {
+    let duration = duration_seconds.unwrap_or(5.0).clamp(1.0, 60.0);
+    let mut gpu_types = vec!["cpu".to_string()];
+    gpu_types.extend(state.ffmpeg.lock().unwrap().working_gpu_types());
+
+    let mut results = Vec::new();
+    for gpu_type in &gpu_types {
+        let (encoder, preset_flag, presets) = get_encoder_config(gpu_type);
+        let mut runs: Vec<(&str, Option<(&str, &str)>)> = ["ultrafast", "fast", "balanced", "quality"]
+            .into_iter()
+            .filter_map(|name| Some((name, Some((preset_flag?, *presets.get(name)?)))))
+            .collect();
+        if runs.is_empty() {
+            runs.push(("default", None));
+        }
+
+        for (name, preset) in runs {
+            if let Some(elapsed) = time_encode(gpu_type, preset, duration).filter(|e| *e > 0.0) {
+                results.push(BenchResult {
+                    encoder: encoder.to_string(),
+                    preset: name.to_string(),
+                    fps: duration * BENCHMARK_FPS / elapsed,
+                    realtime_ratio: duration / elapsed,
+                });
+            }
+        }
+    }
+
+    if results.is_empty() {
+        return Err("Aucun encodeur n'a pu etre teste (ffmpeg est-il installe ?)".to_string());
+    }
+    Ok(results)
+}
+
+fn get_gpu_encoder_name(gpu_type: &str) -> &'static str {
+    match gpu_type {
+        "nvidia" => "h264_nvenc",
+        "amd" => "h264_amf",
+        "intel" => "h264_qsv",
+        "vaapi" => "h264_vaapi",
+        "v4l2m2m" => "h264_v4l2m2m",
+        _ => "libx264",
+    }
+}
+
 fn get_encoder_config(gpu_type: &str) -> (&'static str, Option<&'static str>, HashMap<&'static str, &'static str>) {
     let mut presets = HashMap::new();
     match gpu_type {
@@ -258,6 +1154,7 @@ fn get_encoder_config(gpu_type: &str) -> (&'static str, Option<&'static str>, Ha
             ("h264_qsv", Some("-preset"), presets)
         }
         "vaapi" => ("h264_vaapi", None, presets),
+        "v4l2m2m" => ("h264_v4l2m2m", None, presets),
         _ => {
             presets.insert("ultrafast", "ultrafast");
             presets.insert("fast", "veryfast");
@@ -268,28 +1165,203 @@ fn get_encoder_config(gpu_type: &str) -> (&'static str, Option<&'static str>, Ha
     }
 }
 
-fn build_audio_crossfade_filter(tracks: &[AudioTrack], crossfade_duration: i32, base_input_index: usize) -> (String, String) {
-    let n = tracks.len();
-    let mut parts: Vec<String> = tracks
-        .iter()
-        .enumerate()
-        .map(|(i, t)| {
-            let vol = if t.mute { 0.0 } else { t.volume.min(1.1) };
-            format!("[{}:a]volume={}[ma{}]", base_input_index + i, vol, i)
+// -profile:v and -level for an H.264 encoder. AMF and VAAPI call baseline
+// "constrained_baseline"; V4L2 M2M drivers mostly ignore both, so nothing is sent.
+fn h264_profile_args(encoder: &str, settings: &ProjectSettings) -> Vec<String> {
+    if encoder == "h264_v4l2m2m" {
+        return Vec::new();
+    }
+    let mut args = Vec::new();
+    if let Some(profile) = settings.get_h264_profile() {
+        let profile = match (encoder, profile) {
+            ("h264_amf" | "h264_vaapi", "baseline") => "constrained_baseline",
+            _ => profile,
+        };
+        args.extend(["-profile:v".to_string(), profile.to_string()]);
+    }
+    if let Some(level) = settings.get_h264_level() {
+        args.extend(["-level".to_string(), level.to_string()]);
+    }
+    args
+}
+
+// Hardware decoding flags for the detected GPU, before the inputs
+fn hwaccel_args(gpu_type: Option<&str>) -> Vec<String> {
+    match gpu_type {
+        Some("nvidia") => vec!["-hwaccel".to_string(), "cuda".to_string()],
+        Some("intel") => vec!["-hwaccel".to_string(), "qsv".to_string()],
+        Some("vaapi") => vec!["-vaapi_device".to_string(), "/dev/dri/renderD128".to_string()],
+        _ => Vec::new(),
+    }
+}
+
+// -c:v and its rate control for the detected GPU, or libx264 without one
+fn video_encoder_args(gpu_type: Option<&str>, preset: &str, settings: &ProjectSettings) -> Vec<String> {
+    let mut cmd = Vec::new();
+    if let Some(gt) = gpu_type {
+        let (encoder, preset_flag, presets) = get_encoder_config(gt);
+        cmd.extend(["-c:v".to_string(), encoder.to_string()]);
+
+        if let (Some(flag), Some(preset_val)) = (preset_flag, presets.get(preset)) {
+            cmd.extend([flag.to_string(), preset_val.to_string()]);
+        }
+        cmd.extend(h264_profile_args(encoder, settings));
+        cmd.extend(tune_args(encoder, settings));
+
+        match gt {
+            "nvidia" => cmd.extend(["-rc".to_string(), "vbr".to_string(), "-cq".to_string(), "20".to_string(), "-b:v".to_string(), "0".to_string()]),
+            "amd" => cmd.extend(["-rc".to_string(), "vbr_latency".to_string(), "-qp_p".to_string(), "20".to_string(), "-qp_i".to_string(), "20".to_string()]),
+            "intel" => cmd.extend(["-global_quality".to_string(), "20".to_string(), "-look_ahead".to_string(), "1".to_string()]),
+            // No constant-quality mode on V4L2 M2M, only a target bitrate
+            "v4l2m2m" => cmd.extend([
+                "-b:v".to_string(),
+                settings.video_bitrate.clone().unwrap_or_else(|| "8M".to_string()),
+                "-pix_fmt".to_string(),
+                "yuv420p".to_string(),
+            ]),
+            _ => cmd.extend(["-qp".to_string(), "20".to_string()]),
+        }
+    } else {
+        let (encoder, preset_flag, presets) = get_encoder_config("cpu");
+        cmd.extend(["-c:v".to_string(), encoder.to_string()]);
+        if let (Some(flag), Some(preset_val)) = (preset_flag, presets.get(preset)) {
+            cmd.extend([flag.to_string(), preset_val.to_string()]);
+        }
+        cmd.extend(h264_profile_args(encoder, settings));
+        cmd.extend(tune_args(encoder, settings));
+        cmd.extend(["-crf".to_string(), "20".to_string()]);
+    }
+    cmd
+}
+
+fn tune_args(encoder: &str, settings: &ProjectSettings) -> Vec<String> {
+    settings.get_tune(encoder).map(|tune| vec!["-tune".to_string(), tune.to_string()]).unwrap_or_default()
+}
+
+const AUDIO_CROSSFADE_MARGIN: f64 = 0.5;
+const MIN_AUDIO_CROSSFADE: f64 = 0.1;
+
+// Per-join crossfade durations, clamped so a join never overlaps more than the
+// shorter of the two adjacent tracks. Unprobed tracks (duration 0) aren't clamped.
+pub(crate) fn audio_crossfade_durations(tracks: &[AudioTrack], crossfade_duration: f64) -> Vec<f64> {
+    tracks
+        .windows(2)
+        .map(|pair| {
+            let shortest = pair[0].duration.min(pair[1].duration);
+            if shortest > 0.0 {
+                crossfade_duration.min(shortest - AUDIO_CROSSFADE_MARGIN).max(MIN_AUDIO_CROSSFADE)
+            } else {
+                crossfade_duration
+            }
         })
+        .collect()
+}
+
+// Piecewise-linear gain over the keyframes, held flat before the first and after the last
+fn build_volume_expression(keyframes: &[VolumeKeyframe]) -> Option<String> {
+    let mut points: Vec<VolumeKeyframe> = keyframes.iter().filter(|k| k.time.is_finite()).copied().collect();
+    points.sort_by(|a, b| a.time.total_cmp(&b.time));
+
+    let last = points.last()?;
+    let mut expr = format!("{}", last.volume);
+    for pair in points.windows(2).rev() {
+        let (a, b) = (pair[0], pair[1]);
+        let span = (b.time - a.time).max(f64::EPSILON);
+        expr = format!(
+            "if(lt(t,{}),{}+({})*(t-{})/{},{})",
+            b.time, a.volume, b.volume - a.volume, a.time, span, expr
+        );
+    }
+    Some(format!("if(lt(t,{}),{},{})", points[0].time, points[0].volume, expr))
+}
+
+// How each audio input enters the graph. `Stereo` downmixes (or upmixes) every
+// input first, so crossfades and amix never see mixed layouts.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum AudioInput {
+    Native,
+    Stereo,
+}
+
+impl AudioInput {
+    fn from_settings(settings: &ProjectSettings) -> Self {
+        if settings.force_stereo { Self::Stereo } else { Self::Native }
+    }
+
+    // The input label, followed by the layout conversion when forced
+    fn source(&self, index: usize) -> String {
+        match self {
+            Self::Native => format!("[{}:a]", index),
+            Self::Stereo => format!("[{}:a]aformat=channel_layouts=stereo,", index),
+        }
+    }
+}
+
+// One track's own processing (volume, keyframes, loudness gain, EQ) from input
+// `input` to the `output` label, shared by the mix and `process_track`
+fn build_track_filter(t: &AudioTrack, input: usize, output: &str, audio: AudioInput) -> String {
+    let vol = if t.mute { 0.0 } else { t.volume.min(1.1) * t.get_loudness_factor() };
+    let eq = t
+        .eq
+        .and_then(|eq| eq.get_filter())
+        .into_iter()
+        .chain(t.get_pan_filter())
+        .map(|f| format!(",{}", f))
+        .collect::<String>();
+    match build_volume_expression(&t.volume_keyframes) {
+        Some(expr) if !t.mute => format!("{}volume='{}*{}':eval=frame{}[{}]", audio.source(input), vol, expr, eq, output),
+        _ => format!("{}volume={}{}[{}]", audio.source(input), vol, eq, output),
+    }
+}
+
+// Each track downmixed to one channel of a multichannel stream. amerge stops with
+// its shortest input, so every track is padded with silence to the longest one.
+// Panning doesn't apply: the channel is the position.
+fn build_audio_merge_filter(tracks: &[AudioTrack], base_input_index: usize) -> (String, String) {
+    let longest = tracks.iter().map(|t| t.duration).fold(0.0, f64::max);
+    let mut parts = Vec::new();
+    for (i, t) in tracks.iter().enumerate() {
+        let centered = AudioTrack { pan: None, ..t.clone() };
+        parts.push(build_track_filter(&centered, base_input_index + i, &format!("mt{}", i), AudioInput::Native));
+        let pad = if longest > 0.0 { format!(",apad=whole_dur={}", longest) } else { String::new() };
+        parts.push(format!("[mt{i}]aformat=channel_layouts=mono{pad}[mm{i}]"));
+    }
+    let inputs: String = (0..tracks.len()).map(|i| format!("[mm{}]", i)).collect();
+    parts.push(format!("{}amerge=inputs={}[mmerge]", inputs, tracks.len()));
+    (parts.join(";"), "[mmerge]".to_string())
+}
+
+fn build_audio_crossfade_filter(
+    tracks: &[AudioTrack],
+    crossfade_duration: f64,
+    base_input_index: usize,
+    hard_cuts: bool,
+    audio: AudioInput,
+) -> (String, String) {
+    let n = tracks.len();
+    let mut parts: Vec<String> = tracks
+        .iter()
+        .enumerate()
+        .map(|(i, t)| build_track_filter(t, base_input_index + i, &format!("ma{}", i), audio))
         .collect();
 
     if n == 1 {
         return (parts.join(";"), "[ma0]".to_string());
     }
+    if hard_cuts {
+        let inputs: String = (0..n).map(|i| format!("[ma{}]", i)).collect();
+        parts.push(format!("{}concat=n={}:v=0:a=1[mcat]", inputs, n));
+        return (parts.join(";"), "[mcat]".to_string());
+    }
 
+    let durations = audio_crossfade_durations(tracks, crossfade_duration);
     let mut prev = "ma0".to_string();
     for j in 1..n {
         let cur = format!("ma{}", j);
         let out = format!("mx{}", j);
         parts.push(format!(
             "[{}][{}]acrossfade=d={}:c1=qsin:c2=qsin[{}]",
-            prev, cur, crossfade_duration, out
+            prev, cur, durations[j - 1], out
         ));
         prev = out;
     }
@@ -297,41 +1369,394 @@ fn build_audio_crossfade_filter(tracks: &[AudioTrack], crossfade_duration: i32,
     (parts.join(";"), format!("[{}]", prev))
 }
 
-fn build_video_crossfade_filter(clips: &[VideoClip], crossfade_duration: f64) -> (String, String, String) {
+fn escape_with_backslash(value: &str, special: &[char]) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        if special.contains(&c) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+// A path inside -filter_complex goes through two parsers: the filter's option
+// parser (`:` separates options) and the graph parser (`,;[]` separate filters).
+pub(crate) fn escape_filter_path(path: &str) -> String {
+    let option_level = escape_with_backslash(path, &['\\', '\'', ':']);
+    escape_with_backslash(&option_level, &['\\', '\'', '[', ']', ',', ';'])
+}
+
+// Containers that can hold several audio streams, for `separate_audio_stems`
+fn supports_multiple_audio_streams(container: Option<&str>) -> bool {
+    matches!(container, Some("matroska" | "mp4" | "mov" | "webm"))
+}
+
+// Whether an audio stream in `codec` can be stream-copied into `container`
+fn container_accepts_audio(container: Option<&str>, codec: &str) -> bool {
+    match container {
+        Some("matroska") => true,
+        Some("mp4" | "mov") => matches!(codec, "aac" | "mp3" | "ac3" | "eac3" | "alac" | "flac"),
+        Some("mpegts") => matches!(codec, "aac" | "mp3" | "ac3" | "eac3" | "opus"),
+        Some("webm") => matches!(codec, "opus" | "vorbis"),
+        _ => false,
+    }
+}
+
+// Muxer the output is written with: the `container` setting, else the one a pipe
+// needs, else what ffmpeg picks from the extension. None when it can't be told.
+pub(crate) fn output_container<'a>(settings: &'a ProjectSettings, output_path: &str) -> Option<&'a str> {
+    if let Some(container) = settings.get_container() {
+        return Some(container);
+    }
+    if is_pipe_output(output_path) {
+        return Some(pipe_container(output_path));
+    }
+    match Path::new(output_path).extension().map(|e| e.to_string_lossy().to_lowercase()).as_deref() {
+        Some("mkv") => Some("matroska"),
+        Some("mp4" | "m4v") => Some("mp4"),
+        Some("mov") => Some("mov"),
+        Some("webm") => Some("webm"),
+        Some("ts") => Some("mpegts"),
+        _ => None,
+    }
+}
+
+// Turns a clip's trim into input options (-ss from, -t length) and leaves it
+// untrimmed for the filter graph, which then sees a file starting at `from`
+fn seek_input(clip: &mut VideoClip, from: f64) -> (f64, Option<f64>) {
+    let end = clip.end.unwrap_or(clip.duration);
+    let length = clip.end.map(|e| (e - from).max(0.0));
+    clip.duration = (end - from).max(0.0);
+    clip.start = 0.0;
+    clip.end = None;
+    (from, length)
+}
+
+// Muted clips get generated silence so the acrossfade chain keeps one input per clip
+fn build_clip_audio_filter(clip: &VideoClip, index: usize, audio: AudioInput) -> String {
+    build_clip_audio_filter_to(clip, index, &format!("va{}", index), audio)
+}
+
+fn build_clip_audio_filter_to(clip: &VideoClip, index: usize, output: &str, audio: AudioInput) -> String {
+    if clip.mute_audio {
+        format!(
+            "anullsrc=channel_layout=stereo:sample_rate=48000,atrim=duration={}[{}]",
+            clip.get_effective_duration(),
+            output
+        )
+    } else {
+        // Reversed after the trim
+        let reverse = if clip.reverse { ",areverse" } else { "" };
+        if clip.is_trimmed() {
+            let range = match clip.end {
+                Some(end) => format!("start={}:end={}", clip.start, end),
+                None => format!("start={}", clip.start),
+            };
+            format!("{}atrim={},asetpts=PTS-STARTPTS{}[{}]", audio.source(index), range, reverse, output)
+        } else if clip.reverse {
+            format!("{}areverse[{}]", audio.source(index), output)
+        } else {
+            format!("{}anull[{}]", audio.source(index), output)
+        }
+    }
+}
+
+// Black frames and silence of `gap_before` seconds concatenated in front of clip
+// `i`. The clip's own chains must end in [vc{i}] / [vac{i}]; concat then outputs
+// the usual [v{i}] / [va{i}].
+fn build_gap_filter(clip: &VideoClip, i: usize, (w, h): (i32, i32), sar: &str, with_audio: bool, probes: &ClipProbes) -> String {
+    let fps = probes
+        .properties(&clip.path)
+        .map(|p| p.fps)
+        .filter(|f| *f > 0.0)
+        .unwrap_or(25.0);
+    let mut parts = vec![
+        format!("color=c=black:s={}x{}:r={}:d={},format=yuv420p,setsar={},settb=AVTB[vg{i}]", w, h, fps, clip.gap_before, sar),
+        format!("[vg{i}][vc{i}]concat=n=2:v=1:a=0[v{i}]"),
+    ];
+    if with_audio {
+        parts.push(format!("anullsrc=channel_layout=stereo:sample_rate=48000,atrim=duration={}[vag{i}]", clip.gap_before));
+        parts.push(format!("[vag{i}][vac{i}]concat=n=2:v=0:a=1[va{i}]"));
+    }
+    parts.join(";")
+}
+
+// Deinterlace, trim, color and LUT stages of one clip, before any framing.
+// `square_pixels` also stretches anamorphic clips to their displayed width, so
+// that setsar=1 downstream doesn't squeeze them.
+fn build_clip_video_filters(clip: &VideoClip, project_lut: Option<&str>, square_pixels: bool, probes: &ClipProbes) -> Vec<String> {
+    let mut video_filters: Vec<String> = Vec::new();
+    if clip.deinterlace {
+        video_filters.push("bwdif=mode=send_frame".to_string());
+    }
+    if clip.is_trimmed() {
+        let range = match clip.end {
+            Some(end) => format!("start={}:end={}", clip.start, end),
+            None => format!("start={}", clip.start),
+        };
+        video_filters.push(format!("trim={},setpts=PTS-STARTPTS", range));
+    }
+    if clip.reverse {
+        video_filters.push("reverse".to_string());
+    }
+    if let Some(eq) = clip.color.as_ref().and_then(|c| c.get_filter()) {
+        video_filters.push(eq);
+    }
+    if let Some(lut) = clip.lut.as_deref().or(project_lut) {
+        video_filters.push(format!("lut3d=file={}", escape_filter_path(lut)));
+    }
+    if square_pixels && probes.properties(&clip.path).is_some_and(|p| p.is_anamorphic()) {
+        video_filters.push("scale='trunc(iw*sar/2)*2':ih".to_string());
+    }
+    video_filters
+}
+
+// SAR every clip is normalized to: square pixels, or with `preserve` the first
+// clip's own ratio (xfade and the stacking filters need a common one)
+fn target_sar(clips: &[VideoClip], preserve: bool, probes: &ClipProbes) -> String {
+    clips
+        .first()
+        .filter(|_| preserve)
+        .and_then(|c| probes.properties(&c.path))
+        .filter(|p| p.is_anamorphic())
+        .and_then(|p| p.sample_aspect_ratio.as_deref())
+        .map(|r| r.replace(':', "/"))
+        .unwrap_or_else(|| "1".to_string())
+}
+
+// Two clips on one frame, sized from the main (first) clip: the second one scaled
+// into a corner (PiP), keyed out over the whole frame (chroma key), or both side
+// by side / on top of each other at a common height / width. Audio of both is mixed.
+fn build_composition_filter(
+    clips: &[VideoClip],
+    layout: &CompositionLayout,
+    project_lut: Option<&str>,
+    audio: Option<AudioInput>,
+    auto_rotate: bool,
+    sar: &str,
+    probes: &ClipProbes,
+) -> (String, String, String) {
+    let (w, h) = probes
+        .properties(&clips[0].path)
+        .filter(|p| p.width > 0 && p.height > 0)
+        .map(|p| p.frame_size(auto_rotate))
+        .map(|(w, h)| (w / 2 * 2, h / 2 * 2))
+        .unwrap_or((1920, 1080));
+    let chain = |i: usize, framing: String| {
+        let mut filters = build_clip_video_filters(&clips[i], project_lut, sar == "1", probes);
+        filters.push(framing);
+        format!("[{}:v]{}[v{}]", i, filters.join(","), i)
+    };
+
+    let mut parts = Vec::new();
+    let (combine, audio_duration) = match layout {
+        &CompositionLayout::Pip { position, scale } => {
+            let pip_width = ((w as f64 * scale.clamp(0.05, 1.0)) as i32 / 2 * 2).max(2);
+            parts.push(chain(0, format!("setsar={}", sar)));
+            parts.push(chain(1, format!("scale={}:-2,setsar={}", pip_width, sar)));
+            let (x, y) = position.get_overlay_xy();
+            (format!("overlay=x={}:y={}:eof_action=pass", x, y), "first")
+        }
+        CompositionLayout::SideBySide => {
+            parts.push(chain(0, format!("scale=-2:{},setsar={}", h, sar)));
+            parts.push(chain(1, format!("scale=-2:{},setsar={}", h, sar)));
+            ("hstack=inputs=2:shortest=1".to_string(), "shortest")
+        }
+        CompositionLayout::Stacked => {
+            parts.push(chain(0, format!("scale={}:-2,setsar={}", w, sar)));
+            parts.push(chain(1, format!("scale={}:-2,setsar={}", w, sar)));
+            ("vstack=inputs=2:shortest=1".to_string(), "shortest")
+        }
+        CompositionLayout::ChromaKey { color, similarity, blend } => {
+            parts.push(chain(0, format!("scale={}:{},setsar={}", w, h, sar)));
+            parts.push(chain(
+                1,
+                format!(
+                    "chromakey=color={}:similarity={}:blend={},scale={}:{},setsar={}",
+                    color,
+                    similarity.clamp(0.01, 1.0),
+                    blend.clamp(0.0, 1.0),
+                    w,
+                    h,
+                    sar
+                ),
+            ));
+            ("overlay=x=0:y=0:eof_action=pass".to_string(), "first")
+        }
+    };
+    parts.push(format!("[v0][v1]{},format=yuv420p[vcomp]", combine));
+    let Some(audio) = audio else {
+        return (parts.join(";"), "[vcomp]".to_string(), String::new());
+    };
+
+    parts.push(build_clip_audio_filter(&clips[0], 0, audio));
+    parts.push(build_clip_audio_filter(&clips[1], 1, audio));
+    parts.push(format!("[va0][va1]amix=inputs=2:duration={}:dropout_transition=0[vacomp]", audio_duration));
+
+    (parts.join(";"), "[vcomp]".to_string(), "[vacomp]".to_string())
+}
+
+// xfade needs every input at the same size: when decoded sizes differ (portrait
+// and landscape phone clips), the first clip's frame is used for all of them.
+// Gaps are generated at that size too.
+fn common_frame_size(clips: &[VideoClip], auto_rotate: bool, probes: &ClipProbes) -> Option<(i32, i32)> {
+    let sizes: Vec<(i32, i32)> = clips
+        .iter()
+        .filter_map(|c| probes.properties(&c.path))
+        .filter(|p| p.width > 0 && p.height > 0)
+        .map(|p| p.frame_size(auto_rotate))
+        .collect();
+    let (w, h) = *sizes.first()?;
+    let has_gaps = clips.iter().any(|c| c.gap_before > 0.0);
+    (has_gaps || sizes.iter().any(|s| *s != (w, h))).then_some((w / 2 * 2, h / 2 * 2))
+}
+
+// Without `audio` no audio stage is built at all and the audio tag is empty.
+// A None transition concatenates the clips with hard cuts, for builds without xfade.
+// The LUT, frame size and SAR follow `settings`.
+fn build_video_crossfade_filter(
+    clips: &[VideoClip],
+    crossfade_duration: f64,
+    transition: Option<(&str, TransitionAlign)>,
+    audio: Option<AudioInput>,
+    settings: &ProjectSettings,
+    probes: &ClipProbes,
+) -> (String, String, String) {
+    let project_lut = settings.lut.as_deref();
+    let fit_to = common_frame_size(clips, settings.auto_rotate, probes);
+    let sar = &target_sar(clips, settings.preserve_sar, probes);
     let n = clips.len();
+    let with_audio = audio.is_some();
     let mut parts: Vec<String> = Vec::new();
+    // concat outputs in AV_TIME_BASE, and xfade wants the same time base on both sides
+    let has_gaps = clips.iter().any(|c| c.gap_before > 0.0);
+
+    for (i, clip) in clips.iter().enumerate() {
+        let mut video_filters = build_clip_video_filters(clip, project_lut, sar == "1", probes);
+        if let Some((w, h)) = fit_to {
+            video_filters.push(format!("scale={w}:{h}:force_original_aspect_ratio=decrease,pad={w}:{h}:(ow-iw)/2:(oh-ih)/2"));
+        }
+        video_filters.push(format!("format=yuv420p,setsar={}", sar));
+        if has_gaps {
+            video_filters.push("settb=AVTB".to_string());
+        }
 
-    for i in 0..n {
-        parts.push(format!("[{}:v]format=yuv420p,setsar=1[v{}]", i, i));
-        parts.push(format!("[{}:a]anull[va{}]", i, i));
+        if clip.gap_before > 0.0 {
+            parts.push(format!("[{}:v]{}[vc{}]", i, video_filters.join(","), i));
+            if let Some(audio) = audio {
+                parts.push(build_clip_audio_filter_to(clip, i, &format!("vac{}", i), audio));
+            }
+            parts.push(build_gap_filter(clip, i, fit_to.unwrap_or((1920, 1080)), sar, with_audio, probes));
+        } else {
+            parts.push(format!("[{}:v]{}[v{}]", i, video_filters.join(","), i));
+            if let Some(audio) = audio {
+                parts.push(build_clip_audio_filter(clip, i, audio));
+            }
+        }
     }
 
+    let audio_tag = |tag: &str| if with_audio { format!("[{}]", tag) } else { String::new() };
     if n == 1 {
-        return (parts.join(";"), "[v0]".to_string(), "[va0]".to_string());
+        return (parts.join(";"), "[v0]".to_string(), audio_tag("va0"));
     }
+    let Some((transition, align)) = transition else {
+        let inputs: String = (0..n)
+            .map(|i| if with_audio { format!("[v{i}][va{i}]") } else { format!("[v{i}]") })
+            .collect();
+        parts.push(format!("{}concat=n={}:v=1:a={}[vcat]{}", inputs, n, with_audio as u8, audio_tag("vacat")));
+        return (parts.join(";"), "[vcat]".to_string(), audio_tag("vacat"));
+    };
 
-    let mut acc = clips[0].duration;
-    let mut prev_v = "v0".to_string();
-    let mut prev_a = "va0".to_string();
+    let durations = get_transition_durations(clips, crossfade_duration);
+    let markers = get_timeline_markers(clips, crossfade_duration, align);
+    // Centered transitions hold each clip's edge frames through its half of the fades
+    let mut video_tags: Vec<String> = (0..n).map(|i| format!("v{}", i)).collect();
+    let mut audio_tags: Vec<String> = (0..n).map(|i| format!("va{}", i)).collect();
+    for (i, (head, tail)) in get_transition_pads(clips, crossfade_duration, align).into_iter().enumerate() {
+        if head <= 0.0 && tail <= 0.0 {
+            continue;
+        }
+        parts.push(format!(
+            "[v{i}]tpad=start_mode=clone:start_duration={head}:stop_mode=clone:stop_duration={tail}[vh{i}]"
+        ));
+        video_tags[i] = format!("vh{}", i);
+        if with_audio {
+            parts.push(format!("[va{i}]adelay=delays={}:all=1,apad=pad_dur={tail}[vah{i}]", (head * 1000.0).round()));
+            audio_tags[i] = format!("vah{}", i);
+        }
+    }
+    let mut prev_v = video_tags[0].clone();
+    let mut prev_a = audio_tags[0].clone();
 
     for j in 1..n {
-        let off = (acc - crossfade_duration).max(0.0);
+        let crossfade_duration = durations[j - 1];
+        let off = markers[j].start;
         let vo = format!("vx{}", j);
         let ao = format!("vax{}", j);
         parts.push(format!(
-            "[{}][v{}]xfade=transition=fade:duration={}:offset={}[{}]",
-            prev_v, j, crossfade_duration, off, vo
-        ));
-        parts.push(format!(
-            "[{}][va{}]acrossfade=d={}:c1=qsin:c2=qsin[{}]",
-            prev_a, j, crossfade_duration, ao
+            "[{}][{}]xfade=transition={}:duration={}:offset={}[{}]",
+            prev_v, video_tags[j], transition, crossfade_duration, off, vo
         ));
+        // acrossfade reads d=0 as "use nb_samples" (a full second), so cuts are concatenated
+        if with_audio && crossfade_duration > 0.0 {
+            parts.push(format!(
+                "[{}][{}]acrossfade=d={}:c1=qsin:c2=qsin[{}]",
+                prev_a, audio_tags[j], crossfade_duration, ao
+            ));
+        } else if with_audio {
+            parts.push(format!("[{}][{}]concat=n=2:v=0:a=1[{}]", prev_a, audio_tags[j], ao));
+        }
         prev_v = vo;
         prev_a = ao;
-        acc += (clips[j].duration - crossfade_duration).max(0.0);
     }
 
-    (parts.join(";"), format!("[{}]", prev_v), format!("[{}]", prev_a))
+    (parts.join(";"), format!("[{}]", prev_v), audio_tag(&prev_a))
+}
+
+// Flags clips whose stream reports an interlaced field order
+fn resolve_deinterlacing(clips: &[VideoClip], auto_detect: bool, probes: &ClipProbes) -> Vec<VideoClip> {
+    clips
+        .iter()
+        .map(|clip| {
+            let mut clip = clip.clone();
+            if auto_detect && !clip.deinterlace {
+                clip.deinterlace = probes.properties(&clip.path).is_some_and(|p| p.is_interlaced());
+            }
+            clip
+        })
+        .collect()
+}
+
+// Range conversion of the composed picture. The input range is the first clip's
+// when ffprobe knows it, else whatever the frames are tagged with.
+fn build_color_range_filter(clips: &[VideoClip], range: ColorRange, probes: &ClipProbes) -> String {
+    let input = clips
+        .first()
+        .and_then(|c| probes.properties(&c.path))
+        .and_then(|p| p.color_range)
+        .map_or("auto", |r| r.ffmpeg_name());
+    format!("scale=in_range={}:out_range={}", input, range.ffmpeg_name())
+}
+
+// minterpolate only helps when frames have to be synthesized, i.e. the target
+// rate exceeds every source; otherwise a plain fps conversion is enough.
+fn build_frame_rate_filter(clips: &[VideoClip], fps: f64, mode: Option<InterpMode>, probes: &ClipProbes) -> String {
+    let max_source_fps = || {
+        clips
+            .iter()
+            .filter_map(|c| probes.properties(&c.path))
+            .map(|p| p.fps)
+            .fold(0.0, f64::max)
+    };
+
+    match mode {
+        Some(InterpMode::Blend) if fps > max_source_fps() => format!("minterpolate=fps={}:mi_mode=blend", fps),
+        Some(InterpMode::Mci) if fps > max_source_fps() => {
+            format!("minterpolate=fps={}:mi_mode=mci:mc_mode=aobmc:me_mode=bidir:vsbmc=1", fps)
+        }
+        _ => format!("fps={}", fps),
+    }
 }
 
 #[tauri::command]
@@ -343,10 +1768,78 @@ pub fn build_export_command(
     use_gpu: bool,
     speed_preset: String,
 ) -> Vec<String> {
-    let mut ffmpeg = state.ffmpeg.lock().unwrap();
+    let overwrite = project.settings.overwrite;
+    export_command(&state, project, output_path, preview_seconds, use_gpu, speed_preset, overwrite)
+}
+
+// Previews and graph checks pass `Always`: their temp files are theirs to replace
+fn export_command(
+    state: &AppState,
+    project: Project,
+    output_path: String,
+    preview_seconds: Option<i32>,
+    use_gpu: bool,
+    speed_preset: String,
+    overwrite: OverwritePolicy,
+) -> Vec<String> {
+    let mut project = project;
+    let (gpu_type, support) = {
+        let mut ffmpeg = state.ffmpeg.lock().unwrap();
+        (if use_gpu { ffmpeg.detect_gpu_encoder() } else { None }, ffmpeg.crossfade_support())
+    };
+    support.degrade(&mut project);
+    build_export_args(&project, output_path, preview_seconds, gpu_type, speed_preset, support, overwrite)
+}
+
+// Runs the project's real filter graph on a tenth of a second of its inputs into
+// a null output, so graph errors (unknown filter or transition, unconnected or
+// mismatched labels, xfade size mismatches) show up before a long render.
+// The error is ffmpeg's own line, e.g. "No such filter: 'fadee'".
+#[tauri::command]
+pub async fn validate_filter_graph(state: State<'_, AppState>, project: Project) -> Result<(), String> {
+    let output = get_temp_dir().join(format!("validate_{}.mkv", std::process::id()));
+    let mut cmd = export_command(&state, project, output.to_string_lossy().to_string(), None, false, "ultrafast".to_string(), OverwritePolicy::Always);
+    cmd.pop();
+    cmd.splice(1..1, ["-nostdin", "-v", "error"].map(String::from));
+    cmd.extend(["-t", "0.1", "-f", "null", "-"].map(String::from));
+
+    let result = Command::new(&cmd[0])
+        .args(&cmd[1..])
+        .stdout(Stdio::null())
+        .output()
+        .map_err(|e| format!("Impossible de lancer ffmpeg: {}", e))?;
+    if result.status.success() {
+        return Ok(());
+    }
+
+    let stderr = String::from_utf8_lossy(&result.stderr);
+    let lines: Vec<&str> = stderr.lines().map(str::trim).filter(|l| !l.is_empty()).collect();
+    let markers = ["No such filter", "Invalid", "not found", "matches no streams", "do not match", "unconnected", "Error"];
+    let line = lines
+        .iter()
+        .find(|l| markers.iter().any(|m| l.contains(m)))
+        .or(lines.first())
+        .copied()
+        .unwrap_or("erreur inconnue");
+    Err(format!("Graphe de filtres invalide: {}", line))
+}
+
+// The export command line for an already detected encoder, without app state
+pub(crate) fn build_export_args(
+    project: &Project,
+    output_path: String,
+    preview_seconds: Option<i32>,
+    gpu_type: Option<String>,
+    speed_preset: String,
+    support: CrossfadeSupport,
+    overwrite: OverwritePolicy,
+) -> Vec<String> {
+    let project = &project.exported_project();
     let settings = &project.settings;
 
-    let active_tracks: Vec<&AudioTrack> = if settings.include_music {
+    // mute_all overrides every other audio option: no audio stage, -an
+    let include_video_audio = settings.include_video_audio && !settings.mute_all;
+    let active_tracks: Vec<&AudioTrack> = if settings.include_music && !settings.mute_all {
         let solos: Vec<_> = project.audio_tracks.iter().filter(|t| t.solo).collect();
         if !solos.is_empty() {
             solos.into_iter().filter(|t| !t.mute).collect()
@@ -358,59 +1851,269 @@ pub fn build_export_command(
     };
 
     let video_volume = settings.video_volume / 100.0;
-    let gpu_type = if use_gpu { ffmpeg.detect_gpu_encoder() } else { None };
 
-    let mut cmd = vec!["ffmpeg".to_string(), "-y".to_string()];
+    let mut cmd = vec!["ffmpeg".to_string(), overwrite_flag(overwrite, &output_path).to_string()];
+    if let Some(n) = settings.get_encoder_threads() {
+        cmd.extend(["-filter_threads".to_string(), n.to_string()]);
+    }
 
-    // Hardware acceleration flags
-    if let Some(ref gt) = gpu_type {
-        match gt.as_str() {
-            "nvidia" => cmd.extend(["-hwaccel".to_string(), "cuda".to_string()]),
-            "intel" => cmd.extend(["-hwaccel".to_string(), "qsv".to_string()]),
-            "vaapi" => cmd.extend(["-vaapi_device".to_string(), "/dev/dri/renderD128".to_string()]),
-            _ => {}
+    cmd.extend(hwaccel_args(gpu_type.as_deref()));
+
+    // A composition replaces the linear timeline as the video source
+    let composition = project.composition.as_ref().filter(|c| c.clips.len() == 2);
+    let sources = composition.map_or(&project.videos, |c| &c.clips);
+    let probes = ClipProbes::of(sources.iter().map(|v| v.path.as_str()).chain(active_tracks.iter().map(|t| t.path.as_str())));
+    let mut videos = resolve_deinterlacing(sources, settings.auto_deinterlace, &probes);
+    let resolution = settings.get_output_resolution();
+    let needs_filters = composition.is_some()
+        || project.videos.len() > 1
+        || get_transition_durations(&project.videos, settings.video_crossfade).iter().any(|d| *d > 0.0)
+        || videos.iter().any(|v| v.needs_filtering())
+        || settings.lut.is_some()
+        || resolution.is_some()
+        || settings.get_output_fps().is_some()
+        || settings.burn_timecode
+        || settings.video_fade_in > 0.0
+        || settings.video_fade_out > 0.0
+        || settings.get_output_dar().is_some()
+        || settings.color_range.is_some()
+        || project.get_export_range().is_some()
+        || project.get_hold_duration() > 0.0
+        // A stream copy would keep the source's own profile and level
+        || settings.get_h264_profile().is_some()
+        || settings.get_h264_level().is_some();
+    let mut must_reencode = needs_filters || videos.iter().any(|v| v.is_trimmed());
+
+    // Trims done by seeking the input rather than with the trim filter, per clip
+    let mut input_seeks: Vec<Option<(f64, Option<f64>)>> = vec![None; videos.len()];
+
+    // A lone trimmed clip can still be stream-copied by seeking its input, as long
+    // as the cut starts on a keyframe: the start moves back to the previous one.
+    if must_reencode && !needs_filters && settings.snap_to_keyframes {
+        if let Some(keyframe) = FFmpegProcessor::probe_keyframe_before(&videos[0].path, videos[0].start) {
+            input_seeks[0] = Some(seek_input(&mut videos[0], keyframe));
+            must_reencode = false;
+        }
+    }
+    if settings.trim_accuracy == TrimAccuracy::Fast {
+        for (clip, seek) in videos.iter_mut().zip(input_seeks.iter_mut()) {
+            if seek.is_none() && clip.is_trimmed() {
+                let start = clip.start;
+                *seek = Some(seek_input(clip, start));
+            }
         }
     }
 
-    // Add inputs
-    for v in &project.videos {
+    let container = output_container(settings, &output_path);
+
+    // A plain remux of one clip's own audio: copied as is rather than re-encoded.
+    // webm is left out since its video is always re-encoded to VP9.
+    let copy_audio = settings.copy_audio_when_possible
+        && !must_reencode
+        && videos.len() == 1
+        && active_tracks.is_empty()
+        && include_video_audio
+        && settings.audio_codec.is_none()
+        && !settings.force_stereo
+        && video_volume == 1.0
+        && !videos[0].mute_audio
+        && !videos[0].is_trimmed()
+        && container != Some("webm")
+        && probes
+            .properties(&videos[0].path)
+            .and_then(|p| p.audio_codec.as_deref())
+            .is_some_and(|codec| container_accepts_audio(container, codec));
+
+    // Add inputs; ffmpeg applies the rotation metadata itself unless told not to
+    for (v, seek) in videos.iter().zip(&input_seeks) {
+        if !settings.auto_rotate {
+            cmd.push("-noautorotate".to_string());
+        }
+        if let Some((from, length)) = *seek {
+            cmd.extend(["-ss".to_string(), from.to_string()]);
+            if let Some(length) = length {
+                cmd.extend(["-t".to_string(), length.to_string()]);
+            }
+        }
+        if probes.needs_deep_probe(&v.path) {
+            cmd.extend(DEEP_PROBE_ARGS.map(String::from));
+        }
         cmd.extend(["-i".to_string(), v.path.clone()]);
     }
     for t in &active_tracks {
+        if probes.needs_deep_probe(&t.path) {
+            cmd.extend(DEEP_PROBE_ARGS.map(String::from));
+        }
         cmd.extend(["-i".to_string(), t.path.clone()]);
     }
+    let poster = settings
+        .poster_image
+        .as_deref()
+        .filter(|_| supports_cover_art(&output_path, output_container(settings, &output_path)));
+    if let Some(poster) = poster {
+        cmd.extend(["-i".to_string(), poster.to_string()]);
+    }
 
     // Build filter complex
+    let sar = target_sar(&videos, settings.preserve_sar, &probes);
+    let audio_input = AudioInput::from_settings(settings);
+    let clip_audio = Some(audio_input).filter(|_| !settings.mute_all);
     let mut fc_parts: Vec<String> = Vec::new();
-    let must_reencode = project.videos.len() > 1 || settings.video_crossfade > 0.0;
+    let (vfc, mut tag_vout, tag_vaout) = if let Some(composition) = composition {
+        build_composition_filter(
+            &videos,
+            &composition.layout,
+            settings.lut.as_deref(),
+            clip_audio,
+            settings.auto_rotate,
+            &sar,
+            &probes,
+        )
+    } else if must_reencode || videos.is_empty() {
+        build_video_crossfade_filter(
+            &videos,
+            settings.video_crossfade,
+            Some((settings.get_video_transition(), settings.transition_align))
+                .filter(|_| !support.video_hard_cuts(!settings.mute_all)),
+            clip_audio,
+            settings,
+            &probes,
+        )
+    } else if settings.mute_all || copy_audio {
+        (String::new(), String::new(), String::new())
+    } else {
+        // Stream copy: video is mapped straight from the input, only the audio is filtered
+        (build_clip_audio_filter(&videos[0], 0, audio_input), String::new(), "[va0]".to_string())
+    };
+    if !vfc.is_empty() {
+        fc_parts.push(vfc);
+    }
+
+    // Fit into the target frame, letterboxing when the aspect ratio differs
+    if let Some((w, h)) = resolution {
+        fc_parts.push(format!(
+            "{}scale={w}:{h}:force_original_aspect_ratio=decrease,pad={w}:{h}:(ow-iw)/2:(oh-ih)/2,setsar={}[vscaled]",
+            tag_vout,
+            sar
+        ));
+        tag_vout = "[vscaled]".to_string();
+    }
+
+    if let Some(fps) = settings.get_output_fps() {
+        fc_parts.push(format!("{}{}[vfps]", tag_vout, build_frame_rate_filter(&videos, fps, settings.interpolation_mode, &probes)));
+        tag_vout = "[vfps]".to_string();
+    }
+
+    if settings.burn_timecode {
+        // The timecode counts frames, so it needs the rate of the output stream
+        let fps = settings
+            .get_output_fps()
+            .or_else(|| videos.first().and_then(|v| probes.properties(&v.path)).map(|p| p.fps))
+            .filter(|f| *f > 0.0)
+            .unwrap_or(25.0);
+        let (x, y) = settings.timecode_position.get_drawtext_xy();
+        fc_parts.push(format!(
+            "{}drawtext=timecode='00\\:00\\:00\\:00':rate={}:fontsize={}:fontcolor=white:box=1:boxcolor=black@0.5:boxborderw=6:x={}:y={}[vtc]",
+            tag_vout,
+            fps,
+            settings.timecode_font_size.max(1),
+            x,
+            y
+        ));
+        tag_vout = "[vtc]".to_string();
+    }
+
+    let hold = project.get_hold_duration();
+    if hold > 0.0 {
+        fc_parts.push(format!("{}tpad=stop_mode=clone:stop_duration={}[vhold]", tag_vout, hold));
+        tag_vout = "[vhold]".to_string();
+    }
+
+    // From the clips as cut, keyframe snapping may have moved a start back
+    let video_duration = hold
+        + match composition {
+            Some(composition) => composition.get_duration(),
+            None => get_timeline_duration(&videos, settings.video_crossfade, settings.transition_align),
+        };
+
+    // Fades on the whole composed picture, at the edges of what gets exported
+    if settings.video_fade_in > 0.0 || settings.video_fade_out > 0.0 {
+        let (start, end) = project.get_export_range().unwrap_or((0.0, video_duration));
+        let mut fades = Vec::new();
+        if settings.video_fade_in > 0.0 {
+            fades.push(format!("fade=t=in:st={}:d={}", start, settings.video_fade_in));
+        }
+        if settings.video_fade_out > 0.0 {
+            let d = settings.video_fade_out.min(end - start);
+            fades.push(format!("fade=t=out:st={}:d={}", (end - d).max(start), d));
+        }
+        fc_parts.push(format!("{}{}[vfade]", tag_vout, fades.join(",")));
+        tag_vout = "[vfade]".to_string();
+    }
 
-    let (vfc, tag_vout, tag_vaout) = build_video_crossfade_filter(&project.videos, settings.video_crossfade);
-    fc_parts.push(vfc);
-    fc_parts.push(format!("{}volume={}[va]", tag_vaout, video_volume));
+    if let Some(dar) = settings.get_output_dar() {
+        fc_parts.push(format!("{}setdar={}[vdar]", tag_vout, dar));
+        tag_vout = "[vdar]".to_string();
+    }
+
+    if let Some(range) = settings.color_range {
+        fc_parts.push(format!("{}{}[vrange]", tag_vout, build_color_range_filter(&videos, range, &probes)));
+        tag_vout = "[vrange]".to_string();
+    }
+
+    if !tag_vaout.is_empty() {
+        // Silence under the held frame, so amix=first doesn't stop the music early
+        let pad = if hold > 0.0 { format!(",apad=pad_dur={}", hold) } else { String::new() };
+        fc_parts.push(format!("{}volume={}{}[va]", tag_vaout, video_volume, pad));
+    }
 
     let mut tag_music = String::new();
     if !active_tracks.is_empty() {
-        let base_idx = project.videos.len();
+        let base_idx = videos.len();
         let owned_tracks: Vec<AudioTrack> = active_tracks.iter().map(|t| (*t).clone()).collect();
-        let (cf, tm) = build_audio_crossfade_filter(&owned_tracks, settings.audio_crossfade as i32, base_idx);
+        let (cf, tm) = if settings.audio_merge && owned_tracks.len() > 1 {
+            build_audio_merge_filter(&owned_tracks, base_idx)
+        } else {
+            build_audio_crossfade_filter(
+                &owned_tracks,
+                settings.audio_crossfade,
+                base_idx,
+                !support.acrossfade,
+                audio_input,
+            )
+        };
         fc_parts.push(cf);
-        tag_music = tm;
+        fc_parts.push(format!("{}volume={}[mvol]", tm, settings.music_volume / 100.0));
+        tag_music = "[mvol]".to_string();
 
         if settings.cut_music_at_end {
-            let video_duration = project.get_video_duration();
             fc_parts.push(format!("{}atrim=duration={}[mus]", tag_music, video_duration));
             tag_music = "[mus]".to_string();
         }
     }
 
+    // Stems: video audio and music as two streams instead of one mix
+    let stems = settings.separate_audio_stems
+        && include_video_audio
+        && !tag_music.is_empty()
+        && supports_multiple_audio_streams(container);
+
     // Audio mixing
-    let tag_final_audio = if settings.include_video_audio && !tag_music.is_empty() {
+    let tag_final_audio = if stems {
+        String::new()
+    } else if copy_audio {
+        "0:a:0".to_string()
+    } else if include_video_audio && !tag_music.is_empty() {
+        let weights = settings.get_amix_weights().map(|(v, m)| format!(":weights='{} {}'", v, m)).unwrap_or_default();
         fc_parts.push(format!(
-            "[va]{}amix=inputs=2:duration=longest:dropout_transition=0[aout]",
-            tag_music
+            "[va]{}amix=inputs=2:duration={}:dropout_transition={}{}[aout]",
+            tag_music,
+            settings.get_amix_duration(),
+            settings.get_amix_dropout_transition(),
+            weights
         ));
         "[aout]".to_string()
-    } else if settings.include_video_audio {
+    } else if include_video_audio {
         "[va]".to_string()
     } else if !tag_music.is_empty() {
         tag_music.clone()
@@ -424,53 +2127,161 @@ pub fn build_export_command(
 
     // Mapping
     cmd.extend(["-map".to_string(), if !tag_vout.is_empty() { tag_vout } else { "0:v:0".to_string() }]);
-    if !tag_final_audio.is_empty() {
+    if stems {
+        cmd.extend(["-map".to_string(), "[va]".to_string(), "-map".to_string(), tag_music.clone()]);
+        cmd.extend(["-metadata:s:a:0".to_string(), "title=Audio video".to_string()]);
+        cmd.extend(["-metadata:s:a:1".to_string(), "title=Musique".to_string()]);
+    } else if !tag_final_audio.is_empty() {
         cmd.extend(["-map".to_string(), tag_final_audio]);
     } else {
         cmd.push("-an".to_string());
     }
 
-    // Codecs
-    if output_path.to_lowercase().ends_with(".webm") {
-        cmd.extend(["-c:v".to_string(), "libvpx-vp9".to_string(), "-b:v".to_string(), "0".to_string(), "-crf".to_string(), "30".to_string()]);
-        cmd.extend(["-c:a".to_string(), "libvorbis".to_string()]);
-    } else {
-        if must_reencode {
-            let effective_preset = if preview_seconds.is_some() { "ultrafast" } else { &speed_preset };
+    let effective_preset = if preview_seconds.is_some() { "ultrafast" } else { &speed_preset };
+    cmd.extend(output_encoding_args(settings, &output_path, gpu_type.as_deref(), effective_preset, must_reencode, copy_audio));
+    if poster.is_some() {
+        cmd.extend(poster_args(videos.len() + active_tracks.len(), 1));
+    }
 
-            if let Some(ref gt) = gpu_type {
-                let (encoder, preset_flag, presets) = get_encoder_config(gt);
-                cmd.extend(["-c:v".to_string(), encoder.to_string()]);
+    // Output-side seeking: the whole graph still runs from zero so crossfade
+    // offsets are unchanged, frames before `start` are just dropped
+    if let Some((start, end)) = project.get_export_range() {
+        cmd.extend(["-ss".to_string(), start.to_string(), "-to".to_string(), end.to_string()]);
+    }
 
-                if let (Some(flag), Some(preset_val)) = (preset_flag, presets.get(effective_preset)) {
-                    cmd.extend([flag.to_string(), preset_val.to_string()]);
-                }
+    if let Some(secs) = preview_seconds {
+        cmd.extend(["-t".to_string(), secs.to_string()]);
+    }
+    cmd.push(output_path);
+    cmd
+}
 
-                match gt.as_str() {
-                    "nvidia" => cmd.extend(["-rc".to_string(), "vbr".to_string(), "-cq".to_string(), "20".to_string(), "-b:v".to_string(), "0".to_string()]),
-                    "amd" => cmd.extend(["-rc".to_string(), "vbr_latency".to_string(), "-qp_p".to_string(), "20".to_string(), "-qp_i".to_string(), "20".to_string()]),
-                    "intel" => cmd.extend(["-global_quality".to_string(), "20".to_string(), "-look_ahead".to_string(), "1".to_string()]),
-                    _ => cmd.extend(["-qp".to_string(), "20".to_string()]),
-                }
-            } else {
-                let (encoder, preset_flag, presets) = get_encoder_config("cpu");
-                cmd.extend(["-c:v".to_string(), encoder.to_string()]);
-                if let (Some(flag), Some(preset_val)) = (preset_flag, presets.get(effective_preset)) {
-                    cmd.extend([flag.to_string(), preset_val.to_string()]);
-                }
-                cmd.extend(["-crf".to_string(), "20".to_string()]);
-            }
+// Codecs, encoder limits and muxer options of an export or a transcode. Without
+// `reencode` the video is stream-copied; `copy_audio` copies the audio too.
+fn output_encoding_args(
+    settings: &ProjectSettings,
+    output_path: &str,
+    gpu_type: Option<&str>,
+    preset: &str,
+    reencode: bool,
+    copy_audio: bool,
+) -> Vec<String> {
+    let container = output_container(settings, output_path);
+    let mut cmd = Vec::new();
+    if container == Some("webm") {
+        cmd.extend(["-c:v".to_string(), "libvpx-vp9".to_string(), "-b:v".to_string(), "0".to_string(), "-crf".to_string(), "30".to_string()]);
+        // WebM only carries Opus or Vorbis; Opus is the default, Vorbis for very old players
+        let codec = match settings.audio_codec.as_deref() {
+            Some("libvorbis") => "libvorbis",
+            _ => "libopus",
+        };
+        cmd.extend(["-c:a".to_string(), codec.to_string()]);
+        cmd.extend(["-b:a".to_string(), settings.audio_bitrate.clone().unwrap_or_else(|| "160k".to_string())]);
+    } else {
+        if reencode {
+            cmd.extend(video_encoder_args(gpu_type, preset, settings));
         } else {
             cmd.extend(["-c:v".to_string(), "copy".to_string()]);
         }
 
-        cmd.extend(["-c:a".to_string(), "aac".to_string(), "-b:a".to_string(), "192k".to_string()]);
+        if copy_audio {
+            cmd.extend(["-c:a".to_string(), "copy".to_string()]);
+        } else {
+            cmd.extend(["-c:a".to_string(), settings.get_audio_codec().to_string()]);
+            if let Some(bitrate) = settings.get_audio_bitrate() {
+                cmd.extend(["-b:a".to_string(), bitrate.to_string()]);
+            }
+
+            // Opus in MP4 is still flagged experimental by older ffmpeg builds
+            if settings.get_audio_codec() == "libopus" && matches!(container, Some("mp4" | "mov")) {
+                cmd.extend(["-strict".to_string(), "experimental".to_string()]);
+            }
+        }
     }
 
-    if let Some(secs) = preview_seconds {
-        cmd.extend(["-t".to_string(), secs.to_string()]);
+    if let Some(n) = settings.get_encoder_threads() {
+        cmd.extend(["-threads".to_string(), n.to_string()]);
+    }
+    cmd.extend(["-max_muxing_queue_size".to_string(), settings.get_max_muxing_queue_size().to_string()]);
+    if let Some(range) = settings.color_range {
+        cmd.extend(["-color_range".to_string(), range.ffmpeg_name().to_string()]);
+    }
+
+    if let Some(ref bitrate) = settings.video_bitrate {
+        if reencode || container == Some("webm") {
+            cmd.extend(["-maxrate".to_string(), bitrate.clone(), "-bufsize".to_string(), bitrate.clone()]);
+        }
+    }
+
+    // Upload platforms start playback before the whole file is in
+    if settings.platform_target.is_some() && matches!(container, Some("mp4" | "mov")) && !is_pipe_output(output_path) {
+        cmd.extend(["-movflags".to_string(), "+faststart".to_string()]);
     }
 
+    if settings.get_container().is_some() || is_pipe_output(output_path) {
+        cmd.extend(["-f".to_string(), container.unwrap_or("matroska").to_string()]);
+    }
+    cmd
+}
+
+// One file re-encoded with the project's output settings (encoder, size, frame
+// rate, bitrates, container), without any timeline: no trims, transitions or music
+pub(crate) fn build_transcode_args(
+    input: &str,
+    output_path: String,
+    settings: &ProjectSettings,
+    gpu_type: Option<String>,
+    speed_preset: String,
+) -> Vec<String> {
+    let mut settings = settings.clone();
+    if let Some(platform) = settings.platform_target {
+        platform.apply_defaults(&mut settings);
+    }
+    let settings = &settings;
+
+    let mut cmd = vec!["ffmpeg".to_string(), overwrite_flag(settings.overwrite, &output_path).to_string()];
+    if let Some(n) = settings.get_encoder_threads() {
+        cmd.extend(["-filter_threads".to_string(), n.to_string()]);
+    }
+    cmd.extend(hwaccel_args(gpu_type.as_deref()));
+    if !settings.auto_rotate {
+        cmd.push("-noautorotate".to_string());
+    }
+    let probes = ClipProbes::of([input]);
+    if probes.needs_deep_probe(input) {
+        cmd.extend(DEEP_PROBE_ARGS.map(String::from));
+    }
+    cmd.extend(["-i".to_string(), input.to_string()]);
+    let source = [VideoClip { path: input.to_string(), ..VideoClip::default() }];
+
+    let mut filters = Vec::new();
+    if let Some((w, h)) = settings.get_output_resolution() {
+        filters.push(format!("scale={w}:{h}:force_original_aspect_ratio=decrease,pad={w}:{h}:(ow-iw)/2:(oh-ih)/2,setsar=1"));
+    }
+    if let Some(fps) = settings.get_output_fps() {
+        filters.push(build_frame_rate_filter(&source, fps, settings.interpolation_mode, &probes));
+    }
+    if let Some(dar) = settings.get_output_dar() {
+        filters.push(format!("setdar={}", dar));
+    }
+    if let Some(range) = settings.color_range {
+        filters.push(build_color_range_filter(&source, range, &probes));
+    }
+    if !filters.is_empty() {
+        cmd.extend(["-vf".to_string(), filters.join(",")]);
+    }
+    if settings.force_stereo && !settings.mute_all {
+        cmd.extend(["-ac".to_string(), "2".to_string()]);
+    }
+
+    cmd.extend(["-map".to_string(), "0:v:0".to_string()]);
+    if settings.mute_all {
+        cmd.push("-an".to_string());
+    } else {
+        cmd.extend(["-map".to_string(), "0:a:0?".to_string()]);
+    }
+
+    cmd.extend(output_encoding_args(settings, &output_path, gpu_type.as_deref(), &speed_preset, true, false));
     cmd.push(output_path);
     cmd
 }
@@ -484,85 +2295,689 @@ pub async fn export_project(
     use_gpu: bool,
     speed_preset: String,
 ) -> Result<ExportResult, String> {
-    CANCEL_FLAG.store(false, Ordering::SeqCst);
-    let start_time = Instant::now();
-
-    let gpu_type = {
-        let mut ffmpeg = state.ffmpeg.lock().unwrap();
-        if use_gpu { ffmpeg.detect_gpu_encoder() } else { None }
-    };
+    export_with_events(&app, &state, project, output_path, use_gpu, speed_preset)
+}
 
-    let encoder = gpu_type.as_ref().map(|g| match g.as_str() {
-        "nvidia" => "h264_nvenc",
-        "amd" => "h264_amf",
-        "intel" => "h264_qsv",
-        "vaapi" => "h264_vaapi",
-        _ => "libx264",
-    }).unwrap_or("libx264");
+// export_project for any event sink, also used by the headless `export` subcommand
+pub(crate) fn export_with_events(
+    events: &dyn EventSink,
+    state: &AppState,
+    project: Project,
+    output_path: String,
+    use_gpu: bool,
+    speed_preset: String,
+) -> Result<ExportResult, String> {
+    let _guard = ExportGuard::acquire(&state.is_exporting)?;
+    state.export_process.reset();
+    run_export(events, state, project, output_path, use_gpu, speed_preset, ProgressPhase::FULL)
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BatchExport {
+    pub project: Project,
+    pub output_path: String,
+}
 
-    let mut cmd = build_export_command(
-        state.clone(),
+// Exports each item in turn, stopping at the first cancellation; an item that
+// can't start is reported as failed and the queue goes on. Progress is reported
+// per item and across the whole batch (see `ProgressAggregator`).
+#[tauri::command]
+pub async fn export_batch(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    items: Vec<BatchExport>,
+    use_gpu: bool,
+    speed_preset: String,
+) -> Result<Vec<ExportResult>, String> {
+    let _guard = ExportGuard::acquire(&state.is_exporting)?;
+    state.export_process.reset();
+    // Weighed as run_export will render them, without the overlaps it drops
+    let support = state.ffmpeg.lock().unwrap().crossfade_support();
+    let durations: Vec<f64> = items
+        .iter()
+        .map(|i| {
+            let mut project = i.project.clone();
+            support.degrade(&mut project);
+            project.get_export_duration()
+        })
+        .collect();
+    let aggregator = ProgressAggregator::new(&durations);
+
+    let mut results = Vec::with_capacity(items.len());
+    for (index, item) in items.into_iter().enumerate() {
+        let start_time = Instant::now();
+        let result = run_export(
+            &app,
+            &state,
+            item.project,
+            item.output_path,
+            use_gpu,
+            speed_preset.clone(),
+            aggregator.phase(index),
+        )
+        .unwrap_or_else(|e| ExportResult::failed(e, start_time));
+        let cancelled = result.cancelled;
+        results.push(result);
+        if cancelled {
+            break;
+        }
+    }
+    Ok(results)
+}
+
+fn run_export(
+    events: &dyn EventSink,
+    state: &AppState,
+    project: Project,
+    output_path: String,
+    use_gpu: bool,
+    speed_preset: String,
+    phase: ProgressPhase,
+) -> Result<ExportResult, String> {
+    let start_time = Instant::now();
+
+    let mut project = project;
+    let (gpu_type, support) = {
+        let mut ffmpeg = state.ffmpeg.lock().unwrap();
+        (if use_gpu { ffmpeg.detect_gpu_encoder() } else { None }, ffmpeg.crossfade_support())
+    };
+    // Before the duration is read for progress, which grows without the overlaps
+    let warnings = support.degrade(&mut project);
+
+    let encoder = gpu_type.as_deref().map(get_gpu_encoder_name).unwrap_or("libx264");
+    let output_path = resolve_output_path(&project, &output_path, encoder)?;
+    let inputs: Vec<&str> = project
+        .media_paths()
+        .map(String::as_str)
+        .chain(project.settings.poster_image.as_deref())
+        .chain(project.settings.lut.as_deref())
+        .chain(project.videos.iter().filter_map(|v| v.lut.as_deref()))
+        .collect();
+    let output_path = apply_overwrite_policy(&output_path, project.settings.overwrite, &inputs)?;
+    check_disk_space(&project.exported_project(), &output_path)?;
+
+    let mut cmd = export_command(
+        state,
         project.clone(),
         output_path.clone(),
         None,
         use_gpu,
         speed_preset,
+        project.settings.overwrite,
     );
-    cmd.extend(["-progress".to_string(), "pipe:1".to_string(), "-nostats".to_string()]);
+    cmd.extend(["-progress".to_string(), progress_target(&output_path).to_string(), "-nostats".to_string()]);
+
+    let total_ms = project.get_export_duration() * 1000.0;
+
+    // Nothing is left on disk to decode again after streaming
+    let verify = project.settings.verify_after_export && !is_pipe_output(&output_path);
+    let encode_phase = if verify { phase.sub("encode", 0.0, 90.0) } else { phase };
+
+    let sampler = project
+        .settings
+        .report_utilization
+        .then(|| UtilizationSampler::start(events.boxed(), gpu_type.as_deref()));
+    let outcome = run_ffmpeg_with_progress(
+        events,
+        &state.export_process,
+        new_command(&cmd, project.settings.low_priority),
+        total_ms,
+        &output_path,
+        encode_phase,
+    );
+    drop(sampler);
+
+    let status = match outcome? {
+        FfmpegOutcome::Finished(status) => status,
+        FfmpegOutcome::Cancelled { partial } => {
+            let mut result = ExportResult::cancelled(encoder, gpu_type.is_some(), start_time, partial);
+            result.output_path = partial.then_some(output_path);
+            result.warnings = warnings;
+            return Ok(result);
+        }
+    };
 
-    let total_ms = project.get_video_duration() * 1000.0;
+    let mut result = ExportResult::finished(status, encoder, gpu_type.is_some(), start_time);
+    result.output_path = Some(output_path.clone());
+    result.warnings = warnings;
+    result.keep_failed_files(&output_path);
+    if result.success && verify {
+        let verify_phase = phase.sub("verify", 90.0, 100.0);
+        verify_phase.emit(events, 0.0);
+        result.verification_errors = Some(verify_output_file(&output_path)?);
+        verify_phase.emit(events, 100.0);
+        result.duration_seconds = start_time.elapsed().as_secs_f64();
+    }
+    Ok(result)
+}
+
+// "-" (stdout), an ffmpeg "pipe:N" target, or a named pipe on disk
+pub(crate) fn is_pipe_output(path: &str) -> bool {
+    if path == "-" || path.starts_with("pipe:") {
+        return true;
+    }
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::FileTypeExt;
+        if fs::metadata(path).is_ok_and(|m| m.file_type().is_fifo()) {
+            return true;
+        }
+    }
+    false
+}
+
+// A pipe can't be seeked back into, so mp4 (index written at the end) is out
+fn pipe_container(path: &str) -> &'static str {
+    match Path::new(path).extension().map(|e| e.to_string_lossy().to_lowercase()).as_deref() {
+        Some("ts") => "mpegts",
+        Some("webm") => "webm",
+        _ => "matroska",
+    }
+}
+
+// ffmpeg's stdout carries the video itself when streaming, progress then goes to stderr
+pub(crate) fn progress_target(output_path: &str) -> &'static str {
+    if output_path == "-" || output_path == "pipe:1" {
+        "pipe:2"
+    } else {
+        "pipe:1"
+    }
+}
+
+// Decodes the whole file and returns every error ffmpeg reports (empty when clean)
+fn verify_output_file(path: &str) -> Result<Vec<String>, String> {
+    if !Path::new(path).exists() {
+        return Err(format!("Fichier introuvable: {}", path));
+    }
+
+    let output = Command::new("ffmpeg")
+        .args(["-v", "error", "-i", path, "-f", "null", "-"])
+        .stdout(Stdio::null())
+        .output()
+        .map_err(|e| format!("Impossible de lancer ffmpeg: {}", e))?;
+
+    Ok(String::from_utf8_lossy(&output.stderr)
+        .lines()
+        .map(|l| l.trim().to_string())
+        .filter(|l| !l.is_empty())
+        .collect())
+}
+
+#[tauri::command]
+pub async fn verify_output(path: String) -> Result<Vec<String>, String> {
+    verify_output_file(&path)
+}
+
+pub(crate) enum FfmpegOutcome {
+    Finished(std::process::ExitStatus),
+    // `partial` when ffmpeg was stopped gracefully and the truncated file was kept
+    Cancelled { partial: bool },
+}
+
+fn finish_in_slot(slot: &ChildSlot, output_path: &str) -> Result<FfmpegOutcome, String> {
+    match slot.wait()? {
+        Some(status) => Ok(FfmpegOutcome::Finished(status)),
+        None => {
+            let partial = slot.keeps_partial() && Path::new(output_path).exists();
+            if !partial && Path::new(output_path).exists() && !is_pipe_output(output_path) {
+                let _ = fs::remove_file(output_path);
+            }
+            Ok(FfmpegOutcome::Cancelled { partial })
+        }
+    }
+}
+
+// Where progress events go: the webview, or JSON lines on a terminal (see headless.rs)
+pub(crate) trait EventSink: Send + Sync {
+    fn send(&self, event: &str, payload: serde_json::Value);
+    // An owned handle for sampler threads
+    fn boxed(&self) -> Box<dyn EventSink>;
+}
+
+impl EventSink for AppHandle {
+    fn send(&self, event: &str, payload: serde_json::Value) {
+        let _ = self.emit(event, payload);
+    }
+
+    fn boxed(&self) -> Box<dyn EventSink> {
+        Box::new(self.clone())
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PhaseProgress {
+    pub phase: String,
+    pub percent: f64,
+    pub phase_percent: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BatchProgress {
+    pub item_index: usize,
+    pub item_count: usize,
+    pub item_percent: f64,
+    pub overall_percent: f64,
+}
+
+// Where one batch item sits on the overall bar
+#[derive(Debug, Clone, Copy)]
+struct BatchSlot {
+    index: usize,
+    count: usize,
+    base: f64,
+    range: f64,
+}
+
+// A step of a multi-step job, mapped onto [base, base + range] of the overall 0-100 bar
+#[derive(Debug, Clone, Copy)]
+struct ProgressPhase {
+    label: &'static str,
+    base: f64,
+    range: f64,
+    batch: Option<BatchSlot>,
+}
+
+impl ProgressPhase {
+    const FULL: ProgressPhase = ProgressPhase { label: "encode", base: 0.0, range: 100.0, batch: None };
+
+    // The [from, to] percent slice of this phase, e.g. the verify step of an export
+    fn sub(&self, label: &'static str, from: f64, to: f64) -> Self {
+        Self {
+            label,
+            base: self.base + self.range * from / 100.0,
+            range: self.range * (to - from) / 100.0,
+            batch: self.batch,
+        }
+    }
+
+    // -1 tells the UI to show an indeterminate bar (unknown total duration)
+    fn emit_indeterminate(&self, events: &dyn EventSink) {
+        events.send("export-progress", json!(-1.0));
+        events.send(
+            "export-phase-progress",
+            json!(PhaseProgress { phase: self.label.to_string(), percent: -1.0, phase_percent: -1.0 }),
+        );
+    }
+
+    // `export-progress` keeps carrying the overall percentage for existing listeners
+    fn emit(&self, events: &dyn EventSink, phase_percent: f64) {
+        let phase_percent = phase_percent.clamp(0.0, 100.0);
+        let percent = self.base + phase_percent * self.range / 100.0;
+        events.send("export-progress", json!(percent));
+        events.send(
+            "export-phase-progress",
+            json!(PhaseProgress { phase: self.label.to_string(), percent, phase_percent }),
+        );
+
+        if let Some(slot) = self.batch {
+            let item_percent = if slot.range > 0.0 { (percent - slot.base) / slot.range * 100.0 } else { 100.0 };
+            events.send(
+                "batch-progress",
+                json!(BatchProgress {
+                    item_index: slot.index,
+                    item_count: slot.count,
+                    item_percent: item_percent.clamp(0.0, 100.0),
+                    overall_percent: percent,
+                }),
+            );
+        }
+    }
+}
+
+// Splits the overall bar between batch items proportionally to their expected
+// duration, so a 10 min item weighs ten times a 1 min one. Items of unknown
+// duration get the average weight.
+struct ProgressAggregator {
+    slots: Vec<BatchSlot>,
+}
+
+impl ProgressAggregator {
+    fn new(durations: &[f64]) -> Self {
+        let known: Vec<f64> = durations.iter().copied().filter(|d| d.is_finite() && *d > 0.0).collect();
+        let fallback = if known.is_empty() { 1.0 } else { known.iter().sum::<f64>() / known.len() as f64 };
+        let weights: Vec<f64> = durations
+            .iter()
+            .map(|d| if d.is_finite() && *d > 0.0 { *d } else { fallback })
+            .collect();
+        let total: f64 = weights.iter().sum();
+
+        let mut base = 0.0;
+        let slots = weights
+            .iter()
+            .enumerate()
+            .map(|(index, w)| {
+                let range = w / total * 100.0;
+                let slot = BatchSlot { index, count: weights.len(), base, range };
+                base += range;
+                slot
+            })
+            .collect();
+        Self { slots }
+    }
+
+    fn phase(&self, index: usize) -> ProgressPhase {
+        let slot = self.slots[index];
+        ProgressPhase { label: "encode", base: slot.base, range: slot.range, batch: Some(slot) }
+    }
+}
+
+// ffmpeg reports out_time_ms in microseconds despite its name. None when the
+// total is zero or not a number (unprobed clips), so the UI can go indeterminate.
+fn progress_percent(pos_us: f64, total_ms: f64) -> Option<f64> {
+    if !total_ms.is_finite() || total_ms < 1.0 {
+        return None;
+    }
+    let percent = pos_us / 1000.0 / total_ms * 100.0;
+    percent.is_finite().then(|| percent.clamp(0.0, 100.0))
+}
+
+// Runs an ffmpeg command that has `-progress` set to `progress_target`, emitting
+// progress for `phase`. Streaming to "-" hands ffmpeg's stdout to ours, for
+// piping the app into an upload tool. The child lives in `slot` so it can be
+// cancelled; the partial output is removed on cancel.
+fn run_ffmpeg_with_progress(
+    events: &dyn EventSink,
+    slot: &ChildSlot,
+    mut process: Command,
+    total_ms: f64,
+    output_path: &str,
+    phase: ProgressPhase,
+) -> Result<FfmpegOutcome, String> {
     let time_regex = Regex::new(r"out_time_ms=(\d+)").unwrap();
+    let to_stdout = progress_target(output_path) == "pipe:2";
+    let (command, started) = (support::format_command(&process), Instant::now());
 
-    let mut child = Command::new(&cmd[0])
-        .args(&cmd[1..])
-        .stdout(Stdio::piped())
+    let mut child = process
+        .stdin(Stdio::piped())
+        .stdout(if to_stdout { Stdio::inherit() } else { Stdio::piped() })
         .stderr(Stdio::piped())
         .spawn()
         .map_err(|e| format!("Impossible de lancer ffmpeg: {}", e))?;
 
-    let stdout = child.stdout.take().unwrap();
-    let reader = std::io::BufReader::new(stdout);
+    let progress: Box<dyn std::io::Read + Send> = if to_stdout {
+        Box::new(child.stderr.take().unwrap())
+    } else {
+        Box::new(child.stdout.take().unwrap())
+    };
+    let capture = support::logging_enabled() || support::keeps_failed();
+    let stderr = (capture && !to_stdout).then(|| support::capture_stderr(child.stderr.take()));
+    slot.start(child);
+    let reader = std::io::BufReader::new(progress);
 
     use std::io::BufRead;
     for line in reader.lines().map_while(Result::ok) {
-        if CANCEL_FLAG.load(Ordering::SeqCst) {
-            let _ = child.kill();
-            if Path::new(&output_path).exists() {
-                let _ = fs::remove_file(&output_path);
-            }
-            return Ok(ExportResult {
-                success: false,
-                cancelled: true,
-                error: None,
-                encoder: Some(encoder.to_string()),
-                gpu_accelerated: gpu_type.is_some(),
-                duration_seconds: start_time.elapsed().as_secs_f64(),
-            });
+        // A graceful stop still needs the pipe drained while ffmpeg writes its trailer
+        if slot.is_cancelled() && !slot.keeps_partial() {
+            break;
         }
 
         if let Some(caps) = time_regex.captures(&line) {
             if let Ok(pos) = caps[1].parse::<f64>() {
-                let progress = (pos / total_ms * 100.0).min(100.0);
-                let _ = app.emit("export-progress", progress);
+                match progress_percent(pos, total_ms) {
+                    Some(percent) => phase.emit(events, percent),
+                    None => phase.emit_indeterminate(events),
+                }
             }
         }
     }
 
-    let status = child.wait().map_err(|e| e.to_string())?;
+    let outcome = finish_in_slot(slot, output_path);
+    let stderr = stderr.and_then(|h| h.join().ok()).unwrap_or_default();
+    log_outcome(&command, started, &outcome, &stderr);
+    outcome
+}
+
+// Same as above without progress reporting, for previews
+pub(crate) fn run_ffmpeg_quiet(slot: &ChildSlot, mut process: Command, output_path: &str) -> Result<FfmpegOutcome, String> {
+    let logging = support::logging_enabled() || support::keeps_failed();
+    let (command, started) = (support::format_command(&process), Instant::now());
+    let mut child = process
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(if logging { Stdio::piped() } else { Stdio::null() })
+        .spawn()
+        .map_err(|e| format!("Impossible de lancer ffmpeg: {}", e))?;
+
+    let stderr = logging.then(|| support::capture_stderr(child.stderr.take()));
+    slot.start(child);
+    let outcome = finish_in_slot(slot, output_path);
+    let stderr = stderr.and_then(|h| h.join().ok()).unwrap_or_default();
+    log_outcome(&command, started, &outcome, &stderr);
+    outcome
+}
+
+fn log_outcome(command: &str, started: Instant, outcome: &Result<FfmpegOutcome, String>, stderr: &str) {
+    if support::keeps_failed() && matches!(outcome, Ok(FfmpegOutcome::Finished(status)) if !status.success()) {
+        support::keep_failed_run(command, stderr);
+    }
+    let status = match outcome {
+        Ok(FfmpegOutcome::Finished(status)) => status.to_string(),
+        Ok(FfmpegOutcome::Cancelled { .. }) => "annule".to_string(),
+        Err(e) => e.clone(),
+    };
+    support::log_run(command, started.elapsed(), &status, stderr);
+}
+
+// Containers that keep an attached_pic stream as cover art (mkv stores it as an attachment)
+fn supports_cover_art(output_path: &str, container: Option<&str>) -> bool {
+    let ext = Path::new(output_path).extension().map(|e| e.to_string_lossy().to_lowercase());
+    matches!(container, Some("mp4" | "mov" | "matroska")) || matches!(ext.as_deref(), Some("m4a" | "mp3" | "flac"))
+}
+
+// Maps input `input_index` (the image) as output video stream `stream` and flags
+// it as cover art, after the main codec options so its own codec wins
+fn poster_args(input_index: usize, stream: usize) -> Vec<String> {
+    vec![
+        "-map".to_string(),
+        format!("{}:v", input_index),
+        format!("-c:v:{}", stream),
+        "mjpeg".to_string(),
+        format!("-disposition:v:{}", stream),
+        "attached_pic".to_string(),
+    ]
+}
+
+fn audio_codec_args(output_path: &str) -> (&'static str, Vec<String>) {
+    let ext = Path::new(output_path)
+        .extension()
+        .map(|e| e.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+
+    match ext.as_str() {
+        "mp3" => ("libmp3lame", vec!["-c:a".to_string(), "libmp3lame".to_string(), "-b:a".to_string(), "320k".to_string()]),
+        "flac" => ("flac", vec!["-c:a".to_string(), "flac".to_string()]),
+        "wav" => ("pcm_s16le", vec!["-c:a".to_string(), "pcm_s16le".to_string()]),
+        "ogg" => ("libvorbis", vec!["-c:a".to_string(), "libvorbis".to_string(), "-q:a".to_string(), "6".to_string()]),
+        "opus" => ("libopus", vec!["-c:a".to_string(), "libopus".to_string(), "-b:a".to_string(), "160k".to_string()]),
+        _ => ("aac", vec!["-c:a".to_string(), "aac".to_string(), "-b:a".to_string(), "192k".to_string()]),
+    }
+}
+
+#[tauri::command]
+pub async fn export_audio_mix(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    tracks: Vec<AudioTrack>,
+    output_path: String,
+    crossfade: f64,
+    poster_image: Option<String>,
+    overwrite: Option<OverwritePolicy>,
+) -> Result<ExportResult, String> {
+    let overwrite = overwrite.unwrap_or_default();
+    let inputs: Vec<&str> = tracks.iter().map(|t| t.path.as_str()).chain(poster_image.as_deref()).collect();
+    let output_path = apply_overwrite_policy(&output_path, overwrite, &inputs)?;
+    let _guard = ExportGuard::acquire(&state.is_exporting)?;
+    state.export_process.reset();
+    let start_time = Instant::now();
+
+    let mix = Project {
+        audio_tracks: tracks,
+        ..Project::default()
+    };
+    let active_tracks: Vec<AudioTrack> = mix.get_active_tracks().into_iter().cloned().collect();
+    if active_tracks.is_empty() {
+        return Err("Aucune piste audio a exporter".to_string());
+    }
+
+    let mut cmd = vec!["ffmpeg".to_string(), overwrite_flag(overwrite, &output_path).to_string()];
+    for t in &active_tracks {
+        cmd.extend(["-i".to_string(), t.path.clone()]);
+    }
+
+    let poster = poster_image.filter(|_| supports_cover_art(&output_path, None));
+    if let Some(ref poster) = poster {
+        cmd.extend(["-i".to_string(), poster.clone()]);
+    }
+
+    let support = state.ffmpeg.lock().unwrap().crossfade_support();
+    let hard_cuts = !support.acrossfade && active_tracks.len() > 1;
+    let (fc, tag_out) = build_audio_crossfade_filter(&active_tracks, crossfade, 0, hard_cuts, AudioInput::Native);
+    cmd.extend(["-filter_complex".to_string(), fc, "-map".to_string(), tag_out]);
+
+    let (codec, codec_args) = audio_codec_args(&output_path);
+    cmd.extend(codec_args);
+    match poster {
+        Some(_) => cmd.extend(poster_args(active_tracks.len(), 0)),
+        None => cmd.push("-vn".to_string()),
+    }
+    cmd.extend(["-progress".to_string(), "pipe:1".to_string(), "-nostats".to_string()]);
+    cmd.push(output_path.clone());
 
-    Ok(ExportResult {
-        success: status.success(),
-        cancelled: false,
-        error: if status.success() { None } else { Some(format!("FFmpeg a termine avec le code {}", status.code().unwrap_or(-1))) },
-        encoder: Some(encoder.to_string()),
-        gpu_accelerated: gpu_type.is_some(),
-        duration_seconds: start_time.elapsed().as_secs_f64(),
-    })
+    let overlap: f64 = if hard_cuts {
+        0.0
+    } else {
+        audio_crossfade_durations(&active_tracks, crossfade).iter().sum()
+    };
+    let total_ms = (mix.get_music_duration() - overlap).max(0.0) * 1000.0;
+
+    let status = match run_ffmpeg_with_progress(
+        &app,
+        &state.export_process,
+        new_command(&cmd, false),
+        total_ms,
+        &output_path,
+        ProgressPhase::FULL,
+    )? {
+        FfmpegOutcome::Finished(status) => status,
+        FfmpegOutcome::Cancelled { partial } => return Ok(ExportResult::cancelled(codec, false, start_time, partial)),
+    };
+
+    let mut result = ExportResult::finished(status, codec, false, start_time);
+    result.keep_failed_files(&output_path);
+    if hard_cuts {
+        result.warnings.push("Cette version de ffmpeg n'a pas le filtre acrossfade: les musiques sont enchainees sans fondu".to_string());
+    }
+    result.output_path = Some(output_path);
+    Ok(result)
 }
 
+// Bakes one track's processing into a standalone file, outside any timeline mix;
+// the codec follows the output extension as for `export_audio_mix`
 #[tauri::command]
-pub fn cancel_export() {
-    CANCEL_FLAG.store(true, Ordering::SeqCst);
+pub async fn process_track(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    track: AudioTrack,
+    output_path: String,
+    overwrite: Option<OverwritePolicy>,
+) -> Result<ExportResult, String> {
+    if track.mute {
+        return Err(format!("La piste \"{}\" est muette", track.name));
+    }
+    if !Path::new(&track.path).exists() {
+        return Err(format!("Fichier introuvable: {}", track.path));
+    }
+    let overwrite = overwrite.unwrap_or_default();
+    let output_path = apply_overwrite_policy(&output_path, overwrite, &[&track.path])?;
+    let _guard = ExportGuard::acquire(&state.is_exporting)?;
+    state.export_process.reset();
+    let start_time = Instant::now();
+
+    let mut cmd = vec!["ffmpeg".to_string(), overwrite_flag(overwrite, &output_path).to_string(), "-i".to_string(), track.path.clone()];
+    cmd.extend(["-filter_complex".to_string(), build_track_filter(&track, 0, "out", AudioInput::Native)]);
+    cmd.extend(["-map".to_string(), "[out]".to_string(), "-vn".to_string()]);
+    let (codec, codec_args) = audio_codec_args(&output_path);
+    cmd.extend(codec_args);
+    cmd.extend(["-progress".to_string(), "pipe:1".to_string(), "-nostats".to_string()]);
+    cmd.push(output_path.clone());
+
+    let status = match run_ffmpeg_with_progress(
+        &app,
+        &state.export_process,
+        new_command(&cmd, false),
+        track.duration * 1000.0,
+        &output_path,
+        ProgressPhase::FULL,
+    )? {
+        FfmpegOutcome::Finished(status) => status,
+        FfmpegOutcome::Cancelled { partial } => return Ok(ExportResult::cancelled(codec, false, start_time, partial)),
+    };
+
+    let mut result = ExportResult::finished(status, codec, false, start_time);
+    result.keep_failed_files(&output_path);
+    result.output_path = Some(output_path);
+    Ok(result)
+}
+
+// A quick re-encode of one file (shrink it, change its format) with the given
+// settings; progress is reported as for `export_project`
+#[tauri::command]
+pub async fn transcode_file(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    input: String,
+    output: String,
+    settings: ProjectSettings,
+    use_gpu: bool,
+    speed_preset: String,
+) -> Result<ExportResult, String> {
+    if !Path::new(&input).exists() {
+        return Err(format!("Fichier introuvable: {}", input));
+    }
+    let output = apply_overwrite_policy(&output, settings.overwrite, &[&input])?;
+    let _guard = ExportGuard::acquire(&state.is_exporting)?;
+    state.export_process.reset();
+    let start_time = Instant::now();
+
+    let (gpu_type, duration) = {
+        let mut ffmpeg = state.ffmpeg.lock().unwrap();
+        (if use_gpu { ffmpeg.detect_gpu_encoder() } else { None }, ffmpeg.get_duration(&input))
+    };
+    let encoder = gpu_type.as_deref().map(get_gpu_encoder_name).unwrap_or("libx264");
+    let mut cmd = build_transcode_args(&input, output.clone(), &settings, gpu_type.clone(), speed_preset);
+    let last = cmd.len() - 1;
+    cmd.splice(last..last, ["-progress".to_string(), progress_target(&output).to_string(), "-nostats".to_string()]);
+
+    let status = match run_ffmpeg_with_progress(
+        &app,
+        &state.export_process,
+        new_command(&cmd, settings.low_priority),
+        duration * 1000.0,
+        &output,
+        ProgressPhase::FULL,
+    )? {
+        FfmpegOutcome::Finished(status) => status,
+        FfmpegOutcome::Cancelled { partial } => {
+            let mut result = ExportResult::cancelled(encoder, gpu_type.is_some(), start_time, partial);
+            result.output_path = partial.then_some(output);
+            return Ok(result);
+        }
+    };
+
+    let mut result = ExportResult::finished(status, encoder, gpu_type.is_some(), start_time);
+    result.keep_failed_files(&output);
+    result.output_path = Some(output);
+    Ok(result)
+}
+
+#[tauri::command]
+pub fn cancel_export(state: State<'_, AppState>, mode: Option<CancelMode>) {
+    state.export_process.cancel_with(mode.unwrap_or_default());
+}
+
+#[tauri::command]
+pub fn cancel_preview(state: State<'_, AppState>) {
+    state.preview_process.cancel();
 }
 
 #[tauri::command]
@@ -571,30 +2986,269 @@ pub async fn create_preview(
     project: Project,
     clip_seconds: Option<i32>,
 ) -> Result<String, String> {
-    let temp_dir = std::env::temp_dir();
+    let temp_dir = get_temp_dir();
     let temp_path = temp_dir.join(format!("preview_{}.mkv", std::process::id()));
     let temp_path_str = temp_path.to_string_lossy().to_string();
 
-    let cmd = build_export_command(
-        state,
+    state.preview_process.reset();
+    let low_priority = project.settings.low_priority;
+    let cmd = export_command(
+        &state,
         project,
         temp_path_str.clone(),
         clip_seconds.or(Some(60)),
         true,
         "ultrafast".to_string(),
+        OverwritePolicy::Always,
     );
 
-    let status = Command::new(&cmd[0])
-        .args(&cmd[1..])
-        .stdout(Stdio::null())
-        .stderr(Stdio::null())
-        .status()
-        .map_err(|e| format!("Impossible de lancer ffmpeg: {}", e))?;
+    match run_ffmpeg_quiet(&state.preview_process, new_command(&cmd, low_priority), &temp_path_str)? {
+        FfmpegOutcome::Finished(status) if status.success() => Ok(temp_path_str),
+        FfmpegOutcome::Finished(_) => Err("La generation de la preview a echoue".to_string()),
+        FfmpegOutcome::Cancelled { .. } => Err("La preview a ete annulee".to_string()),
+    }
+}
+
+const DEFAULT_AROUND_WINDOW: f64 = 6.0;
+
+// Short render of the composed timeline around `center_seconds`, for checking one
+// moment while scrubbing. Goes through the export range, so the whole graph still
+// runs from zero and crossfade offsets match the real export; the window is
+// shifted rather than cut when it overflows either end of the timeline.
+#[tauri::command]
+pub async fn preview_around(
+    state: State<'_, AppState>,
+    mut project: Project,
+    center_seconds: f64,
+    window_seconds: Option<f64>,
+) -> Result<String, String> {
+    let total = project.get_video_duration();
+    if total <= 0.0 {
+        return Err("La timeline est vide".to_string());
+    }
+    let window = window_seconds.unwrap_or(DEFAULT_AROUND_WINDOW).min(total).max(total.min(0.5));
+    let start = (center_seconds - window / 2.0).clamp(0.0, total - window);
+    project.settings.export_range = Some((start, start + window));
+
+    let temp_path = get_temp_dir().join(format!("preview_around_{}.mkv", std::process::id()));
+    let temp_path_str = temp_path.to_string_lossy().to_string();
+
+    state.preview_process.reset();
+    let low_priority = project.settings.low_priority;
+    let cmd = export_command(&state, project, temp_path_str.clone(), None, true, "ultrafast".to_string(), OverwritePolicy::Always);
+
+    match run_ffmpeg_quiet(&state.preview_process, new_command(&cmd, low_priority), &temp_path_str)? {
+        FfmpegOutcome::Finished(status) if status.success() => Ok(temp_path_str),
+        FfmpegOutcome::Finished(_) => Err("La generation de la preview a echoue".to_string()),
+        FfmpegOutcome::Cancelled { .. } => Err("La preview a ete annulee".to_string()),
+    }
+}
+
+const DEFAULT_STORYBOARD_COLUMNS: i32 = 4;
+const DEFAULT_THUMB_WIDTH: i32 = 320;
+
+// Contact sheet of the timeline: the middle frame of each clip (as cut), tiled
+// in clip order in one ffmpeg pass. Thumbnails are letterboxed to 16:9 so tile
+// gets equal frames; a last row that isn't full is left black. Returns where
+// the sheet was written, after the overwrite policy.
+#[tauri::command]
+pub async fn export_storyboard(
+    state: State<'_, AppState>,
+    project: Project,
+    columns: Option<i32>,
+    thumb_width: Option<i32>,
+    output_path: String,
+) -> Result<String, String> {
+    let clips = &project.get_enabled_clips().1;
+    if clips.is_empty() {
+        return Err("La timeline est vide".to_string());
+    }
+    let overwrite = project.settings.overwrite;
+    let inputs: Vec<&str> = clips.iter().map(|c| c.path.as_str()).collect();
+    let output_path = apply_overwrite_policy(&output_path, overwrite, &inputs)?;
+    let columns = columns.unwrap_or(DEFAULT_STORYBOARD_COLUMNS).clamp(1, clips.len() as i32);
+    let rows = (clips.len() as i32 + columns - 1) / columns;
+    let w = thumb_width.unwrap_or(DEFAULT_THUMB_WIDTH).clamp(32, 1920) / 2 * 2;
+    let h = w * 9 / 16 / 2 * 2;
+
+    let mut cmd = vec!["ffmpeg".to_string(), overwrite_flag(overwrite, &output_path).to_string(), "-v".to_string(), "error".to_string()];
+    for clip in clips {
+        if !project.settings.auto_rotate {
+            cmd.push("-noautorotate".to_string());
+        }
+        let middle = clip.start + clip.get_effective_duration() / 2.0;
+        cmd.extend(["-ss".to_string(), middle.to_string(), "-i".to_string(), clip.path.clone()]);
+    }
+
+    let mut parts: Vec<String> = (0..clips.len())
+        .map(|i| {
+            format!(
+                "[{i}:v]trim=end_frame=1,setpts=PTS-STARTPTS,scale={w}:{h}:force_original_aspect_ratio=decrease,pad={w}:{h}:(ow-iw)/2:(oh-ih)/2,setsar=1,format=yuv420p[t{i}]"
+            )
+        })
+        .collect();
+    let inputs: String = (0..clips.len()).map(|i| format!("[t{}]", i)).collect();
+    parts.push(format!(
+        "{}concat=n={}:v=1:a=0,tile={}x{}:margin=4:padding=4[sheet]",
+        inputs,
+        clips.len(),
+        columns,
+        rows
+    ));
+    cmd.extend(["-filter_complex".to_string(), parts.join(";")]);
+    cmd.extend(["-map".to_string(), "[sheet]".to_string(), "-frames:v".to_string(), "1".to_string(), output_path.clone()]);
+
+    state.preview_process.reset();
+    match run_ffmpeg_quiet(&state.preview_process, new_command(&cmd, project.settings.low_priority), &output_path)? {
+        FfmpegOutcome::Finished(status) if status.success() => Ok(output_path),
+        FfmpegOutcome::Finished(_) => Err("Impossible de generer le storyboard".to_string()),
+        FfmpegOutcome::Cancelled { .. } => Err("Le storyboard a ete annule".to_string()),
+    }
+}
+
+const DEFAULT_GIF_WIDTH: i32 = 480;
+const DEFAULT_GIF_FPS: i32 = 10;
+const DEFAULT_GIF_SECONDS: i32 = 15;
+
+// Low-res GIF of the start of the timeline. Takes the export command up to its
+// video mapping and chains a two-pass palette (palettegen/paletteuse) onto the
+// composed video, which keeps GIF banding and file size down.
+#[tauri::command]
+pub async fn generate_preview_gif(
+    state: State<'_, AppState>,
+    project: Project,
+    width: Option<i32>,
+    fps: Option<i32>,
+    max_seconds: Option<i32>,
+) -> Result<String, String> {
+    let temp_path = get_temp_dir().join(format!("preview_{}.gif", std::process::id()));
+    let temp_path_str = temp_path.to_string_lossy().to_string();
+    let width = width.unwrap_or(DEFAULT_GIF_WIDTH).max(16);
+    let fps = fps.unwrap_or(DEFAULT_GIF_FPS).clamp(1, 50);
+    let seconds = max_seconds.unwrap_or(DEFAULT_GIF_SECONDS).max(1);
+
+    state.preview_process.reset();
+    let low_priority = project.settings.low_priority;
+    let export_cmd = export_command(&state, project, temp_path_str.clone(), Some(seconds), false, "ultrafast".to_string(), OverwritePolicy::Always);
+
+    let map_at = export_cmd.iter().position(|a| a == "-map").ok_or("Aucune video a convertir en GIF")?;
+    let video_tag = match export_cmd[map_at + 1].as_str() {
+        tag if tag.starts_with('[') => tag.to_string(),
+        _ => "[0:v]".to_string(),
+    };
+    let gif_filter = format!(
+        "{}fps={},scale={}:-2:flags=lanczos,split[gif0][gif1];[gif0]palettegen=stats_mode=diff[gifpal];[gif1][gifpal]paletteuse=dither=bayer:bayer_scale=3[gif]",
+        video_tag, fps, width
+    );
+
+    let mut cmd: Vec<String> = export_cmd[..map_at].to_vec();
+    match cmd.iter().position(|a| a == "-filter_complex") {
+        Some(i) => {
+            let graph = &mut cmd[i + 1];
+            graph.push(';');
+            graph.push_str(&gif_filter);
+        }
+        None => cmd.extend(["-filter_complex".to_string(), gif_filter]),
+    }
+    cmd.extend(["-map".to_string(), "[gif]".to_string(), "-an".to_string()]);
+    cmd.extend(["-t".to_string(), seconds.to_string(), "-loop".to_string(), "0".to_string(), temp_path_str.clone()]);
+
+    match run_ffmpeg_quiet(&state.preview_process, new_command(&cmd, low_priority), &temp_path_str)? {
+        FfmpegOutcome::Finished(status) if status.success() => Ok(temp_path_str),
+        FfmpegOutcome::Finished(_) => Err("La generation du GIF a echoue".to_string()),
+        FfmpegOutcome::Cancelled { .. } => Err("La preview a ete annulee".to_string()),
+    }
+}
+
+// The crossfade the export uses between clips[position] and the next, and for
+// each side the input seek and the sub-clip that covers it. The crossfade is
+// pinned on the incoming sub-clip, since the sub-clips no longer look contiguous.
+fn transition_window(
+    clips: &[impl Borrow<VideoClip>],
+    position: usize,
+    default_crossfade: f64,
+    pre_seconds: Option<f64>,
+    post_seconds: Option<f64>,
+) -> (f64, [(f64, VideoClip); 2]) {
+    let (outgoing, incoming): (&VideoClip, &VideoClip) = (clips[position].borrow(), clips[position + 1].borrow());
+    let crossfade = get_transition_durations(clips, default_crossfade)[position];
+
+    // Each side must at least cover the crossfade, but can't exceed its clip
+    let pre = pre_seconds.unwrap_or(3.0).max(crossfade).min(outgoing.get_effective_duration());
+    let post = post_seconds.unwrap_or(3.0).max(crossfade).min(incoming.get_effective_duration());
+    let tail = |clip: &VideoClip, length: f64| clip.start + (clip.get_effective_duration() - length).max(0.0);
+    // A reversed clip ends on its source head and starts on its source tail
+    let out_seek = if outgoing.reverse { outgoing.start } else { tail(outgoing, pre) };
+    let in_seek = if incoming.reverse { tail(incoming, post) } else { incoming.start };
+
+    // Trims are applied by input seeking, so the sub-clips start at zero
+    let sub_outgoing = VideoClip { duration: pre, start: 0.0, end: None, gap_before: 0.0, ..outgoing.clone() };
+    let sub_incoming = VideoClip { duration: post, start: 0.0, end: None, crossfade_in: Some(crossfade), ..incoming.clone() };
+    (crossfade, [(out_seek, sub_outgoing), (in_seek, sub_incoming)])
+}
+
+#[tauri::command]
+pub async fn preview_transition(
+    state: State<'_, AppState>,
+    project: Project,
+    boundary_index: usize,
+    pre_seconds: Option<f64>,
+    post_seconds: Option<f64>,
+) -> Result<String, String> {
+    // `boundary_index` is the outgoing clip in `videos`; it fades into the next
+    // enabled clip, as on the exported timeline
+    let (indices, clips) = project.get_enabled_clips();
+    let position = indices
+        .iter()
+        .position(|i| *i == boundary_index)
+        .filter(|p| p + 1 < clips.len())
+        .ok_or_else(|| format!("Aucune transition a l'index {}", boundary_index))?;
+
+    state.preview_process.reset();
+    let settings = &project.settings;
+    let (crossfade, inputs) = transition_window(&clips, position, settings.video_crossfade, pre_seconds, post_seconds);
+    let sub_clips: Vec<VideoClip> = inputs.iter().map(|(_, clip)| clip.clone()).collect();
+
+    let temp_path = get_temp_dir().join(format!("transition_{}.mkv", std::process::id()));
+    let temp_path_str = temp_path.to_string_lossy().to_string();
+
+    let mut cmd = vec!["ffmpeg".to_string(), overwrite_flag(OverwritePolicy::Always, &temp_path_str).to_string()];
+    for (seek, sub_clip) in &inputs {
+        if !settings.auto_rotate {
+            cmd.push("-noautorotate".to_string());
+        }
+        cmd.extend(["-ss".to_string(), seek.to_string(), "-t".to_string(), sub_clip.duration.to_string(), "-i".to_string(), sub_clip.path.clone()]);
+    }
 
-    if status.success() {
-        Ok(temp_path_str)
+    let with_audio = settings.include_video_audio && !settings.mute_all;
+    let hard_cuts = state.ffmpeg.lock().unwrap().crossfade_support().video_hard_cuts(with_audio);
+    let transition = Some((settings.get_video_transition(), settings.transition_align)).filter(|_| !hard_cuts);
+    let audio = Some(AudioInput::from_settings(settings)).filter(|_| with_audio);
+    let probes = ClipProbes::of(sub_clips.iter().map(|c| c.path.as_str()));
+    let (vfc, tag_vout, tag_vaout) = build_video_crossfade_filter(&sub_clips, crossfade, transition, audio, settings, &probes);
+    let mut fc_parts = vec![vfc];
+    cmd.extend(["-map".to_string(), tag_vout]);
+
+    if with_audio {
+        fc_parts.push(format!("{}volume={}[va]", tag_vaout, settings.video_volume / 100.0));
+        cmd.extend(["-map".to_string(), "[va]".to_string()]);
+        cmd.extend(["-c:a".to_string(), "aac".to_string(), "-b:a".to_string(), "192k".to_string()]);
     } else {
-        Err("La generation de la preview a echoue".to_string())
+        cmd.push("-an".to_string());
+    }
+    cmd.extend(["-filter_complex".to_string(), fc_parts.join(";")]);
+
+    let (encoder, preset_flag, presets) = get_encoder_config("cpu");
+    cmd.extend(["-c:v".to_string(), encoder.to_string()]);
+    if let (Some(flag), Some(preset_val)) = (preset_flag, presets.get("ultrafast")) {
+        cmd.extend([flag.to_string(), preset_val.to_string()]);
+    }
+    cmd.push(temp_path_str.clone());
+
+    match run_ffmpeg_quiet(&state.preview_process, new_command(&cmd, settings.low_priority), &temp_path_str)? {
+        FfmpegOutcome::Finished(status) if status.success() => Ok(temp_path_str),
+        FfmpegOutcome::Finished(_) => Err("La generation de la preview de transition a echoue".to_string()),
+        FfmpegOutcome::Cancelled { .. } => Err("La preview a ete annulee".to_string()),
     }
 }
 
@@ -606,3 +3260,474 @@ pub fn play_preview(path: String) -> Result<(), String> {
         .map_err(|e| format!("Impossible de lancer ffplay: {}", e))?;
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::{clip_at, clip_of, track_at, track_of};
+    use crate::models::Composition;
+
+    #[test]
+    fn xfade_transitions_are_read_from_the_filter_help() {
+        let help = "xfade AVOptions:
+       transition        <int>        ..FV....... set cross fade transition (from -1 to 45) (default fade)
+         custom          -1           ..FV....... custom transition
+         fade            0            ..FV....... fade transition
+         wipeleft        1            ..FV....... wipeleft transition
+       duration          <duration>   ..FV....... set cross fade duration (default 1)
+         other           2";
+        assert_eq!(FFmpegProcessor::parse_xfade_transitions(help), ["fade", "wipeleft"]);
+    }
+
+    #[test]
+    fn encoders_are_read_from_the_encoder_list() {
+        let output = "Encoders:
+     V..... = Video
+     ------
+     V....D libx264              libx264 H.264 / AVC / MPEG-4 AVC (codec h264)
+     V....D h264_nvenc           NVIDIA NVENC H.264 encoder (codec h264)
+     A....D aac                  AAC (Advanced Audio Coding)
+     X....D unknown              something";
+        let encoders = FFmpegProcessor::parse_encoders(output);
+        let summary: Vec<(&str, &str, &str, bool)> =
+            encoders.iter().map(|e| (e.name.as_str(), e.codec.as_str(), e.kind.as_str(), e.hardware)).collect();
+        assert_eq!(summary, [("libx264", "h264", "video", false), ("h264_nvenc", "h264", "video", true), ("aac", "aac", "audio", false)]);
+    }
+
+    #[test]
+    fn progress_is_indeterminate_without_a_duration() {
+        assert_eq!(progress_percent(5_000_000.0, 10_000.0), Some(50.0));
+        assert_eq!(progress_percent(20_000_000.0, 10_000.0), Some(100.0));
+        assert_eq!(progress_percent(1_000.0, 0.0), None);
+        assert_eq!(progress_percent(1_000.0, f64::NAN), None);
+    }
+
+    #[derive(Clone, Default)]
+    struct RecordedEvents(std::sync::Arc<Mutex<Vec<(String, serde_json::Value)>>>);
+
+    impl EventSink for RecordedEvents {
+        fn send(&self, event: &str, payload: serde_json::Value) {
+            self.0.lock().unwrap().push((event.to_string(), payload));
+        }
+
+        fn boxed(&self) -> Box<dyn EventSink> {
+            Box::new(self.clone())
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn unprobed_projects_export_with_an_indeterminate_progress() {
+        let project = Project { videos: vec![clip_of(0.0), clip_of(0.0)], ..Project::default() };
+        assert_eq!(project.get_export_duration(), 0.0);
+
+        // A stand-in for ffmpeg printing one -progress report
+        let mut process = Command::new("sh");
+        process.args(["-c", "echo out_time_ms=1500000; echo progress=end"]);
+        let events = RecordedEvents::default();
+        let total_ms = project.get_export_duration() * 1000.0;
+        run_ffmpeg_with_progress(&events, &ChildSlot::new(), process, total_ms, "out.mp4", ProgressPhase::FULL).unwrap();
+
+        let sent = events.0.lock().unwrap();
+        let overall: Vec<_> = sent.iter().filter(|(e, _)| e == "export-progress").map(|(_, p)| p.clone()).collect();
+        assert_eq!(overall, vec![json!(-1.0)]);
+    }
+
+    #[test]
+    fn batch_items_share_the_bar_by_duration() {
+        let aggregator = ProgressAggregator::new(&[30.0, 10.0, 0.0, f64::NAN]);
+        // Unprobed items weigh as much as the average of the others
+        let ranges: Vec<(f64, f64)> = (0..4).map(|i| (aggregator.phase(i).base, aggregator.phase(i).range)).collect();
+        assert_eq!(ranges, [(0.0, 37.5), (37.5, 12.5), (50.0, 25.0), (75.0, 25.0)]);
+
+        let verify = aggregator.phase(0).sub("verify", 80.0, 100.0);
+        assert_eq!((verify.base, verify.range), (30.0, 7.5));
+        assert_eq!(ProgressAggregator::new(&[0.0, 0.0]).phase(1).base, 50.0);
+    }
+
+    #[test]
+    fn audio_mix_codec_follows_the_extension() {
+        assert_eq!(audio_codec_args("mix.MP3").0, "libmp3lame");
+        assert_eq!(audio_codec_args("mix.flac").1, ["-c:a", "flac"]);
+        assert_eq!(audio_codec_args("mix.opus").0, "libopus");
+        assert_eq!(audio_codec_args("mix.m4a").0, "aac");
+        assert_eq!(audio_codec_args("mix").0, "aac");
+    }
+
+    #[test]
+    fn streamed_outputs_get_a_seekless_container() {
+        assert!(is_pipe_output("-") && is_pipe_output("pipe:1"));
+        assert!(!is_pipe_output("out.mp4"));
+        assert_eq!(progress_target("-"), "pipe:2");
+        assert_eq!(progress_target("out.mp4"), "pipe:1");
+
+        let settings = ProjectSettings::default();
+        assert_eq!(output_container(&settings, "-"), Some("matroska"));
+        assert_eq!(output_container(&settings, "pipe:1"), Some("matroska"));
+        assert_eq!(pipe_container("stream.ts"), "mpegts");
+        assert_eq!(output_container(&settings, "out.M4V"), Some("mp4"));
+        assert_eq!(output_container(&settings, "out.avi"), None);
+        let forced = ProjectSettings { container: Some("mov".to_string()), ..ProjectSettings::default() };
+        assert_eq!(output_container(&forced, "-"), Some("mov"));
+    }
+
+    #[test]
+    fn copied_audio_must_fit_the_container() {
+        assert!(container_accepts_audio(Some("matroska"), "pcm_s24le"));
+        assert!(container_accepts_audio(Some("mp4"), "aac"));
+        assert!(!container_accepts_audio(Some("mp4"), "opus"));
+        assert!(container_accepts_audio(Some("webm"), "vorbis"));
+        assert!(!container_accepts_audio(None, "aac"));
+    }
+
+    #[test]
+    fn volume_keyframes_become_a_piecewise_linear_expression() {
+        let key = |time: f64, volume: f64| VolumeKeyframe { time, volume };
+        assert_eq!(build_volume_expression(&[]), None);
+        assert_eq!(build_volume_expression(&[key(2.0, 0.5)]), Some("if(lt(t,2),0.5,0.5)".to_string()));
+        // Unsorted keyframes are ordered by time first
+        assert_eq!(
+            build_volume_expression(&[key(4.0, 1.0), key(0.0, 0.2)]),
+            Some("if(lt(t,0),0.2,if(lt(t,4),0.2+(0.8)*(t-0)/4,1))".to_string())
+        );
+    }
+
+    #[test]
+    fn missing_crossfade_filters_become_hard_cuts() {
+        let project = Project {
+            videos: vec![clip_of(5.0), VideoClip { crossfade_in: Some(2.0), ..clip_of(6.0) }],
+            audio_tracks: vec![track_of(10.0), track_of(12.0)],
+            ..Project::default()
+        };
+
+        let mut full = project.clone();
+        assert!(CrossfadeSupport::default().degrade(&mut full).is_empty());
+        assert_eq!(full.get_video_duration(), project.get_video_duration());
+
+        // Without acrossfade the clips' own audio can't follow the video crossfades either
+        let mut degraded = project.clone();
+        let warnings = CrossfadeSupport { xfade: true, acrossfade: false }.degrade(&mut degraded);
+        assert_eq!(warnings.len(), 2, "{:?}", warnings);
+        assert_eq!(degraded.get_video_duration(), 11.0);
+        assert_eq!(degraded.settings.audio_crossfade, 0.0);
+
+        let mut silent = Project { audio_tracks: vec![], ..project };
+        silent.settings.mute_all = true;
+        assert!(CrossfadeSupport { xfade: true, acrossfade: false }.degrade(&mut silent).is_empty());
+    }
+
+    #[test]
+    fn transition_previews_match_the_exported_cut() {
+        let clip = |path: &str, start: f64, end: f64| VideoClip { start, end: Some(end), ..clip_at(path, 20.0) };
+        let clips = vec![clip("a.mp4", 2.0, 12.0), VideoClip { crossfade_in: Some(8.0), ..clip("b.mp4", 5.0, 9.0) }];
+
+        // The 8 s crossfade is clamped to the 4 s incoming clip, as in the export
+        let (crossfade, [(out_seek, outgoing), (in_seek, incoming)]) = transition_window(&clips, 0, 1.0, None, None);
+        assert_eq!(crossfade, 4.0);
+        assert_eq!((out_seek, outgoing.duration), (8.0, 4.0));
+        assert_eq!((in_seek, incoming.duration, incoming.crossfade_in), (5.0, 4.0, Some(4.0)));
+
+        // Reversed clips end on their source head and start on their source tail
+        let reversed = vec![VideoClip { reverse: true, ..clip("a.mp4", 2.0, 12.0) }, VideoClip { reverse: true, ..clip("b.mp4", 5.0, 15.0) }];
+        let (_, [(out_seek, _), (in_seek, _)]) = transition_window(&reversed, 0, 1.0, Some(2.0), Some(2.0));
+        assert_eq!((out_seek, in_seek), (2.0, 13.0));
+
+        // Contiguous cuts of one take get the export's hard cut
+        let take = vec![clip("a.mp4", 0.0, 6.0), clip("a.mp4", 6.0, 10.0)];
+        let (crossfade, [_, (_, incoming)]) = transition_window(&take, 0, 1.0, None, None);
+        assert_eq!((crossfade, incoming.crossfade_in), (0.0, Some(0.0)));
+    }
+
+    #[test]
+    fn clips_are_probed_once_per_file() {
+        let probes = ClipProbes::of(["missing.mp4", "missing.mp4", "other.m4a"]);
+        assert_eq!(probes.0.len(), 2);
+        assert!(probes.properties("missing.mp4").is_none());
+        assert!(!probes.needs_deep_probe("missing.mp4"));
+        assert_eq!(probes.audio_channels("never_probed.mp4"), None);
+
+        let stream = json!({
+            "codec_type": "video", "width": 1920, "height": 1080, "r_frame_rate": "30000/1001",
+            "sample_aspect_ratio": "1:1", "color_range": "pc", "side_data_list": [{ "rotation": -90 }],
+        });
+        let props = FFmpegProcessor::parse_video_stream(&stream, Some(&json!({ "codec_name": "aac" })));
+        assert_eq!(props.rotation, 90);
+        assert_eq!(props.frame_size(true), (1080, 1920));
+        assert_eq!(props.audio_codec.as_deref(), Some("aac"));
+        assert_eq!(props.color_range, Some(ColorRange::Full));
+    }
+
+    fn export_args(project: &Project, output: &str) -> Vec<String> {
+        build_export_args(project, output.to_string(), None, None, "ultrafast".to_string(), CrossfadeSupport::default(), OverwritePolicy::Always)
+    }
+
+    fn filter_graph(project: &Project) -> String {
+        let cmd = export_args(project, "out.mkv");
+        cmd.iter().skip_while(|a| *a != "-filter_complex").nth(1).cloned().unwrap_or_default()
+    }
+
+    fn xfade_offsets(graph: &str) -> Vec<f64> {
+        graph
+            .split("offset=")
+            .skip(1)
+            .map(|rest| rest.split('[').next().unwrap().parse().unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn music_volume_is_applied_to_the_music_mix() {
+        let mut project = Project {
+            videos: vec![clip_at("clip.mp4", 10.0)],
+            audio_tracks: vec![track_at("music.m4a", 10.0)],
+            ..Project::default()
+        };
+        project.settings.music_volume = 70.0;
+
+        let graph = filter_graph(&project);
+        assert!(graph.contains("volume=0.7[mvol]"), "{}", graph);
+        assert!(graph.contains("[va][mvol]amix"), "{}", graph);
+    }
+
+    #[test]
+    fn crossfades_are_clamped_around_a_short_clip() {
+        let clips = vec![clip_of(10.0), clip_of(1.0), clip_of(10.0)];
+        let mut project = Project { videos: clips, ..Project::default() };
+        project.settings.video_crossfade = 2.0;
+        project.settings.mute_all = true;
+        let graph = filter_graph(&project);
+        let offsets = xfade_offsets(&graph);
+        assert_eq!(offsets, vec![9.0, 10.0], "{}", graph);
+    }
+
+    #[test]
+    fn markers_match_the_exported_offsets() {
+        let mut gapped = clip_of(4.0);
+        gapped.gap_before = 1.0;
+        let mut clips = vec![clip_of(6.0), gapped, clip_of(3.0), clip_of(5.0)];
+        clips[3].crossfade_in = Some(0.0);
+
+        let mut project = Project { videos: clips, ..Project::default() };
+        project.settings.video_crossfade = 1.5;
+        project.settings.mute_all = true;
+        let graph = filter_graph(&project);
+        let offsets = xfade_offsets(&graph);
+
+        let markers = get_timeline_markers(&project.videos, 1.5, TransitionAlign::Trailing);
+        let starts: Vec<f64> = markers[1..].iter().map(|m| m.start).collect();
+        assert_eq!(offsets, starts, "{}", graph);
+    }
+
+    #[test]
+    fn filter_paths_are_escaped_for_both_parsers() {
+        assert_eq!(escape_filter_path("/media/a.cube"), "/media/a.cube");
+        assert_eq!(escape_filter_path(r"it's [v1], d;e:f"), r"it\\\'s \[v1\]\, d\;e\\:f");
+        assert_eq!(escape_filter_path(r"C:\luts\x.cube"), r"C\\:\\\\luts\\\\x.cube");
+    }
+
+    #[test]
+    fn last_frame_is_held_until_the_music_ends() {
+        let mut project = Project { videos: vec![clip_of(10.0)], audio_tracks: vec![track_of(15.0)], ..Project::default() };
+        assert_eq!(project.get_hold_duration(), 0.0);
+        project.settings.hold_last_frame = true;
+        assert_eq!(project.get_hold_duration(), 5.0);
+        assert_eq!(project.get_video_duration(), 15.0);
+
+        let graph = filter_graph(&project);
+        assert!(graph.contains("tpad=stop_mode=clone:stop_duration=5[vhold]"), "{}", graph);
+        assert!(graph.contains("apad=pad_dur=5[va]"), "{}", graph);
+    }
+
+    #[test]
+    fn merged_tracks_become_channels() {
+        let mut project = Project {
+            videos: vec![clip_of(10.0)],
+            audio_tracks: vec![track_of(8.0), track_of(6.0)],
+            ..Project::default()
+        };
+        project.settings.audio_merge = true;
+        project.settings.include_video_audio = false;
+        assert_eq!(project.get_mixed_music_duration(), 8.0);
+
+        let graph = filter_graph(&project);
+        assert!(graph.contains("[mm0][mm1]amerge=inputs=2[mmerge]"), "{}", graph);
+        assert!(graph.contains("aformat=channel_layouts=mono,apad=whole_dur=8[mm1]"), "{}", graph);
+        assert!(!graph.contains("acrossfade"), "{}", graph);
+    }
+
+    #[test]
+    fn transcoding_always_reencodes_one_file() {
+        let mut settings = ProjectSettings::default();
+        let cmd = build_transcode_args("in.mov", "out.mp4".to_string(), &settings, None, "fast".to_string());
+        assert!(cmd.windows(2).any(|w| w == ["-c:v", "libx264"]), "{:?}", cmd);
+        assert!(cmd.windows(2).any(|w| w == ["-preset", "veryfast"]), "{:?}", cmd);
+        assert!(!cmd.contains(&"-vf".to_string()) && !cmd.contains(&"-filter_complex".to_string()), "{:?}", cmd);
+        assert_eq!(cmd.last().unwrap(), "out.mp4");
+
+        settings.video_bitrate = Some("2M".to_string());
+        settings.mute_all = true;
+        let cmd = build_transcode_args("in.mov", "out.webm".to_string(), &settings, None, "fast".to_string());
+        assert!(cmd.windows(2).any(|w| w == ["-c:v", "libvpx-vp9"]), "{:?}", cmd);
+        assert!(cmd.windows(2).any(|w| w == ["-maxrate", "2M"]), "{:?}", cmd);
+        assert!(cmd.contains(&"-an".to_string()), "{:?}", cmd);
+    }
+
+    #[test]
+    fn color_range_is_converted_and_tagged() {
+        let mut project = Project { videos: vec![clip_of(10.0)], ..Project::default() };
+        project.settings.color_range = Some(ColorRange::Full);
+
+        let cmd = export_args(&project, "out.mp4");
+        let graph = filter_graph(&project);
+        assert!(graph.contains("scale=in_range=auto:out_range=pc[vrange]"), "{}", graph);
+        assert!(cmd.windows(2).any(|w| w == ["-color_range", "pc"]), "{:?}", cmd);
+        assert!(!cmd.windows(2).any(|w| w == ["-c:v", "copy"]), "{:?}", cmd);
+    }
+
+    #[test]
+    fn tune_is_passed_only_to_encoders_that_know_it() {
+        let mut settings = ProjectSettings { tune: Some("animation".to_string()), ..ProjectSettings::default() };
+        let cmd = build_transcode_args("in.mov", "out.mp4".to_string(), &settings, None, "fast".to_string());
+        assert!(cmd.windows(2).any(|w| w == ["-tune", "animation"]), "{:?}", cmd);
+        let cmd = build_transcode_args("in.mov", "out.mp4".to_string(), &settings, Some("nvidia".to_string()), "fast".to_string());
+        assert!(!cmd.contains(&"-tune".to_string()), "{:?}", cmd);
+
+        settings.tune = Some("hq".to_string());
+        let cmd = build_transcode_args("in.mov", "out.mp4".to_string(), &settings, Some("nvidia".to_string()), "fast".to_string());
+        assert!(cmd.windows(2).any(|w| w == ["-tune", "hq"]), "{:?}", cmd);
+        let cmd = build_transcode_args("in.mov", "out.mp4".to_string(), &settings, None, "fast".to_string());
+        assert!(!cmd.contains(&"-tune".to_string()), "{:?}", cmd);
+    }
+
+    #[test]
+    fn disabled_items_are_left_out() {
+        let disabled = VideoClip { enabled: false, ..clip_of(4.0) };
+        let mut project = Project {
+            videos: vec![clip_of(10.0), disabled, clip_of(6.0)],
+            audio_tracks: vec![AudioTrack { enabled: false, ..track_of(8.0) }, track_of(5.0)],
+            ..Project::default()
+        };
+        project.settings.video_crossfade = 1.0;
+        assert_eq!(project.get_video_duration(), 15.0);
+        assert_eq!(project.get_music_duration(), 5.0);
+
+        // The third clip is input 1, crossfaded straight from the first
+        let cmd = export_args(&project, "out.mkv");
+        let inputs: Vec<&str> = cmd.windows(2).filter(|w| w[0] == "-i").map(|w| w[1].as_str()).collect();
+        assert_eq!(inputs, ["clip_10.mp4", "clip_6.mp4", "music_5.m4a"]);
+        let graph = filter_graph(&project);
+        assert!(!graph.contains("[3:"), "{}", graph);
+
+        let markers = crate::models::get_project_markers(project);
+        assert_eq!(markers.iter().map(|m| m.clip_index).collect::<Vec<_>>(), vec![0, 2]);
+    }
+
+    #[test]
+    fn encoder_threads_cap_filters_and_encoder() {
+        let mut project = Project { videos: vec![clip_of(10.0)], ..Project::default() };
+        project.settings.video_fade_in = 1.0;
+        project.settings.encoder_threads = Some(0);
+        let cmd = export_args(&project, "out.mkv");
+        assert!(!cmd.contains(&"-threads".to_string()) && !cmd.contains(&"-filter_threads".to_string()), "{:?}", cmd);
+
+        project.settings.encoder_threads = Some(2);
+        let cmd = export_args(&project, "out.mkv");
+        assert!(cmd.windows(2).any(|w| w == ["-threads", "2"]), "{:?}", cmd);
+        assert!(cmd.windows(2).any(|w| w == ["-filter_threads", "2"]), "{:?}", cmd);
+    }
+
+    #[test]
+    fn muxing_queue_size_defaults_when_unset() {
+        let mut project = Project { videos: vec![clip_of(10.0)], ..Project::default() };
+        let queue = |project: &Project| {
+            let cmd = export_args(project, "out.mkv");
+            cmd.windows(2).find(|w| w[0] == "-max_muxing_queue_size").map(|w| w[1].clone())
+        };
+        assert_eq!(queue(&project).as_deref(), Some("1024"));
+        project.settings.max_muxing_queue_size = Some(4096);
+        assert_eq!(queue(&project).as_deref(), Some("4096"));
+        project.settings.max_muxing_queue_size = Some(-1);
+        assert_eq!(queue(&project).as_deref(), Some("1024"));
+    }
+
+    #[test]
+    fn reversed_clips_play_the_kept_range_backwards() {
+        let clip = VideoClip { start: 2.0, end: Some(6.0), reverse: true, ..clip_of(10.0) };
+        let mut project = Project { videos: vec![clip], ..Project::default() };
+        project.settings.trim_accuracy = TrimAccuracy::Accurate;
+        assert_eq!(project.get_video_duration(), 4.0);
+
+        let graph = filter_graph(&project);
+        assert!(graph.contains("trim=start=2:end=6,setpts=PTS-STARTPTS,reverse"), "{}", graph);
+        assert!(graph.contains("atrim=start=2:end=6,asetpts=PTS-STARTPTS,areverse"), "{}", graph);
+    }
+
+    #[test]
+    fn gaps_put_black_and_silence_before_a_clip() {
+        let gapped = VideoClip { gap_before: 1.5, ..clip_of(6.0) };
+        let project = Project { videos: vec![clip_of(10.0), gapped], ..Project::default() };
+        // The default 1 s crossfade fades from the first clip into the black
+        assert_eq!(project.get_video_duration(), 16.5);
+
+        let graph = filter_graph(&project);
+        assert!(graph.contains(":d=1.5,format=yuv420p,setsar=1,settb=AVTB[vg1]"), "{}", graph);
+        assert!(graph.contains("[vg1][vc1]concat=n=2:v=1:a=0[v1]"), "{}", graph);
+        assert!(graph.contains("atrim=duration=1.5[vag1]"), "{}", graph);
+        assert!(!graph.contains("[vg0]"), "{}", graph);
+    }
+
+    #[test]
+    fn fades_follow_the_export_range() {
+        let mut project = Project { videos: vec![clip_of(10.0)], ..Project::default() };
+        project.settings.video_fade_in = 1.0;
+        project.settings.video_fade_out = 2.0;
+        let graph = filter_graph(&project);
+        assert!(graph.contains("fade=t=in:st=0:d=1,fade=t=out:st=8:d=2[vfade]"), "{}", graph);
+
+        // A fade longer than the range is cut down to it
+        project.settings.export_range = Some((2.0, 3.5));
+        let graph = filter_graph(&project);
+        assert!(graph.contains("fade=t=in:st=2:d=1,fade=t=out:st=2:d=1.5[vfade]"), "{}", graph);
+    }
+
+    #[test]
+    fn burnt_timecode_is_drawn_last() {
+        let mut project = Project { videos: vec![clip_of(10.0)], ..Project::default() };
+        project.settings.burn_timecode = true;
+        let cmd = export_args(&project, "out.mp4");
+        let graph = filter_graph(&project);
+        assert!(graph.contains("drawtext=timecode='00\\:00\\:00\\:00':rate="), "{}", graph);
+        assert!(cmd.windows(2).any(|w| w == ["-map", "[vtc]"]), "{:?}", cmd);
+    }
+
+    #[test]
+    fn chroma_key_settings_are_clamped() {
+        let project = Project {
+            composition: Some(Composition {
+                layout: CompositionLayout::ChromaKey { color: "0x00FF00".to_string(), similarity: 2.0, blend: -1.0 },
+                clips: vec![clip_of(8.0), clip_of(5.0)],
+            }),
+            ..Project::default()
+        };
+        let graph = filter_graph(&project);
+        assert!(graph.contains("chromakey=color=0x00FF00:similarity=1:blend=0,"), "{}", graph);
+        assert!(graph.contains("overlay=x=0:y=0:eof_action=pass"), "{}", graph);
+        assert_eq!(project.get_video_duration(), 8.0);
+    }
+
+    #[test]
+    fn stems_are_mapped_as_two_streams() {
+        let mut project = Project { videos: vec![clip_of(10.0)], audio_tracks: vec![track_of(10.0)], ..Project::default() };
+        project.settings.separate_audio_stems = true;
+        let cmd = export_args(&project, "out.mkv");
+        let maps: Vec<&str> = cmd.windows(2).filter(|w| w[0] == "-map").map(|w| w[1].as_str()).collect();
+        assert_eq!(maps.len(), 3, "{:?}", cmd);
+        assert_eq!(maps[1], "[va]");
+        assert!(cmd.windows(2).any(|w| w == ["-metadata:s:a:1", "title=Musique"]), "{:?}", cmd);
+        assert!(!filter_graph(&project).contains("amix"));
+
+        // Without music there is nothing to split
+        project.audio_tracks.clear();
+        let cmd = export_args(&project, "out.mkv");
+        assert!(!cmd.contains(&"-metadata:s:a:1".to_string()), "{:?}", cmd);
+    }
+}
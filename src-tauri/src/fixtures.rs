@@ -0,0 +1,29 @@
+// Timeline items and scratch folders shared by the tests
+
+use std::path::PathBuf;
+
+use crate::models::{AudioTrack, VideoClip};
+
+pub fn clip_at(path: &str, seconds: f64) -> VideoClip {
+    VideoClip { path: path.to_string(), duration: seconds, ..VideoClip::default() }
+}
+
+pub fn clip_of(seconds: f64) -> VideoClip {
+    clip_at(&format!("clip_{}.mp4", seconds), seconds)
+}
+
+pub fn track_at(path: &str, seconds: f64) -> AudioTrack {
+    AudioTrack { path: path.to_string(), duration: seconds, ..AudioTrack::default() }
+}
+
+pub fn track_of(seconds: f64) -> AudioTrack {
+    track_at(&format!("music_{}.m4a", seconds), seconds)
+}
+
+// An empty folder in the temp dir, one per test and process
+pub fn work_dir(test: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("video_musique_{}_{}", test, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
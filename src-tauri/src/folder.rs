@@ -0,0 +1,115 @@
+use std::fs;
+use std::path::Path;
+use std::sync::atomic::Ordering;
+use tauri::State;
+
+use crate::ffmpeg::{probe_durations, FFmpegProcessor, MediaKind};
+use crate::models::VideoClip;
+use crate::ordering::natural_cmp;
+use crate::AppState;
+
+const DEFAULT_EXTENSIONS: [&str; 9] = ["mp4", "mov", "mkv", "m4v", "avi", "webm", "mts", "m2ts", "ts"];
+
+// Lowercase, without the leading dot
+fn normalize_extensions(extensions: Option<Vec<String>>) -> Vec<String> {
+    match extensions {
+        Some(list) if !list.is_empty() => list
+            .iter()
+            .map(|e| e.trim().trim_start_matches('.').to_lowercase())
+            .filter(|e| !e.is_empty())
+            .collect(),
+        _ => DEFAULT_EXTENSIONS.iter().map(|e| e.to_string()).collect(),
+    }
+}
+
+// Unreadable subfolders and entries are skipped. Symlinked folders aren't
+// followed, so a link back to a parent can't loop.
+fn collect_files(dir: &Path, recursive: bool, extensions: &[String], files: &mut Vec<String>) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let Ok(file_type) = entry.file_type() else {
+            continue;
+        };
+        let path = entry.path();
+        if file_type.is_dir() {
+            if recursive {
+                collect_files(&path, recursive, extensions, files);
+            }
+            continue;
+        }
+        let matches = path
+            .extension()
+            .map(|e| e.to_string_lossy().to_lowercase())
+            .is_some_and(|e| extensions.contains(&e));
+        if matches {
+            files.push(path.to_string_lossy().to_string());
+        }
+    }
+}
+
+// Tauri commands
+
+// Video files of a folder as clips, in natural path order ("clip2" before
+// "clip10"). Files ffprobe doesn't see as video are left out.
+#[tauri::command]
+pub async fn import_folder(
+    state: State<'_, AppState>,
+    path: String,
+    recursive: bool,
+    extensions: Option<Vec<String>>,
+) -> Result<Vec<VideoClip>, String> {
+    let root = Path::new(&path);
+    if !root.is_dir() {
+        return Err(format!("Dossier introuvable: {}", path));
+    }
+    fs::read_dir(root).map_err(|e| format!("Impossible de lire le dossier: {}", e))?;
+
+    let mut files = Vec::new();
+    collect_files(root, recursive, &normalize_extensions(extensions), &mut files);
+    files.retain(|f| FFmpegProcessor::probe_media_kind(f) == MediaKind::Video);
+    files.sort_by(|a, b| natural_cmp(a, b));
+
+    state.probe_cancelled.store(false, Ordering::SeqCst);
+    let durations = probe_durations(&state.ffmpeg, &files, &state.probe_cancelled, |_, _| {});
+
+    Ok(files
+        .into_iter()
+        .zip(durations)
+        .map(|(path, duration)| VideoClip {
+            name: Path::new(&path).file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default(),
+            duration: duration.unwrap_or(0.0),
+            path,
+            ..VideoClip::default()
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matching_files_are_collected_from_subfolders() {
+        let dir = std::env::temp_dir().join(format!("video_musique_folder_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("day 2")).unwrap();
+        for name in ["a.MP4", "notes.txt", "day 2/b.mov"] {
+            fs::write(dir.join(name), b"").unwrap();
+        }
+
+        let extensions = normalize_extensions(Some(vec![".MP4".to_string(), " mov ".to_string(), String::new()]));
+        assert_eq!(extensions, ["mp4", "mov"]);
+        assert_eq!(normalize_extensions(Some(vec![])).len(), DEFAULT_EXTENSIONS.len());
+
+        let mut files = Vec::new();
+        collect_files(&dir, false, &extensions, &mut files);
+        assert_eq!(files, [dir.join("a.MP4").to_string_lossy().to_string()]);
+        files.clear();
+        collect_files(&dir, true, &extensions, &mut files);
+        assert_eq!(files.len(), 2);
+        assert!(files.iter().any(|f| f.ends_with("b.mov")), "{:?}", files);
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
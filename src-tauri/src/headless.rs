@@ -0,0 +1,154 @@
+// `video-musique export <projet.mixproj|-> <sortie> [--no-gpu] [--preset <nom>]`:
+// runs one export without the window. Every event the UI would get is printed
+// as a JSON line {"event": ..., "payload": ...}, ending with a "result" line
+// carrying the ExportResult. See the README for the exit codes.
+
+use std::io::{Read, Write};
+
+use serde_json::json;
+
+use crate::ffmpeg::{export_with_events, progress_target, EventSink};
+use crate::models::{get_config, load_project, Project};
+use crate::AppState;
+
+const EXIT_SUCCESS: i32 = 0;
+const EXIT_FAILED: i32 = 1;
+const EXIT_USAGE: i32 = 2;
+const EXIT_CANCELLED: i32 = 3;
+
+const USAGE: &str = "Usage: video-musique export <projet.mixproj|-> <sortie> [--no-gpu] [--preset <nom>]";
+
+// JSON lines on stdout, or on stderr when the video itself goes to stdout
+#[derive(Clone, Copy)]
+struct JsonLines {
+    stderr: bool,
+}
+
+impl JsonLines {
+    fn print(&self, line: serde_json::Value) {
+        if self.stderr {
+            eprintln!("{}", line);
+        } else {
+            let mut out = std::io::stdout().lock();
+            let _ = writeln!(out, "{}", line);
+            let _ = out.flush();
+        }
+    }
+}
+
+impl EventSink for JsonLines {
+    fn send(&self, event: &str, payload: serde_json::Value) {
+        self.print(json!({ "event": event, "payload": payload }));
+    }
+
+    fn boxed(&self) -> Box<dyn EventSink> {
+        Box::new(*self)
+    }
+}
+
+struct Options {
+    project: String,
+    output: String,
+    use_gpu: bool,
+    speed_preset: String,
+}
+
+fn parse_args(args: &[String]) -> Result<Options, String> {
+    let config = get_config();
+    let mut positional = Vec::new();
+    let mut use_gpu = config.use_gpu;
+    let mut speed_preset = config.speed_preset;
+
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--no-gpu" => use_gpu = false,
+            "--gpu" => use_gpu = true,
+            "--preset" => speed_preset = iter.next().ok_or("--preset attend un nom de preset")?.clone(),
+            // "-" is stdin/stdout, anything else starting with a dash is unknown
+            flag if flag.starts_with("--") => return Err(format!("Option inconnue: {}", flag)),
+            _ => positional.push(arg.clone()),
+        }
+    }
+
+    match <[String; 2]>::try_from(positional) {
+        Ok([project, output]) => Ok(Options { project, output, use_gpu, speed_preset }),
+        Err(_) => Err(USAGE.to_string()),
+    }
+}
+
+fn read_project(source: &str) -> Result<Project, String> {
+    if source != "-" {
+        return load_project(source.to_string());
+    }
+    let mut content = String::new();
+    std::io::stdin()
+        .read_to_string(&mut content)
+        .map_err(|e| format!("Impossible de lire l'entree standard: {}", e))?;
+    serde_json::from_str(&content).map_err(|e| format!("Format de projet invalide: {}", e))
+}
+
+// None unless the first argument is the `export` subcommand, otherwise the exit code
+pub fn run_from_args() -> Option<i32> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.first().map(String::as_str) != Some("export") {
+        return None;
+    }
+
+    let options = match parse_args(&args[1..]) {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("{}", e);
+            return Some(EXIT_USAGE);
+        }
+    };
+    let events = JsonLines { stderr: progress_target(&options.output) == "pipe:2" };
+
+    let project = match read_project(&options.project) {
+        Ok(project) => project,
+        Err(e) => {
+            events.send("error", json!(e));
+            return Some(EXIT_USAGE);
+        }
+    };
+
+    let state = AppState::new();
+    match export_with_events(&events, &state, project, options.output, options.use_gpu, options.speed_preset) {
+        Ok(result) => {
+            let code = if result.cancelled {
+                EXIT_CANCELLED
+            } else if result.success && result.verification_errors.as_ref().is_none_or(|e| e.is_empty()) {
+                EXIT_SUCCESS
+            } else {
+                EXIT_FAILED
+            };
+            events.send("result", json!(result));
+            Some(code)
+        }
+        Err(e) => {
+            events.send("error", json!(e));
+            Some(EXIT_FAILED)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(list: &[&str]) -> Vec<String> {
+        list.iter().map(|a| a.to_string()).collect()
+    }
+
+    #[test]
+    fn export_arguments_are_parsed() {
+        let options = parse_args(&args(&["-", "out.mkv", "--no-gpu", "--preset", "slow"])).unwrap();
+        assert_eq!((options.project.as_str(), options.output.as_str()), ("-", "out.mkv"));
+        assert!(!options.use_gpu);
+        assert_eq!(options.speed_preset, "slow");
+
+        assert_eq!(parse_args(&args(&["p.mixproj"])).err(), Some(USAGE.to_string()));
+        assert!(parse_args(&args(&["p.mixproj", "out.mp4", "--preset"])).is_err());
+        assert!(parse_args(&args(&["p.mixproj", "out.mp4", "--verbose"])).is_err());
+    }
+}
@@ -1,23 +1,66 @@
+mod analysis;
+mod append;
+mod automation;
+mod disk;
+mod edl;
+#[cfg(test)]
+mod export_tests;
 mod ffmpeg;
+#[cfg(test)]
+mod fixtures;
+mod folder;
+mod headless;
 mod models;
+mod monitor;
+mod naming;
+mod ordering;
+mod process;
+mod support;
+mod timecode;
+mod validation;
 
+use std::sync::atomic::AtomicBool;
 use std::sync::Mutex;
 use tauri::Manager;
 
 pub struct AppState {
     pub ffmpeg: Mutex<ffmpeg::FFmpegProcessor>,
+    pub export_process: process::ChildSlot,
+    // Set while an export runs, see process::ExportGuard
+    pub is_exporting: AtomicBool,
+    // Stops get_durations_* between files, see ffmpeg::cancel_probe
+    pub probe_cancelled: AtomicBool,
+    pub preview_process: process::ChildSlot,
+}
+
+impl AppState {
+    fn new() -> Self {
+        let config = models::get_config();
+        support::set_logging(config.debug_logging);
+        support::set_keep_failed(config.keep_temp_on_error);
+        Self {
+            ffmpeg: Mutex::new(ffmpeg::FFmpegProcessor::new()),
+            export_process: process::ChildSlot::new(),
+            is_exporting: AtomicBool::new(false),
+            probe_cancelled: AtomicBool::new(false),
+            preview_process: process::ChildSlot::new(),
+        }
+    }
 }
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
+    // `video-musique export ...` renders without opening a window
+    if let Some(code) = headless::run_from_args() {
+        std::process::exit(code);
+    }
+
     tauri::Builder::default()
         .plugin(tauri_plugin_dialog::init())
         .plugin(tauri_plugin_fs::init())
         .plugin(tauri_plugin_shell::init())
         .setup(|app| {
-            app.manage(AppState {
-                ffmpeg: Mutex::new(ffmpeg::FFmpegProcessor::new()),
-            });
+            app.manage(AppState::new());
             Ok(())
         })
         .invoke_handler(tauri::generate_handler![
@@ -25,16 +68,63 @@ pub fn run() {
             ffmpeg::detect_gpu_encoder,
             ffmpeg::get_duration,
             ffmpeg::get_durations_parallel,
+            ffmpeg::get_durations_with_progress,
+            ffmpeg::cancel_probe,
+            ffmpeg::get_last_probe_log,
             ffmpeg::get_gpu_info,
+            ffmpeg::benchmark_encoders,
+            ffmpeg::list_transitions,
+            ffmpeg::get_supported_encoders,
+            ffmpeg::probe_video_properties,
+            ffmpeg::snap_to_keyframe,
+            ffmpeg::is_valid_media,
+            ffmpeg::file_content_hash,
             ffmpeg::build_export_command,
+            ffmpeg::validate_filter_graph,
+            disk::estimate_output_size,
             ffmpeg::export_project,
+            ffmpeg::export_batch,
+            ffmpeg::export_audio_mix,
+            ffmpeg::process_track,
+            ffmpeg::transcode_file,
+            append::append_to_video,
             ffmpeg::create_preview,
+            ffmpeg::preview_around,
+            ffmpeg::generate_preview_gif,
+            ffmpeg::export_storyboard,
+            ffmpeg::preview_transition,
             ffmpeg::play_preview,
             ffmpeg::cancel_export,
+            ffmpeg::cancel_preview,
+            ffmpeg::verify_output,
+            analysis::detect_silence,
+            analysis::trim_silence_from_video,
+            analysis::detect_scenes,
+            analysis::analyze_track_loudness,
+            analysis::render_waveform_image,
+            edl::import_edl,
+            folder::import_folder,
+            ordering::auto_order_by,
+            timecode::format_timecode,
+            timecode::parse_timecode,
             models::save_project,
             models::load_project,
+            models::get_project_markers,
+            models::snap_clips_to_beats,
+            models::find_missing_media,
+            models::relink_media,
+            models::autosave_project,
+            models::recover_project,
+            models::clear_autosaves,
+            validation::validate_project,
+            validation::get_project_stats,
             models::get_config,
             models::set_config,
+            models::remember_directory,
+            models::list_presets,
+            models::apply_preset,
+            models::save_preset,
+            support::collect_support_bundle,
         ])
         .run(tauri::generate_context!())
         .expect("Erreur lors du lancement de l'application");
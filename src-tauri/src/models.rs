@@ -1,6 +1,10 @@
 use serde::{Deserialize, Serialize};
+use std::borrow::Borrow;
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use tauri::State;
+
+use crate::AppState;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AudioTrack {
@@ -15,6 +19,66 @@ pub struct AudioTrack {
     pub mute: bool,
     #[serde(default)]
     pub solo: bool,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub volume_keyframes: Vec<VolumeKeyframe>,
+    // Loudness-matching offset in dB, from `analyze_track_loudness`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub loudness_gain: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub eq: Option<AudioEq>,
+    // Stereo position, -1.0 full left to 1.0 full right; None or 0 is centered
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pan: Option<f64>,
+    // Off leaves the track out of the export and durations, without removing it
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+}
+
+impl Default for AudioTrack {
+    fn default() -> Self {
+        Self {
+            path: String::new(),
+            volume: 1.0,
+            name: String::new(),
+            duration: 0.0,
+            mute: false,
+            solo: false,
+            volume_keyframes: vec![],
+            loudness_gain: None,
+            eq: None,
+            pan: None,
+            enabled: true,
+        }
+    }
+}
+
+// Shelf gains in dB (-20..20, 0 neutral) for the `bass` and `treble` filters
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct AudioEq {
+    #[serde(default)]
+    pub bass_db: f64,
+    #[serde(default)]
+    pub treble_db: f64,
+}
+
+impl AudioEq {
+    pub fn get_filter(&self) -> Option<String> {
+        let mut filters = Vec::new();
+        if self.bass_db != 0.0 {
+            filters.push(format!("bass=g={}", self.bass_db.clamp(-20.0, 20.0)));
+        }
+        if self.treble_db != 0.0 {
+            filters.push(format!("treble=g={}", self.treble_db.clamp(-20.0, 20.0)));
+        }
+        (!filters.is_empty()).then(|| filters.join(","))
+    }
+}
+
+// Gain multiplier at a time (seconds) relative to the start of the track
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct VolumeKeyframe {
+    pub time: f64,
+    pub volume: f64,
 }
 
 fn default_volume() -> f64 {
@@ -29,6 +93,22 @@ impl AudioTrack {
             self.volume.min(1.1)
         }
     }
+
+    // Applied on top of the user volume; the 1.1 cap only bounds the fader
+    pub fn get_loudness_factor(&self) -> f64 {
+        match self.loudness_gain {
+            Some(db) if db.is_finite() => 10f64.powf(db / 20.0),
+            _ => 1.0,
+        }
+    }
+
+    // Balance: the side opposite the position is attenuated, the other kept at
+    // unity. A mono track is upmixed first so it can be placed at all.
+    pub fn get_pan_filter(&self) -> Option<String> {
+        let pan = self.pan.filter(|p| p.is_finite() && *p != 0.0)?.clamp(-1.0, 1.0);
+        let (left, right) = ((1.0 - pan).min(1.0), (1.0 + pan).min(1.0));
+        Some(format!("aformat=channel_layouts=stereo,pan=stereo|c0={}*c0|c1={}*c1", left, right))
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -38,6 +118,285 @@ pub struct VideoClip {
     pub name: String,
     #[serde(default)]
     pub duration: f64,
+    #[serde(default)]
+    pub start: f64,
+    #[serde(default)]
+    pub end: Option<f64>,
+    #[serde(default)]
+    pub mute_audio: bool,
+    // Length of the transition from the previous clip, overriding `video_crossfade`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub crossfade_in: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub color: Option<ColorAdjust>,
+    // .cube LUT for this clip, replacing the project-wide one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub lut: Option<String>,
+    // Forces deinterlacing even when ffprobe reports progressive frames
+    #[serde(default)]
+    pub deinterlace: bool,
+    // Seconds of black and silence played before the clip
+    #[serde(default)]
+    pub gap_before: f64,
+    // Plays the clip (as trimmed) backwards, audio included
+    #[serde(default)]
+    pub reverse: bool,
+    // Off leaves the clip out of the export and the timeline, without removing it
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+}
+
+impl Default for VideoClip {
+    fn default() -> Self {
+        Self {
+            path: String::new(),
+            name: String::new(),
+            duration: 0.0,
+            start: 0.0,
+            end: None,
+            mute_audio: false,
+            crossfade_in: None,
+            color: None,
+            lut: None,
+            deinterlace: false,
+            gap_before: 0.0,
+            reverse: false,
+            enabled: true,
+        }
+    }
+}
+
+// Values follow the `eq` filter: brightness -1..1 (0 neutral), contrast -1000..1000,
+// saturation 0..3 and gamma 0.1..10 (1 neutral)
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ColorAdjust {
+    #[serde(default)]
+    pub brightness: f64,
+    #[serde(default = "default_one")]
+    pub contrast: f64,
+    #[serde(default = "default_one")]
+    pub saturation: f64,
+    #[serde(default = "default_one")]
+    pub gamma: f64,
+}
+
+fn default_one() -> f64 { 1.0 }
+
+impl Default for ColorAdjust {
+    fn default() -> Self {
+        Self { brightness: 0.0, contrast: 1.0, saturation: 1.0, gamma: 1.0 }
+    }
+}
+
+impl ColorAdjust {
+    pub fn is_neutral(&self) -> bool {
+        *self == Self::default()
+    }
+
+    pub fn get_filter(&self) -> Option<String> {
+        if self.is_neutral() {
+            return None;
+        }
+        Some(format!(
+            "eq=brightness={}:contrast={}:saturation={}:gamma={}",
+            self.brightness.clamp(-1.0, 1.0),
+            self.contrast.clamp(-1000.0, 1000.0),
+            self.saturation.clamp(0.0, 3.0),
+            self.gamma.clamp(0.1, 10.0)
+        ))
+    }
+}
+
+// Up to about a frame between two cuts still counts as contiguous
+const CONTIGUOUS_CUT_TOLERANCE: f64 = 0.05;
+
+impl VideoClip {
+    pub fn is_trimmed(&self) -> bool {
+        self.start > 0.0 || self.end.is_some()
+    }
+
+    pub fn get_effective_duration(&self) -> f64 {
+        let end = self.end.unwrap_or(self.duration);
+        (end - self.start).max(0.0)
+    }
+
+    // Place taken on the timeline: the gap before it plus the clip as cut
+    pub fn get_timeline_length(&self) -> f64 {
+        self.gap_before.max(0.0) + self.get_effective_duration()
+    }
+
+    // Whether this clip picks up the same file where `previous` was cut, so
+    // together they play as one uninterrupted take
+    pub fn continues(&self, previous: &VideoClip) -> bool {
+        let previous_end = previous.end.unwrap_or(previous.duration);
+        self.path == previous.path
+            && self.gap_before <= 0.0
+            && !self.reverse
+            && !previous.reverse
+            && previous.duration > 0.0
+            && (self.start - previous_end).abs() <= CONTIGUOUS_CUT_TOLERANCE
+    }
+
+    // Per-clip processing that rules out stream copy. Trimming isn't included: a
+    // keyframe-aligned input seek can do it losslessly (see `snap_to_keyframes`).
+    pub fn needs_filtering(&self) -> bool {
+        self.deinterlace
+            || self.lut.is_some()
+            || self.color.as_ref().is_some_and(|c| !c.is_neutral())
+            || self.gap_before > 0.0
+            || self.reverse
+    }
+}
+
+// Length of the clips played back to back, minus the transition overlaps
+pub fn get_timeline_duration(clips: &[impl Borrow<VideoClip>], default_crossfade: f64, align: TransitionAlign) -> f64 {
+    get_timeline_markers(clips, default_crossfade, align).last().map_or(0.0, |m| m.end)
+}
+
+// Where a crossfade sits relative to the cut. `Trailing` overlaps the incoming
+// clip onto the tail of the outgoing one, so the fade ends on the cut. `Centered`
+// spans half the fade on each side: each clip is held on its edge frame (and
+// silence) for half of the fades around it, so the cuts keep their place.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TransitionAlign {
+    #[default]
+    Trailing,
+    Centered,
+}
+
+// An existing output file is replaced (`Always`), refused (`Never`) or kept by
+// writing to the next {counter} of the filename template, or "name_001.ext"
+// and up (`Rename`)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OverwritePolicy {
+    Always,
+    Never,
+    #[default]
+    Rename,
+}
+
+// Held time (head, tail) added to each clip for centered transitions
+pub fn get_transition_pads(clips: &[impl Borrow<VideoClip>], default_crossfade: f64, align: TransitionAlign) -> Vec<(f64, f64)> {
+    let durations = get_transition_durations(clips, default_crossfade);
+    (0..clips.len())
+        .map(|i| match align {
+            TransitionAlign::Trailing => (0.0, 0.0),
+            TransitionAlign::Centered => {
+                let head = if i > 0 { durations[i - 1] / 2.0 } else { 0.0 };
+                (head, durations.get(i).map_or(0.0, |d| d / 2.0))
+            }
+        })
+        .collect()
+}
+
+// Where a clip sits on the composed timeline. `start` includes its gap and falls
+// inside the previous clip's crossfade; `crossfade_start` is where the fade to the
+// next clip begins (the xfade offset), None for the last clip or a plain cut.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TimelineMarker {
+    pub clip_index: usize,
+    pub start: f64,
+    pub end: f64,
+    pub crossfade_start: Option<f64>,
+}
+
+pub fn get_timeline_markers(clips: &[impl Borrow<VideoClip>], default_crossfade: f64, align: TransitionAlign) -> Vec<TimelineMarker> {
+    let durations = get_transition_durations(clips, default_crossfade);
+    let pads = get_transition_pads(clips, default_crossfade, align);
+    let mut markers = Vec::with_capacity(clips.len());
+    let mut start = 0.0;
+    for (i, clip) in clips.iter().map(Borrow::borrow).enumerate() {
+        let (head, tail) = pads[i];
+        let end = start + head + clip.get_timeline_length() + tail;
+        let next_start = (end - durations.get(i).copied().unwrap_or(0.0)).max(0.0);
+        let crossfade_start = durations.get(i).filter(|d| **d > 0.0).map(|_| next_start);
+        markers.push(TimelineMarker { clip_index: i, start, end, crossfade_start });
+        start = next_start;
+    }
+    markers
+}
+
+// One duration per boundary (clips.len() - 1 entries). Each is clamped to the
+// incoming clip and to what the previous crossfade left of the outgoing one, so
+// xfade offsets never reach back before a clip's start. Unprobed clips
+// (duration 0) aren't clamped. Back-to-back cuts of one source get a plain cut
+// unless the incoming clip asks for its own crossfade.
+pub fn get_transition_durations(clips: &[impl Borrow<VideoClip>], default: f64) -> Vec<f64> {
+    let mut durations = Vec::with_capacity(clips.len().saturating_sub(1));
+    let mut consumed = 0.0;
+    for pair in clips.windows(2) {
+        let (previous, next): (&VideoClip, &VideoClip) = (pair[0].borrow(), pair[1].borrow());
+        let (outgoing, incoming) = (previous.get_timeline_length(), next.get_timeline_length());
+        let default = if next.continues(previous) { 0.0 } else { default };
+        let mut duration = next.crossfade_in.unwrap_or(default).max(0.0);
+        if outgoing > 0.0 {
+            duration = duration.min((outgoing - consumed).max(0.0));
+        }
+        if incoming > 0.0 {
+            duration = duration.min(incoming);
+        }
+        durations.push(duration);
+        consumed = duration;
+    }
+    durations
+}
+
+// Shortest a clip gets when its cut is moved onto a beat
+const MIN_BEAT_CLIP: f64 = 0.1;
+
+// Where the exporter cuts from clip `i` to the next: the middle of the
+// transition, so a crossfade straddles the beat rather than ending on it
+fn get_cut_time(clips: &[impl Borrow<VideoClip>], i: usize, default_crossfade: f64, align: TransitionAlign) -> f64 {
+    let markers = get_timeline_markers(clips, default_crossfade, align);
+    let duration = get_transition_durations(clips, default_crossfade).get(i).copied().unwrap_or(0.0);
+    markers[i].end - duration / 2.0
+}
+
+// Lengths (as cut, gap excluded) that put every cut on one of `beats`. Clip by
+// clip, the cut moves to the nearest beat after the previous cut that the
+// clip's source can reach; the end trim absorbs the difference. Moving a clip
+// also changes the crossfades clamped to it, so the cut is measured again
+// until it sits on the beat. The last clip, unprobed clips and clips no beat
+// fits keep their length.
+pub fn trim_to_beat_grid(clips: &[impl Borrow<VideoClip>], beats: &[f64], default_crossfade: f64, align: TransitionAlign) -> Vec<f64> {
+    let mut beats: Vec<f64> = beats.iter().copied().filter(|b| b.is_finite() && *b > 0.0).collect();
+    beats.sort_by(f64::total_cmp);
+    let mut clips: Vec<VideoClip> = clips.iter().map(|c| c.borrow().clone()).collect();
+    let mut previous_cut = 0.0;
+
+    for i in 0..clips.len().saturating_sub(1) {
+        let natural = get_cut_time(&clips, i, default_crossfade, align);
+        let (start, end) = (clips[i].start, clips[i].end.unwrap_or(clips[i].duration));
+        if clips[i].duration <= 0.0 || end <= start || start + MIN_BEAT_CLIP > clips[i].duration {
+            previous_cut = natural;
+            continue;
+        }
+        let earliest = previous_cut.max(natural - (end - start)) + MIN_BEAT_CLIP;
+        let latest = natural + (clips[i].duration - end);
+        let Some(beat) = beats
+            .iter()
+            .copied()
+            .filter(|b| *b >= earliest && *b <= latest)
+            .min_by(|a, b| (a - natural).abs().total_cmp(&(b - natural).abs()))
+        else {
+            previous_cut = natural;
+            continue;
+        };
+
+        let mut cut = natural;
+        for _ in 0..4 {
+            if (beat - cut).abs() < 1e-6 {
+                break;
+            }
+            let end = (clips[i].end.unwrap_or(clips[i].duration) + beat - cut).clamp(start + MIN_BEAT_CLIP, clips[i].duration);
+            clips[i].end = Some(end);
+            cut = get_cut_time(&clips, i, default_crossfade, align);
+        }
+        previous_cut = cut;
+    }
+    clips.iter().map(VideoClip::get_effective_duration).collect()
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -52,6 +411,13 @@ pub struct ProjectSettings {
     pub video_crossfade: f64,
     #[serde(default)]
     pub cut_music_at_end: bool,
+    // The inverse of cut_music_at_end: the last frame is held until the music ends
+    #[serde(default)]
+    pub hold_last_frame: bool,
+    // Plays the music tracks together, one channel each in track order (two mono
+    // files become left and right), instead of one after the other
+    #[serde(default)]
+    pub audio_merge: bool,
     #[serde(default = "default_video_volume")]
     pub video_volume: f64,
     #[serde(default = "default_music_volume")]
@@ -60,14 +426,436 @@ pub struct ProjectSettings {
     pub use_gpu: bool,
     #[serde(default = "default_speed_preset")]
     pub speed_preset: String,
+    // Seconds over which amix renormalizes when an input ends, 0 for a jump
+    #[serde(default = "default_amix_dropout_transition")]
+    pub amix_dropout_transition: f64,
+    // "longest" keeps audio until the last input ends (may outlast the video),
+    // "first" stops with the video audio, "shortest" with the first input to end.
+    #[serde(default = "default_amix_duration")]
+    pub amix_duration: String,
+    // Relative (video audio, music) weights of the final amix, on top of
+    // video_volume and music_volume; None weighs both inputs equally
+    #[serde(default)]
+    pub amix_weights: Option<(f64, f64)>,
+    #[serde(default)]
+    pub output_width: Option<i32>,
+    #[serde(default)]
+    pub output_height: Option<i32>,
+    // Bitrate cap such as "8M", applied as -maxrate/-bufsize on top of the quality target
+    #[serde(default)]
+    pub video_bitrate: Option<String>,
+    #[serde(default)]
+    pub output_fps: Option<f64>,
+    // Motion interpolation when raising the frame rate above the sources (CPU-heavy)
+    #[serde(default)]
+    pub interpolation_mode: Option<InterpMode>,
+    // Converted to and tagged on the output; unset leaves the sources' range alone
+    #[serde(default)]
+    pub color_range: Option<ColorRange>,
+    #[serde(default)]
+    pub verify_after_export: bool,
+    // Unset keeps aac at 192k (libopus at 160k for webm, which also accepts libvorbis)
+    #[serde(default)]
+    pub audio_codec: Option<String>,
+    #[serde(default)]
+    pub audio_bitrate: Option<String>,
+    // Converts every clip and track to stereo before crossfades and mixing
+    #[serde(default)]
+    pub force_stereo: bool,
+    // H.264 profile and level for constrained players (e.g. "main" + "4.0" for
+    // older TVs); unset lets the encoder choose
+    #[serde(default)]
+    pub h264_profile: Option<String>,
+    #[serde(default)]
+    pub h264_level: Option<String>,
+    // Encoder -tune for the content, e.g. "animation" or "stillimage" with x264.
+    // Values depend on the encoder (see `encoder_tunes`); others are dropped.
+    #[serde(default)]
+    pub tune: Option<String>,
+    // Stream-copies the audio of a single-clip remux instead of re-encoding it
+    #[serde(default = "default_true")]
+    pub copy_audio_when_possible: bool,
+    // Muxer forced with -f whatever the file name; unset goes by the extension
+    #[serde(default)]
+    pub container: Option<String>,
+    // Image stored as cover art (attached_pic), not drawn on the frames
+    #[serde(default)]
+    pub poster_image: Option<String>,
+    // Caps encoder and filter-graph threads; None or 0 lets ffmpeg use every core
+    #[serde(default)]
+    pub encoder_threads: Option<i32>,
+    // Packets ffmpeg may buffer per output stream, for exports stopping with
+    // "Too many packets buffered for output stream". Unset means 1024.
+    #[serde(default)]
+    pub max_muxing_queue_size: Option<i32>,
+    // Runs ffmpeg at a lower OS scheduling priority
+    #[serde(default)]
+    pub low_priority: bool,
+    // xfade transition name, see `list_transitions`
+    #[serde(default = "default_video_transition")]
+    pub video_transition: String,
+    // .cube LUT applied to every clip without its own, after color correction
+    #[serde(default)]
+    pub lut: Option<String>,
+    // Deinterlace clips whose field order says interlaced
+    #[serde(default = "default_true")]
+    pub auto_deinterlace: bool,
+    // Turns phone footage upright from its rotation metadata; off keeps the stored orientation
+    #[serde(default = "default_true")]
+    pub auto_rotate: bool,
+    // Keeps non-square pixels (first clip's ratio) instead of stretching to square ones
+    #[serde(default)]
+    pub preserve_sar: bool,
+    // Display aspect ratio forced on the output, e.g. "16:9"
+    #[serde(default)]
+    pub output_dar: Option<String>,
+    // Fills the size, aspect ratio, frame rate, bitrate cap and H.264 profile
+    // left unset with the platform's recommended upload specs
+    #[serde(default)]
+    pub platform_target: Option<Platform>,
+    // Running HH:MM:SS:FF overlay for review copies
+    #[serde(default)]
+    pub burn_timecode: bool,
+    #[serde(default)]
+    pub timecode_position: OverlayPosition,
+    #[serde(default = "default_timecode_font_size")]
+    pub timecode_font_size: i32,
+    // Seconds of fade from / to black on the final picture, 0 for none
+    #[serde(default)]
+    pub video_fade_in: f64,
+    #[serde(default)]
+    pub video_fade_out: f64,
+    // [start, end] seconds of the composed timeline to export, None for all of it
+    #[serde(default)]
+    pub export_range: Option<(f64, f64)>,
+    // File name used when the export target is a directory, see naming::expand_template
+    #[serde(default)]
+    pub filename_template: Option<String>,
+    // What an export does when the file it names already exists
+    #[serde(default)]
+    pub overwrite: OverwritePolicy,
+    // Moves a single clip's start back to the previous keyframe so it can be stream-copied
+    #[serde(default)]
+    pub snap_to_keyframes: bool,
+    #[serde(default)]
+    pub trim_accuracy: TrimAccuracy,
+    #[serde(default)]
+    pub transition_align: TransitionAlign,
+    // Video audio and music as two audio streams for post-editing, not premixed
+    #[serde(default)]
+    pub separate_audio_stems: bool,
+    // Emits CPU/GPU load during export (export-utilization events)
+    #[serde(default)]
+    pub report_utilization: bool,
+    // Silent export: overrides the music and video audio options, no audio stream at all
+    #[serde(default)]
+    pub mute_all: bool,
+}
+
+// Where clip trims happen: `Accurate` decodes from the top of the file and cuts
+// with the trim filter (exact but slow on late starts), `Fast` seeks the input
+// with -ss before -i (near instant, but the cut may land a little off).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TrimAccuracy {
+    Fast,
+    #[default]
+    Accurate,
+}
+
+// Upload target picked with `platform_target`
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Platform {
+    Youtube,
+    Instagram,
+    Tiktok,
+    Twitter,
+    Discord,
+}
+
+impl Platform {
+    // (width, height, dar, fps, bitrate cap, h264 profile) from each platform's upload guidelines
+    fn specs(self) -> (i32, i32, &'static str, f64, &'static str, &'static str) {
+        match self {
+            Platform::Youtube => (1920, 1080, "16:9", 30.0, "12M", "high"),
+            Platform::Instagram => (1080, 1920, "9:16", 30.0, "5M", "high"),
+            Platform::Tiktok => (1080, 1920, "9:16", 30.0, "8M", "high"),
+            Platform::Twitter => (1280, 720, "16:9", 30.0, "5M", "high"),
+            // Small enough to stay under the upload limit of a short clip
+            Platform::Discord => (1280, 720, "16:9", 30.0, "2M", "main"),
+        }
+    }
+
+    // Only fills what the settings leave unset
+    pub fn apply_defaults(self, settings: &mut ProjectSettings) {
+        let (width, height, dar, fps, bitrate, profile) = self.specs();
+        if settings.output_width.is_none() && settings.output_height.is_none() {
+            settings.output_width = Some(width);
+            settings.output_height = Some(height);
+        }
+        settings.output_dar.get_or_insert_with(|| dar.to_string());
+        settings.output_fps.get_or_insert(fps);
+        settings.video_bitrate.get_or_insert_with(|| bitrate.to_string());
+        settings.h264_profile.get_or_insert_with(|| profile.to_string());
+    }
 }
 
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OverlayPosition {
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    #[default]
+    BottomRight,
+}
+
+impl OverlayPosition {
+    // drawtext x/y expressions with a 10px margin
+    pub fn get_drawtext_xy(&self) -> (&'static str, &'static str) {
+        match self {
+            OverlayPosition::TopLeft => ("10", "10"),
+            OverlayPosition::TopRight => ("w-tw-10", "10"),
+            OverlayPosition::BottomLeft => ("10", "h-th-10"),
+            OverlayPosition::BottomRight => ("w-tw-10", "h-th-10"),
+        }
+    }
+
+    // overlay x/y expressions with a 20px margin
+    pub fn get_overlay_xy(&self) -> (&'static str, &'static str) {
+        match self {
+            OverlayPosition::TopLeft => ("20", "20"),
+            OverlayPosition::TopRight => ("W-w-20", "20"),
+            OverlayPosition::BottomLeft => ("20", "H-h-20"),
+            OverlayPosition::BottomRight => ("W-w-20", "H-h-20"),
+        }
+    }
+}
+
+// Two clips played at the same time on one frame, instead of the linear timeline.
+// clips[0] is the main clip: the background of a PiP, left or top of a split screen.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Composition {
+    pub layout: CompositionLayout,
+    pub clips: Vec<VideoClip>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum CompositionLayout {
+    // `scale` is the inset width as a fraction of the main clip's width
+    Pip {
+        #[serde(default)]
+        position: OverlayPosition,
+        #[serde(default = "default_pip_scale")]
+        scale: f64,
+    },
+    SideBySide,
+    Stacked,
+    // clips[1] (green screen) keyed out and laid over the whole of clips[0].
+    // `similarity` 0.01..1 widens the keyed range, `blend` 0..1 softens its edge.
+    ChromaKey {
+        #[serde(default = "default_key_color")]
+        color: String,
+        #[serde(default = "default_key_similarity")]
+        similarity: f64,
+        #[serde(default = "default_key_blend")]
+        blend: f64,
+    },
+}
+
+fn default_pip_scale() -> f64 { 0.3 }
+fn default_key_color() -> String { "0x00FF00".to_string() }
+fn default_key_similarity() -> f64 { 0.1 }
+fn default_key_blend() -> f64 { 0.05 }
+
+impl Composition {
+    // An overlay (PiP, keyed) lasts as long as the main clip, a split screen stops
+    // with the shorter one
+    pub fn get_duration(&self) -> f64 {
+        let mut durations = self.clips.iter().map(|c| c.get_effective_duration());
+        match self.layout {
+            CompositionLayout::Pip { .. } | CompositionLayout::ChromaKey { .. } => durations.next().unwrap_or(0.0),
+            _ => durations.reduce(f64::min).unwrap_or(0.0),
+        }
+    }
+}
+
+pub const SUPPORTED_AUDIO_CODECS: [&str; 5] = ["aac", "libopus", "libmp3lame", "flac", "ac3"];
+// ffmpeg muxer names accepted for `container`
+pub const SUPPORTED_CONTAINERS: [&str; 5] = ["mp4", "matroska", "webm", "mov", "mpegts"];
+pub const H264_PROFILES: [&str; 3] = ["baseline", "main", "high"];
+pub const X264_TUNES: [&str; 8] = ["film", "animation", "grain", "stillimage", "fastdecode", "zerolatency", "psnr", "ssim"];
+pub const NVENC_TUNES: [&str; 4] = ["hq", "ll", "ull", "lossless"];
+
+// The -tune values an encoder knows; AMF, QSV, VAAPI and V4L2 M2M have none
+pub fn encoder_tunes(encoder: &str) -> &'static [&'static str] {
+    match encoder {
+        "libx264" | "libx265" => &X264_TUNES,
+        "h264_nvenc" | "hevc_nvenc" => &NVENC_TUNES,
+        _ => &[],
+    }
+}
+
+// level_idc values (4.1 is 41) up to 5.2, the highest every H.264 encoder here knows
+pub const H264_LEVELS: [i32; 16] = [10, 11, 12, 13, 20, 21, 22, 30, 31, 32, 40, 41, 42, 50, 51, 52];
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum InterpMode {
+    Blend,
+    Mci,
+}
+
+// Luma range of the output: limited (16-235, "tv") is what players expect from
+// H.264; full (0-255, "pc") comes from screen captures and some phones
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ColorRange {
+    Limited,
+    Full,
+}
+
+impl ColorRange {
+    // ffmpeg's name, for -color_range and the scale filter
+    pub fn ffmpeg_name(&self) -> &'static str {
+        match self {
+            Self::Limited => "tv",
+            Self::Full => "pc",
+        }
+    }
+
+    // From ffprobe's color_range field; "unknown" and missing give None
+    pub fn from_ffprobe(name: &str) -> Option<Self> {
+        match name {
+            "tv" | "mpeg" => Some(Self::Limited),
+            "pc" | "jpeg" => Some(Self::Full),
+            _ => None,
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Limited => "limitee (tv)",
+            Self::Full => "complete (pc)",
+        }
+    }
+}
+
+// ffmpeg's own default (128 at most) is too low for multi-input graphs
+const DEFAULT_MAX_MUXING_QUEUE_SIZE: i32 = 1024;
+
 fn default_true() -> bool { true }
 fn default_audio_crossfade() -> f64 { 10.0 }
 fn default_video_crossfade() -> f64 { 1.0 }
 fn default_video_volume() -> f64 { 100.0 }
 fn default_music_volume() -> f64 { 70.0 }
 fn default_speed_preset() -> String { "balanced".to_string() }
+fn default_amix_dropout_transition() -> f64 { 2.0 }
+fn default_amix_duration() -> String { "longest".to_string() }
+fn default_video_transition() -> String { "fade".to_string() }
+fn default_timecode_font_size() -> i32 { 32 }
+
+impl ProjectSettings {
+    pub fn get_amix_duration(&self) -> &str {
+        match self.amix_duration.as_str() {
+            "first" | "shortest" => &self.amix_duration,
+            _ => "longest",
+        }
+    }
+
+    pub fn get_output_resolution(&self) -> Option<(i32, i32)> {
+        match (self.output_width, self.output_height) {
+            (Some(w), Some(h)) if w > 0 && h > 0 => Some((w, h)),
+            _ => None,
+        }
+    }
+
+    // "16:9", "16/9" or "1.7778" as a setdar value, None for anything else
+    pub fn get_output_dar(&self) -> Option<String> {
+        let dar = self.output_dar.as_deref()?.trim();
+        let positive = |v: &str| v.trim().parse::<f64>().is_ok_and(|v| v.is_finite() && v > 0.0);
+        match dar.split_once([':', '/']) {
+            Some((w, h)) if positive(w) && positive(h) => Some(format!("{}/{}", w.trim(), h.trim())),
+            None if positive(dar) => Some(dar.to_string()),
+            _ => None,
+        }
+    }
+
+    pub fn get_output_fps(&self) -> Option<f64> {
+        self.output_fps.filter(|f| f.is_finite() && *f > 0.0)
+    }
+
+    // Unknown codecs fall back to aac; validate_project reports them
+    pub fn get_audio_codec(&self) -> &str {
+        match self.audio_codec.as_deref() {
+            Some(codec) if SUPPORTED_AUDIO_CODECS.contains(&codec) => codec,
+            _ => "aac",
+        }
+    }
+
+    pub fn get_h264_profile(&self) -> Option<&str> {
+        self.h264_profile.as_deref().filter(|p| H264_PROFILES.contains(p))
+    }
+
+    pub fn get_tune(&self, encoder: &str) -> Option<&str> {
+        let tune = self.tune.as_deref()?.trim();
+        encoder_tunes(encoder).contains(&tune).then_some(tune)
+    }
+
+    // "4.1" as level_idc 41, the integer form every H.264 encoder accepts for -level
+    pub fn get_h264_level(&self) -> Option<i32> {
+        let level = self.h264_level.as_deref()?.trim().parse::<f64>().ok()?;
+        let idc = (level * 10.0).round() as i32;
+        H264_LEVELS.contains(&idc).then_some(idc)
+    }
+
+    pub fn get_container(&self) -> Option<&str> {
+        self.container.as_deref().filter(|c| SUPPORTED_CONTAINERS.contains(c))
+    }
+
+    // Lossless codecs take no bitrate
+    pub fn get_audio_bitrate(&self) -> Option<&str> {
+        if self.get_audio_codec() == "flac" {
+            return None;
+        }
+        Some(self.audio_bitrate.as_deref().filter(|b| !b.trim().is_empty()).unwrap_or("192k"))
+    }
+
+    pub fn get_encoder_threads(&self) -> Option<i32> {
+        self.encoder_threads.filter(|t| *t > 0)
+    }
+
+    pub fn get_max_muxing_queue_size(&self) -> i32 {
+        self.max_muxing_queue_size.filter(|n| *n > 0).unwrap_or(DEFAULT_MAX_MUXING_QUEUE_SIZE)
+    }
+
+    // Guards the filter graph against anything that isn't a plain transition name
+    pub fn get_video_transition(&self) -> &str {
+        let name = self.video_transition.trim();
+        if !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric()) {
+            name
+        } else {
+            "fade"
+        }
+    }
+
+    // Both weights 0 count as unset
+    pub fn get_amix_weights(&self) -> Option<(f64, f64)> {
+        let (video, music) = self.amix_weights?;
+        let valid = |w: f64| w.is_finite() && w >= 0.0;
+        (valid(video) && valid(music) && video + music > 0.0).then_some((video, music))
+    }
+
+    pub fn get_amix_dropout_transition(&self) -> f64 {
+        if self.amix_dropout_transition.is_finite() {
+            self.amix_dropout_transition.max(0.0)
+        } else {
+            default_amix_dropout_transition()
+        }
+    }
+}
 
 impl Default for ProjectSettings {
     fn default() -> Self {
@@ -77,10 +865,55 @@ impl Default for ProjectSettings {
             audio_crossfade: 10.0,
             video_crossfade: 1.0,
             cut_music_at_end: false,
+            hold_last_frame: false,
+            audio_merge: false,
             video_volume: 100.0,
             music_volume: 70.0,
             use_gpu: true,
             speed_preset: "balanced".to_string(),
+            amix_dropout_transition: 2.0,
+            amix_duration: "longest".to_string(),
+            output_width: None,
+            output_height: None,
+            video_bitrate: None,
+            output_fps: None,
+            interpolation_mode: None,
+            color_range: None,
+            verify_after_export: false,
+            audio_codec: None,
+            audio_bitrate: None,
+            copy_audio_when_possible: true,
+            container: None,
+            poster_image: None,
+            encoder_threads: None,
+            max_muxing_queue_size: None,
+            low_priority: false,
+            video_transition: "fade".to_string(),
+            lut: None,
+            auto_deinterlace: true,
+            auto_rotate: true,
+            preserve_sar: false,
+            output_dar: None,
+            platform_target: None,
+            burn_timecode: false,
+            timecode_position: OverlayPosition::BottomRight,
+            timecode_font_size: 32,
+            video_fade_in: 0.0,
+            video_fade_out: 0.0,
+            export_range: None,
+            filename_template: None,
+            overwrite: OverwritePolicy::Rename,
+            snap_to_keyframes: false,
+            trim_accuracy: TrimAccuracy::Accurate,
+            transition_align: TransitionAlign::Trailing,
+            separate_audio_stems: false,
+            report_utilization: false,
+            mute_all: false,
+            h264_profile: None,
+            h264_level: None,
+            tune: None,
+            amix_weights: None,
+            force_stereo: false,
         }
     }
 }
@@ -93,6 +926,9 @@ pub struct Project {
     pub audio_tracks: Vec<AudioTrack>,
     #[serde(default)]
     pub settings: ProjectSettings,
+    // When set, exported instead of `videos`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub composition: Option<Composition>,
 }
 
 impl Default for Project {
@@ -101,43 +937,159 @@ impl Default for Project {
             videos: Vec::new(),
             audio_tracks: Vec::new(),
             settings: ProjectSettings::default(),
+            composition: None,
         }
     }
 }
 
 impl Project {
-    pub fn get_active_tracks(&self) -> Vec<&AudioTrack> {
-        if self.audio_tracks.is_empty() {
-            return vec![];
+    // The project as exported: disabled clips and tracks left out of the
+    // timeline (a composition keeps both its clips), then platform defaults
+    // applied to the settings
+    pub fn exported_project(&self) -> Project {
+        let mut project = self.clone();
+        project.videos.retain(|v| v.enabled);
+        project.audio_tracks.retain(|t| t.enabled);
+        if let Some(platform) = project.settings.platform_target {
+            platform.apply_defaults(&mut project.settings);
         }
+        project
+    }
 
-        let solos: Vec<_> = self.audio_tracks.iter().filter(|t| t.solo).collect();
+    pub fn get_active_tracks(&self) -> Vec<&AudioTrack> {
+        let enabled: Vec<_> = self.audio_tracks.iter().filter(|t| t.enabled).collect();
+        let solos: Vec<_> = enabled.iter().copied().filter(|t| t.solo).collect();
         if !solos.is_empty() {
             solos.into_iter().filter(|t| !t.mute).collect()
         } else {
-            self.audio_tracks.iter().filter(|t| !t.mute).collect()
+            enabled.into_iter().filter(|t| !t.mute).collect()
         }
     }
 
+    // The clips on the timeline with their index in `videos`, disabled ones skipped
+    pub fn get_enabled_clips(&self) -> (Vec<usize>, Vec<&VideoClip>) {
+        self.videos.iter().enumerate().filter(|(_, v)| v.enabled).unzip()
+    }
+
+    // The composed picture, including a held last frame
     pub fn get_video_duration(&self) -> f64 {
-        if self.videos.is_empty() {
+        self.get_footage_duration() + self.get_hold_duration()
+    }
+
+    fn get_footage_duration(&self) -> f64 {
+        match self.composition {
+            Some(ref composition) if composition.clips.len() == 2 => composition.get_duration(),
+            _ => get_timeline_duration(&self.get_enabled_clips().1, self.settings.video_crossfade, self.settings.transition_align),
+        }
+    }
+
+    // How long `hold_last_frame` freezes the picture for the music to finish
+    pub fn get_hold_duration(&self) -> f64 {
+        let settings = &self.settings;
+        if !settings.hold_last_frame || !settings.include_music || settings.mute_all {
             return 0.0;
         }
+        (self.get_mixed_music_duration() - self.get_footage_duration()).max(0.0)
+    }
+
+    // The export range clamped to the timeline, None when unset or empty
+    pub fn get_export_range(&self) -> Option<(f64, f64)> {
+        let (start, end) = self.settings.export_range?;
+        let start = start.max(0.0);
+        let end = end.min(self.get_video_duration());
+        (end > start).then_some((start, end))
+    }
 
-        let base: f64 = self.videos.iter().map(|v| v.duration).sum();
-        let overlap = self.settings.video_crossfade * (self.videos.len() as f64 - 1.0).max(0.0);
-        (base - overlap).max(0.0)
+    // Length of what actually gets written, honouring the export range
+    pub fn get_export_duration(&self) -> f64 {
+        match self.get_export_range() {
+            Some((start, end)) => end - start,
+            None => self.get_video_duration(),
+        }
     }
 
     pub fn get_music_duration(&self) -> f64 {
         self.get_active_tracks().iter().map(|t| t.duration).sum()
     }
+
+    // Active tracks back to back, minus the overlap of each crossfade; merged
+    // tracks run side by side, as long as the longest
+    pub fn get_mixed_music_duration(&self) -> f64 {
+        let tracks: Vec<_> = self.get_active_tracks().into_iter().cloned().collect();
+        if self.settings.audio_merge {
+            return tracks.iter().map(|t| t.duration).fold(0.0, f64::max);
+        }
+        let overlap: f64 = crate::ffmpeg::audio_crossfade_durations(&tracks, self.settings.audio_crossfade).iter().sum();
+        (self.get_music_duration() - overlap).max(0.0)
+    }
+
+    pub fn media_paths(&self) -> impl Iterator<Item = &String> {
+        self.videos
+            .iter()
+            .chain(self.composition.iter().flat_map(|c| c.clips.iter()))
+            .map(|v| &v.path)
+            .chain(self.audio_tracks.iter().map(|t| &t.path))
+    }
+
+    fn media_paths_mut(&mut self) -> impl Iterator<Item = &mut String> {
+        self.videos
+            .iter_mut()
+            .chain(self.composition.iter_mut().flat_map(|c| c.clips.iter_mut()))
+            .map(|v| &mut v.path)
+            .chain(self.audio_tracks.iter_mut().map(|t| &mut t.path))
+    }
+
+    pub fn get_missing_media(&self) -> Vec<String> {
+        self.media_paths()
+            .filter(|p| !Path::new(p).exists())
+            .cloned()
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExportPreset {
+    pub name: String,
+    pub settings: ProjectSettings,
+    #[serde(default)]
+    pub builtin: bool,
+}
+
+impl ExportPreset {
+    fn builtin(name: &str, width: i32, height: i32, bitrate: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            settings: ProjectSettings {
+                output_width: Some(width),
+                output_height: Some(height),
+                video_bitrate: Some(bitrate.to_string()),
+                ..ProjectSettings::default()
+            },
+            builtin: true,
+        }
+    }
+}
+
+pub fn get_builtin_presets() -> Vec<ExportPreset> {
+    vec![
+        ExportPreset::builtin("YouTube 1080p", 1920, 1080, "12M"),
+        ExportPreset::builtin("Instagram 9:16", 1080, 1920, "5M"),
+        ExportPreset::builtin("Twitter", 1280, 720, "5M"),
+    ]
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Config {
     #[serde(default = "default_last_directory")]
     pub last_directory: String,
+    // Per-dialog folders; empty in configs written before they existed, then
+    // filled from `last_directory` on load
+    #[serde(default)]
+    pub import_directory: String,
+    #[serde(default)]
+    pub export_directory: String,
+    #[serde(default)]
+    pub project_directory: String,
     #[serde(default = "default_theme")]
     pub theme: String,
     #[serde(default = "default_window_width")]
@@ -156,6 +1108,22 @@ pub struct Config {
     pub use_gpu: bool,
     #[serde(default = "default_speed_preset")]
     pub speed_preset: String,
+    #[serde(default)]
+    pub presets: Vec<ExportPreset>,
+    // Records which ffprobe method produced each duration, see `get_last_probe_log`,
+    // and logs every export and preview run for `collect_support_bundle`
+    #[serde(default)]
+    pub debug_logging: bool,
+    // Count packets when a file has no duration metadata at all (slow: reads the whole file)
+    #[serde(default = "default_true")]
+    pub packet_count_fallback: bool,
+    // Folder for previews, waveforms and other scratch files instead of the system temp dir
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub temp_dir: Option<String>,
+    // A failed ffmpeg run leaves its command, its stderr and the partial output
+    // behind, listed in `ExportResult::kept_files`
+    #[serde(default)]
+    pub keep_temp_on_error: bool,
 }
 
 fn default_last_directory() -> String {
@@ -169,6 +1137,9 @@ impl Default for Config {
     fn default() -> Self {
         Self {
             last_directory: default_last_directory(),
+            import_directory: default_last_directory(),
+            export_directory: default_last_directory(),
+            project_directory: default_last_directory(),
             theme: "modern".to_string(),
             window_width: 1100,
             window_height: 700,
@@ -178,6 +1149,39 @@ impl Default for Config {
             video_volume: 100.0,
             use_gpu: true,
             speed_preset: "balanced".to_string(),
+            presets: Vec::new(),
+            debug_logging: false,
+            packet_count_fallback: true,
+            temp_dir: None,
+            keep_temp_on_error: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DirectoryKind {
+    Import,
+    Export,
+    Project,
+}
+
+impl Config {
+    fn directory_mut(&mut self, kind: DirectoryKind) -> &mut String {
+        match kind {
+            DirectoryKind::Import => &mut self.import_directory,
+            DirectoryKind::Export => &mut self.export_directory,
+            DirectoryKind::Project => &mut self.project_directory,
+        }
+    }
+
+    fn fill_directories(&mut self) {
+        for kind in [DirectoryKind::Import, DirectoryKind::Export, DirectoryKind::Project] {
+            let fallback = self.last_directory.clone();
+            let dir = self.directory_mut(kind);
+            if dir.is_empty() {
+                *dir = fallback;
+            }
         }
     }
 }
@@ -186,6 +1190,34 @@ fn get_config_path() -> PathBuf {
     dirs::home_dir().unwrap_or_default().join(".video_musique_config.json")
 }
 
+const AUTOSAVE_KEEP: usize = 5;
+
+fn get_autosave_dir() -> PathBuf {
+    dirs::config_dir()
+        .or_else(dirs::home_dir)
+        .unwrap_or_default()
+        .join("video_musique")
+        .join("autosave")
+}
+
+// Oldest first; the zero-padded timestamp in the name keeps lexical order chronological
+fn list_autosaves() -> Vec<PathBuf> {
+    let mut files: Vec<PathBuf> = fs::read_dir(get_autosave_dir())
+        .map(|entries| {
+            entries
+                .filter_map(|e| e.ok().map(|e| e.path()))
+                .filter(|p| {
+                    p.file_name()
+                        .map(|n| n.to_string_lossy().starts_with("autosave_"))
+                        .unwrap_or(false)
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    files.sort();
+    files
+}
+
 // Tauri commands
 
 #[tauri::command]
@@ -197,25 +1229,386 @@ pub fn save_project(project: Project, file_path: String) -> Result<(), String> {
 #[tauri::command]
 pub fn load_project(file_path: String) -> Result<Project, String> {
     let content = fs::read_to_string(&file_path).map_err(|e| format!("Impossible de charger le projet: {}", e))?;
-    serde_json::from_str(&content).map_err(|e| format!("Format de projet invalide: {}", e))
+    let mut project: Project = serde_json::from_str(&content).map_err(|e| format!("Format de projet invalide: {}", e))?;
+
+    if let Some(automation) = crate::automation::load_sidecar(&file_path)? {
+        automation.apply_to(&mut project);
+    }
+    Ok(project)
+}
+
+// Clip boundaries of the sequential timeline, as the exporter lays them out.
+// Disabled clips get no marker; `clip_index` still points into `videos`.
+#[tauri::command]
+pub fn get_project_markers(project: Project) -> Vec<TimelineMarker> {
+    let (indices, clips) = project.get_enabled_clips();
+    let mut markers = get_timeline_markers(&clips, project.settings.video_crossfade, project.settings.transition_align);
+    for marker in &mut markers {
+        marker.clip_index = indices[marker.clip_index];
+    }
+    markers
+}
+
+// Clip lengths that land the cuts on the given beat times (seconds), see `trim_to_beat_grid`
+#[tauri::command]
+pub fn snap_clips_to_beats(project: Project, beats: Vec<f64>) -> Vec<f64> {
+    let (indices, clips) = project.get_enabled_clips();
+    let snapped = trim_to_beat_grid(&clips, &beats, project.settings.video_crossfade, project.settings.transition_align);
+    // Disabled clips keep their length
+    let mut lengths: Vec<f64> = project.videos.iter().map(VideoClip::get_effective_duration).collect();
+    for (i, length) in indices.into_iter().zip(snapped) {
+        lengths[i] = length;
+    }
+    lengths
+}
+
+#[tauri::command]
+pub fn find_missing_media(project: Project) -> Vec<String> {
+    project.get_missing_media()
+}
+
+#[tauri::command]
+pub fn relink_media(mut project: Project, old_root: String, new_root: String) -> Result<Project, String> {
+    let mut not_found = Vec::new();
+
+    for path in project.media_paths_mut() {
+        if let Ok(rest) = Path::new(path.as_str()).strip_prefix(&old_root) {
+            let relinked = Path::new(&new_root).join(rest);
+            if relinked.exists() {
+                *path = relinked.to_string_lossy().to_string();
+            } else {
+                not_found.push(relinked.to_string_lossy().to_string());
+            }
+        }
+    }
+
+    if !not_found.is_empty() {
+        return Err(format!("Fichiers introuvables apres relocalisation: {}", not_found.join(", ")));
+    }
+    Ok(project)
 }
 
 #[tauri::command]
 pub fn get_config() -> Config {
     let config_path = get_config_path();
-    if config_path.exists() {
+    let mut config: Config = if config_path.exists() {
         fs::read_to_string(&config_path)
             .ok()
             .and_then(|s| serde_json::from_str(&s).ok())
             .unwrap_or_default()
     } else {
         Config::default()
-    }
+    };
+    config.fill_directories();
+    config
 }
 
-#[tauri::command]
-pub fn set_config(config: Config) -> Result<(), String> {
+fn write_config(config: &Config) -> Result<(), String> {
     let config_path = get_config_path();
-    let json = serde_json::to_string_pretty(&config).map_err(|e| e.to_string())?;
+    let json = serde_json::to_string_pretty(config).map_err(|e| e.to_string())?;
     fs::write(&config_path, json).map_err(|e| format!("Impossible de sauvegarder la configuration: {}", e))
 }
+
+// The configured scratch folder, or the system one when unset or gone
+pub fn get_temp_dir() -> PathBuf {
+    get_config()
+        .temp_dir
+        .filter(|dir| !dir.trim().is_empty())
+        .map(PathBuf::from)
+        .filter(|dir| dir.is_dir())
+        .unwrap_or_else(std::env::temp_dir)
+}
+
+// Creates the folder if needed and checks it can be written to
+fn check_temp_dir(dir: &str) -> Result<(), String> {
+    let probe = Path::new(dir).join(format!(".video_musique_{}", std::process::id()));
+    fs::create_dir_all(dir)
+        .and_then(|_| fs::write(&probe, b""))
+        .map_err(|e| format!("Dossier temporaire inutilisable ({}): {}", dir, e))?;
+    let _ = fs::remove_file(probe);
+    Ok(())
+}
+
+#[tauri::command]
+pub fn set_config(state: State<'_, AppState>, config: Config) -> Result<(), String> {
+    if let Some(dir) = config.temp_dir.as_deref().filter(|d| !d.trim().is_empty()) {
+        check_temp_dir(dir)?;
+    }
+    {
+        let mut ffmpeg = state.ffmpeg.lock().unwrap();
+        ffmpeg.debug_logging = config.debug_logging;
+        crate::support::set_logging(config.debug_logging);
+        crate::support::set_keep_failed(config.keep_temp_on_error);
+        ffmpeg.packet_count_fallback = config.packet_count_fallback;
+    }
+    write_config(&config)
+}
+
+// Stores the folder a dialog of this kind was last confirmed in; `last_directory`
+// keeps tracking the most recent one
+#[tauri::command]
+pub fn remember_directory(kind: DirectoryKind, directory: String) -> Result<(), String> {
+    let mut config = get_config();
+    *config.directory_mut(kind) = directory.clone();
+    config.last_directory = directory;
+    write_config(&config)
+}
+
+#[tauri::command]
+pub fn autosave_project(project: Project) -> Result<(), String> {
+    let dir = get_autosave_dir();
+    fs::create_dir_all(&dir).map_err(|e| format!("Impossible de creer le dossier d'autosauvegarde: {}", e))?;
+
+    let millis = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis();
+    let json = serde_json::to_string_pretty(&project).map_err(|e| e.to_string())?;
+    fs::write(dir.join(format!("autosave_{:015}.mixproj", millis)), json)
+        .map_err(|e| format!("Impossible d'autosauvegarder le projet: {}", e))?;
+
+    let files = list_autosaves();
+    if files.len() > AUTOSAVE_KEEP {
+        for old in &files[..files.len() - AUTOSAVE_KEEP] {
+            let _ = fs::remove_file(old);
+        }
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub fn recover_project() -> Option<Project> {
+    list_autosaves()
+        .iter()
+        .rev()
+        .find_map(|p| fs::read_to_string(p).ok().and_then(|s| serde_json::from_str(&s).ok()))
+}
+
+#[tauri::command]
+pub fn clear_autosaves() {
+    for file in list_autosaves() {
+        let _ = fs::remove_file(file);
+    }
+}
+
+#[tauri::command]
+pub fn list_presets() -> Vec<ExportPreset> {
+    let mut presets = get_builtin_presets();
+    presets.extend(get_config().presets);
+    presets
+}
+
+#[tauri::command]
+pub fn apply_preset(name: String) -> Result<ProjectSettings, String> {
+    list_presets()
+        .into_iter()
+        .rev()
+        .find(|p| p.name == name)
+        .map(|p| p.settings)
+        .ok_or_else(|| format!("Prereglage introuvable: {}", name))
+}
+
+#[tauri::command]
+pub fn save_preset(name: String, settings: ProjectSettings) -> Result<(), String> {
+    if name.trim().is_empty() {
+        return Err("Le nom du prereglage est vide".to_string());
+    }
+    if get_builtin_presets().iter().any(|p| p.name == name) {
+        return Err(format!("Le prereglage integre \"{}\" ne peut pas etre remplace", name));
+    }
+
+    let mut config = get_config();
+    config.presets.retain(|p| p.name != name);
+    config.presets.push(ExportPreset { name, settings, builtin: false });
+    write_config(&config)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::clip_of;
+
+    #[test]
+    fn cuts_are_moved_onto_beats() {
+        let trimmed = |end: f64| VideoClip { end: Some(end), duration: 20.0, ..clip_of(20.0) };
+        let clips = vec![trimmed(4.3), trimmed(5.0), trimmed(6.0)];
+        let beats: Vec<f64> = (1..20).map(|b| b as f64 * 2.0).collect();
+        let close = |a: Vec<f64>, b: &[f64]| a.iter().zip(b).all(|(x, y)| (x - y).abs() < 1e-9);
+
+        // The crossfades' middles land on 4 and 8; the last clip keeps its length
+        let lengths = trim_to_beat_grid(&clips, &beats, 1.0, TransitionAlign::Trailing);
+        assert!(close(lengths.clone(), &[4.5, 5.0, 6.0]), "{:?}", lengths);
+
+        // Centered fades don't overlap the clips, so the cut is the clip's own edge
+        let lengths = trim_to_beat_grid(&clips, &beats, 1.0, TransitionAlign::Centered);
+        assert!(close(lengths.clone(), &[4.0, 4.0, 6.0]), "{:?}", lengths);
+
+        // A source too short to reach any beat is left alone
+        let short = vec![VideoClip { end: None, ..clip_of(1.5) }, clip_of(3.0)];
+        assert_eq!(trim_to_beat_grid(&short, &[10.0], 0.0, TransitionAlign::Trailing), vec![1.5, 3.0]);
+
+        // Nor is one trimmed to within MIN_BEAT_CLIP of its source's end
+        let tail = vec![VideoClip { start: 9.95, ..clip_of(10.0) }, clip_of(3.0)];
+        let beats: Vec<f64> = (1..300).map(|b| b as f64 * 0.01).collect();
+        let lengths = trim_to_beat_grid(&tail, &beats, 0.0, TransitionAlign::Trailing);
+        assert!(close(lengths.clone(), &[0.05, 3.0]), "{:?}", lengths);
+    }
+
+    #[test]
+    fn unusable_beats_and_unprobed_clips_keep_their_length() {
+        let clips = vec![VideoClip { end: Some(4.0), ..clip_of(10.0) }, clip_of(0.0), clip_of(5.0)];
+        let lengths = trim_to_beat_grid(&clips, &[f64::NAN, -3.0], 0.0, TransitionAlign::Trailing);
+        assert_eq!(lengths, vec![4.0, 0.0, 5.0]);
+        // Beats come unsorted from the analysis; the nearest reachable one is used
+        let lengths = trim_to_beat_grid(&clips, &[9.0, 5.0, 3.5], 0.0, TransitionAlign::Trailing);
+        assert_eq!(lengths, vec![3.5, 0.0, 5.0]);
+    }
+
+    #[test]
+    fn markers_follow_gaps_and_crossfades() {
+        let gapped = VideoClip { gap_before: 1.0, ..clip_of(4.0) };
+        let clips = vec![clip_of(6.0), gapped, clip_of(3.0), VideoClip { crossfade_in: Some(0.0), ..clip_of(5.0) }];
+
+        let markers = get_timeline_markers(&clips, 1.5, TransitionAlign::Trailing);
+        let fades: Vec<Option<f64>> = markers.iter().map(|m| m.crossfade_start).collect();
+        assert_eq!(fades, vec![Some(4.5), Some(8.0), None, None]);
+        assert!(markers.windows(2).all(|w| w[1].start <= w[0].end), "{:?}", markers);
+        assert_eq!(markers.last().unwrap().end, get_timeline_duration(&clips, 1.5, TransitionAlign::Trailing));
+
+        // Centered fades straddle the cut, half on each clip
+        let markers = get_timeline_markers(&[clip_of(3.0), clip_of(3.0)], 1.0, TransitionAlign::Centered);
+        assert_eq!(markers[0].crossfade_start, Some(2.5));
+        assert_eq!(markers[1].start, 2.5);
+    }
+
+    #[test]
+    fn contiguous_cuts_of_one_source_are_not_crossfaded() {
+        let mut first = clip_of(20.0);
+        first.end = Some(8.0);
+        let mut second = clip_of(20.0);
+        second.start = 8.0;
+        second.end = Some(14.0);
+        let mut jump = clip_of(20.0);
+        jump.start = 16.0;
+        let clips = vec![first, second, jump, clip_of(5.0)];
+
+        assert_eq!(get_transition_durations(&clips, 1.0), vec![0.0, 1.0, 1.0]);
+        let mut asked = clips.clone();
+        asked[1].crossfade_in = Some(0.5);
+        assert_eq!(get_transition_durations(&asked, 1.0), vec![0.5, 1.0, 1.0]);
+    }
+
+    #[test]
+    fn crossfades_are_clamped_to_the_clips() {
+        let clips = vec![clip_of(10.0), clip_of(1.0), clip_of(10.0)];
+        // The short clip caps the first crossfade and is used up by it
+        assert_eq!(get_transition_durations(&clips, 2.0), vec![1.0, 0.0]);
+        assert_eq!(get_timeline_duration(&clips, 2.0, TransitionAlign::Trailing), 20.0);
+
+        // A clip's own crossfade_in wins over the default, within the same limits
+        let mut asked = vec![clip_of(10.0), clip_of(10.0), clip_of(10.0)];
+        asked[1].crossfade_in = Some(3.0);
+        asked[2].crossfade_in = Some(-1.0);
+        assert_eq!(get_transition_durations(&asked, 1.0), vec![3.0, 0.0]);
+        assert_eq!(get_transition_durations(&[clip_of(3.0), clip_of(2.0)], 10.0), vec![2.0]);
+        assert!(get_transition_durations(&[clip_of(3.0)], 1.0).is_empty());
+    }
+
+    #[test]
+    fn eq_gains_are_clamped_and_neutral_bands_dropped() {
+        assert_eq!(AudioEq { bass_db: 6.0, treble_db: 0.0 }.get_filter(), Some("bass=g=6".to_string()));
+        assert_eq!(AudioEq { bass_db: -30.0, treble_db: 25.0 }.get_filter(), Some("bass=g=-20,treble=g=20".to_string()));
+        assert_eq!(AudioEq::default().get_filter(), None);
+    }
+
+    #[test]
+    fn panning_attenuates_the_opposite_side() {
+        let panned = |pan: f64| AudioTrack { pan: Some(pan), ..AudioTrack::default() }.get_pan_filter();
+        assert_eq!(panned(-0.5), Some("aformat=channel_layouts=stereo,pan=stereo|c0=1*c0|c1=0.5*c1".to_string()));
+        assert_eq!(panned(3.0), Some("aformat=channel_layouts=stereo,pan=stereo|c0=0*c0|c1=1*c1".to_string()));
+        assert_eq!(panned(0.0), None);
+        assert_eq!(panned(f64::NAN), None);
+    }
+
+    #[test]
+    fn platform_defaults_only_fill_unset_settings() {
+        let mut settings = ProjectSettings { video_bitrate: Some("20M".to_string()), output_width: Some(720), ..ProjectSettings::default() };
+        Platform::Instagram.apply_defaults(&mut settings);
+        assert_eq!(settings.video_bitrate.as_deref(), Some("20M"));
+        // A size half given is left for the exporter to complete
+        assert_eq!((settings.output_width, settings.output_height), (Some(720), None));
+        assert_eq!(settings.output_dar.as_deref(), Some("9:16"));
+        assert_eq!(settings.output_fps, Some(30.0));
+
+        let mut settings = ProjectSettings::default();
+        Platform::Discord.apply_defaults(&mut settings);
+        assert_eq!(settings.get_output_resolution(), Some((1280, 720)));
+        assert_eq!(settings.get_h264_profile(), Some("main"));
+    }
+
+    #[test]
+    fn export_ranges_are_clamped_to_the_timeline() {
+        let mut project = Project { videos: vec![clip_of(10.0)], ..Project::default() };
+        project.settings.export_range = Some((-2.0, 4.0));
+        assert_eq!(project.get_export_range(), Some((0.0, 4.0)));
+        project.settings.export_range = Some((6.0, 30.0));
+        assert_eq!(project.get_export_duration(), 4.0);
+        project.settings.export_range = Some((12.0, 15.0));
+        assert_eq!(project.get_export_range(), None);
+        assert_eq!(project.get_export_duration(), 10.0);
+    }
+
+    #[test]
+    fn unusable_amix_weights_count_as_unset() {
+        let weights = |w: (f64, f64)| ProjectSettings { amix_weights: Some(w), ..ProjectSettings::default() }.get_amix_weights();
+        assert_eq!(weights((1.0, 0.5)), Some((1.0, 0.5)));
+        assert_eq!(weights((0.0, 1.0)), Some((0.0, 1.0)));
+        assert_eq!(weights((0.0, 0.0)), None);
+        assert_eq!(weights((-1.0, 1.0)), None);
+        assert_eq!(weights((f64::INFINITY, 1.0)), None);
+    }
+
+    #[test]
+    fn amix_dropout_transition_is_kept_usable() {
+        let dropout = |d: f64| ProjectSettings { amix_dropout_transition: d, ..ProjectSettings::default() }.get_amix_dropout_transition();
+        assert_eq!(dropout(0.5), 0.5);
+        assert_eq!(dropout(-3.0), 0.0);
+        assert_eq!(dropout(f64::NAN), default_amix_dropout_transition());
+    }
+
+    #[test]
+    fn color_adjustments_are_clamped_to_the_eq_filter() {
+        assert_eq!(ColorAdjust::default().get_filter(), None);
+        let adjust = ColorAdjust { brightness: 2.0, contrast: 1.2, saturation: 5.0, gamma: 0.0 };
+        assert_eq!(adjust.get_filter(), Some("eq=brightness=1:contrast=1.2:saturation=3:gamma=0.1".to_string()));
+        assert!(VideoClip { color: Some(adjust), ..clip_of(5.0) }.needs_filtering());
+    }
+
+    #[test]
+    fn display_aspect_ratios_are_checked_before_use() {
+        let dar = |value: &str| ProjectSettings { output_dar: Some(value.to_string()), ..ProjectSettings::default() }.get_output_dar();
+        assert_eq!(dar("16:9"), Some("16/9".to_string()));
+        assert_eq!(dar(" 4 / 3 "), Some("4/3".to_string()));
+        assert_eq!(dar("1.7778"), Some("1.7778".to_string()));
+        assert_eq!(dar("16:0"), None);
+        assert_eq!(dar("wide[v]"), None);
+    }
+
+    #[test]
+    fn disabled_items_are_dropped_from_the_exported_project() {
+        let mut project = Project {
+            videos: vec![clip_of(4.0), VideoClip { enabled: false, ..clip_of(6.0) }],
+            audio_tracks: vec![AudioTrack { enabled: false, solo: true, ..AudioTrack::default() }, AudioTrack::default()],
+            ..Project::default()
+        };
+        project.settings.platform_target = Some(Platform::Youtube);
+
+        // A disabled solo track doesn't silence the others
+        assert_eq!(project.get_active_tracks().len(), 1);
+        let (indices, clips) = project.get_enabled_clips();
+        assert_eq!((indices, clips.len()), (vec![0], 1));
+        let exported = project.exported_project();
+        assert_eq!((exported.videos.len(), exported.audio_tracks.len()), (1, 1));
+        assert_eq!(exported.settings.get_output_resolution(), Some((1920, 1080)));
+    }
+}
@@ -0,0 +1,109 @@
+use serde::{Deserialize, Serialize};
+use std::process::Command;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+use crate::ffmpeg::EventSink;
+
+const SAMPLE_INTERVAL: Duration = Duration::from_secs(1);
+const STOP_POLL: Duration = Duration::from_millis(100);
+
+// None means the value can't be read on this machine
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UtilizationSample {
+    pub cpu_percent: Option<f64>,
+    pub gpu_percent: Option<f64>,
+    // Load of the dedicated encoder block (NVENC), which `gpu_percent` doesn't show
+    pub encoder_percent: Option<f64>,
+}
+
+// Busy and total jiffies summed over all cores, from the first line of /proc/stat
+#[cfg(target_os = "linux")]
+fn read_cpu_times() -> Option<(u64, u64)> {
+    let stat = std::fs::read_to_string("/proc/stat").ok()?;
+    let fields: Vec<u64> = stat.lines().next()?.split_whitespace().skip(1).filter_map(|v| v.parse().ok()).collect();
+    let idle = fields.get(3)? + fields.get(4).unwrap_or(&0);
+    let total: u64 = fields.iter().take(8).sum();
+    Some((total - idle, total))
+}
+
+#[cfg(not(target_os = "linux"))]
+fn read_cpu_times() -> Option<(u64, u64)> {
+    None
+}
+
+// "GPU %, encoder %" of the first NVIDIA card
+fn query_nvidia() -> Option<(f64, f64)> {
+    let output = Command::new("nvidia-smi")
+        .args(["--query-gpu=utilization.gpu,utilization.encoder", "--format=csv,noheader,nounits"])
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+
+    let out = String::from_utf8_lossy(&output.stdout);
+    let (gpu, encoder) = out.lines().next()?.split_once(',')?;
+    Some((gpu.trim().parse().ok()?, encoder.trim().parse().ok()?))
+}
+
+// Emits an `export-utilization` event every second until dropped. GPU load is
+// only queried for NVIDIA, the one vendor with a cheap non-root query tool.
+pub struct UtilizationSampler {
+    stop: Arc<AtomicBool>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl UtilizationSampler {
+    pub(crate) fn start(events: Box<dyn EventSink>, gpu_type: Option<&str>) -> Self {
+        let stop = Arc::new(AtomicBool::new(false));
+        let query_gpu = gpu_type == Some("nvidia");
+        let flag = stop.clone();
+
+        let handle = std::thread::spawn(move || {
+            let mut previous = read_cpu_times();
+            loop {
+                let mut waited = Duration::ZERO;
+                while waited < SAMPLE_INTERVAL && !flag.load(Ordering::SeqCst) {
+                    std::thread::sleep(STOP_POLL);
+                    waited += STOP_POLL;
+                }
+                if flag.load(Ordering::SeqCst) {
+                    break;
+                }
+
+                let current = read_cpu_times();
+                let cpu_percent = match (previous, current) {
+                    (Some((busy0, total0)), Some((busy1, total1))) if total1 > total0 => {
+                        Some(busy1.saturating_sub(busy0) as f64 / (total1 - total0) as f64 * 100.0)
+                    }
+                    _ => None,
+                };
+                previous = current;
+
+                let gpu = if query_gpu { query_nvidia() } else { None };
+                events.send(
+                    "export-utilization",
+                    serde_json::json!(UtilizationSample {
+                        cpu_percent,
+                        gpu_percent: gpu.map(|g| g.0),
+                        encoder_percent: gpu.map(|g| g.1),
+                    }),
+                );
+            }
+        });
+
+        Self { stop, handle: Some(handle) }
+    }
+}
+
+impl Drop for UtilizationSampler {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::SeqCst);
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
@@ -0,0 +1,272 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::ffmpeg::{is_pipe_output, FFmpegProcessor};
+use crate::models::{OverwritePolicy, Project};
+
+pub const DEFAULT_FILENAME_TEMPLATE: &str = "{name}_{date}_{resolution}.{ext}";
+const DEFAULT_EXTENSION: &str = "mp4";
+// Highest {counter} tried before giving up on finding a free name
+const MAX_COUNTER: u32 = 9999;
+
+// Inverse of ordering::days_from_civil: (year, month, day) of a day count since 1970-01-01
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let z = days + 719468;
+    let era = if z >= 0 { z } else { z - 146096 } / 146097;
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    (if month <= 2 { yoe + era * 400 + 1 } else { yoe + era * 400 }, month, day)
+}
+
+// UTC "YYYY-MM-DD" and "HH-MM-SS" (no colons, they are invalid in Windows file names)
+fn date_and_time() -> (String, String) {
+    let secs = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs() as i64).unwrap_or(0);
+    let (year, month, day) = civil_from_days(secs.div_euclid(86400));
+    let rem = secs.rem_euclid(86400);
+    (
+        format!("{:04}-{:02}-{:02}", year, month, day),
+        format!("{:02}-{:02}-{:02}", rem / 3600, rem % 3600 / 60, rem % 60),
+    )
+}
+
+fn sanitize(value: &str) -> String {
+    value
+        .chars()
+        .map(|c| if matches!(c, '<' | '>' | ':' | '"' | '/' | '\\' | '|' | '?' | '*') || c.is_control() { '_' } else { c })
+        .collect()
+}
+
+// File name of the first enabled clip
+fn project_name(project: &Project) -> String {
+    project
+        .get_enabled_clips()
+        .1
+        .first()
+        .and_then(|v| Path::new(&v.path).file_stem().map(|s| s.to_string_lossy().to_string()))
+        .unwrap_or_else(|| "export".to_string())
+}
+
+fn resolution(project: &Project) -> String {
+    project
+        .settings
+        .get_output_resolution()
+        .or_else(|| {
+            let props = FFmpegProcessor::probe_video_properties(&project.get_enabled_clips().1.first()?.path)?;
+            (props.width > 0 && props.height > 0).then_some(props.frame_size(project.settings.auto_rotate))
+        })
+        .map(|(w, h)| format!("{}x{}", w, h))
+        .unwrap_or_else(|| "source".to_string())
+}
+
+// Follows a forced container, mp4 otherwise
+fn extension(project: &Project) -> &'static str {
+    match project.settings.get_container() {
+        Some("matroska") => "mkv",
+        Some("webm") => "webm",
+        Some("mov") => "mov",
+        Some("mpegts") => "ts",
+        _ => DEFAULT_EXTENSION,
+    }
+}
+
+// Tokens: {name} {date} {time} {resolution} {codec} {counter} {ext}
+pub fn expand_template(template: &str, project: &Project, codec: &str, counter: u32) -> String {
+    let (date, time) = date_and_time();
+    let expanded = template
+        .replace("{name}", &project_name(project))
+        .replace("{date}", &date)
+        .replace("{time}", &time)
+        .replace("{resolution}", &resolution(project))
+        .replace("{codec}", codec)
+        .replace("{counter}", &format!("{:03}", counter))
+        .replace("{ext}", extension(project));
+    sanitize(&expanded)
+}
+
+// A full file name is used as is; a directory gets one from the project's template.
+// With `Rename` an existing file bumps {counter}, or numbers a name without one.
+pub fn resolve_output_path(project: &Project, output_path: &str, codec: &str) -> Result<String, String> {
+    let dir = Path::new(output_path);
+    if !dir.is_dir() {
+        return Ok(output_path.to_string());
+    }
+
+    let template = project.settings.filename_template.as_deref().unwrap_or(DEFAULT_FILENAME_TEMPLATE);
+    if template.contains('/') || template.contains('\\') {
+        return Err(format!("Le modele de nom \"{}\" ne doit pas contenir de dossier", template));
+    }
+
+    for counter in 1..=MAX_COUNTER {
+        let name = expand_template(template, project, codec, counter);
+        if name.trim().is_empty() || name.starts_with('.') || Path::new(&name).extension().is_none() {
+            return Err(format!("Le modele de nom \"{}\" donne un nom de fichier invalide: \"{}\"", template, name));
+        }
+
+        let path = dir.join(&name);
+        if !path.exists() || project.settings.overwrite == OverwritePolicy::Always {
+            return Ok(path.to_string_lossy().to_string());
+        }
+        match project.settings.overwrite {
+            OverwritePolicy::Never => return Err(format!("Le fichier {} existe deja", path.display())),
+            _ if !template.contains("{counter}") => return numbered_path(&path).map(|p| p.to_string_lossy().to_string()),
+            _ => {}
+        }
+    }
+
+    Err(format!("Aucun nom libre trouve dans {} pour le modele \"{}\"", dir.display(), template))
+}
+
+fn same_file(a: &Path, b: &Path) -> bool {
+    match (fs::canonicalize(a), fs::canonicalize(b)) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => a == b,
+    }
+}
+
+// "name_001.ext", "name_002.ext"... next to `path`, the first that's free
+fn numbered_path(path: &Path) -> Result<PathBuf, String> {
+    let stem = path.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default();
+    let ext = path.extension().map(|e| format!(".{}", e.to_string_lossy())).unwrap_or_default();
+    (1..=MAX_COUNTER)
+        .map(|counter| path.with_file_name(format!("{}_{:03}{}", stem, counter, ext)))
+        .find(|p| !p.exists())
+        .ok_or_else(|| format!("Aucun nom libre trouve pour {}", path.display()))
+}
+
+// Settles an existing output before ffmpeg runs, per `overwrite`. The export's
+// own inputs are refused whatever the policy.
+pub fn apply_overwrite_policy(output_path: &str, policy: OverwritePolicy, inputs: &[&str]) -> Result<String, String> {
+    let path = Path::new(output_path);
+    if is_pipe_output(output_path) || !path.exists() {
+        return Ok(output_path.to_string());
+    }
+    if inputs.iter().any(|input| same_file(path, Path::new(input))) {
+        return Err(format!("La sortie {} est aussi un fichier source de l'export", output_path));
+    }
+    match policy {
+        OverwritePolicy::Always => Ok(output_path.to_string()),
+        OverwritePolicy::Never => Err(format!("Le fichier {} existe deja", output_path)),
+        OverwritePolicy::Rename => numbered_path(path).map(|p| p.to_string_lossy().to_string()),
+    }
+}
+
+// ffmpeg's own flag to go with the policy: with `-n` a file that turns up
+// between the check and the run still isn't replaced. A named pipe exists by
+// definition, and `-n` would refuse to write into it.
+pub fn overwrite_flag(policy: OverwritePolicy, output_path: &str) -> &'static str {
+    match policy {
+        OverwritePolicy::Never | OverwritePolicy::Rename if !is_pipe_output(output_path) => "-n",
+        _ => "-y",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::work_dir;
+
+    fn path_in(dir: &Path, name: &str) -> String {
+        dir.join(name).to_string_lossy().to_string()
+    }
+
+    #[test]
+    fn existing_outputs_follow_the_overwrite_policy() {
+        let dir = work_dir("overwrite");
+        let (source, output) = (path_in(&dir, "source.mp4"), path_in(&dir, "out.mp4"));
+        fs::write(&source, b"").unwrap();
+
+        assert_eq!(apply_overwrite_policy(&output, OverwritePolicy::Never, &[&source]), Ok(output.clone()));
+        fs::write(&output, b"").unwrap();
+        assert_eq!(apply_overwrite_policy(&output, OverwritePolicy::Always, &[&source]), Ok(output.clone()));
+        assert!(apply_overwrite_policy(&output, OverwritePolicy::Never, &[&source]).is_err());
+        assert_eq!(apply_overwrite_policy(&output, OverwritePolicy::Rename, &[&source]), Ok(path_in(&dir, "out_001.mp4")));
+
+        // Inputs are refused even with Always
+        assert!(apply_overwrite_policy(&source, OverwritePolicy::Always, &[&source]).is_err());
+        assert_eq!(overwrite_flag(OverwritePolicy::Always, &output), "-y");
+        assert_eq!(overwrite_flag(OverwritePolicy::Rename, &output), "-n");
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    fn project_with(template: &str, overwrite: OverwritePolicy) -> Project {
+        let mut project = Project {
+            videos: vec![crate::models::VideoClip { path: "/media/holiday.mov".to_string(), ..Default::default() }],
+            ..Project::default()
+        };
+        project.settings.filename_template = Some(template.to_string());
+        project.settings.output_width = Some(1280);
+        project.settings.output_height = Some(720);
+        project.settings.overwrite = overwrite;
+        project
+    }
+
+    #[test]
+    fn templates_name_the_output_in_a_directory() {
+        let dir = work_dir("template");
+        let dir_str = dir.to_string_lossy().to_string();
+
+        let project = project_with("{name}_{resolution}_{codec}_{counter}.{ext}", OverwritePolicy::Rename);
+        let first = resolve_output_path(&project, &dir_str, "libx264").unwrap();
+        assert_eq!(first, path_in(&dir, "holiday_1280x720_libx264_001.mp4"));
+        fs::write(&first, b"").unwrap();
+        // Rename bumps {counter} past the existing file
+        assert_eq!(resolve_output_path(&project, &dir_str, "libx264"), Ok(path_in(&dir, "holiday_1280x720_libx264_002.mp4")));
+
+        // Without {counter} the name is numbered instead
+        let project = project_with("{name}.{ext}", OverwritePolicy::Rename);
+        fs::write(dir.join("holiday.mp4"), b"").unwrap();
+        assert_eq!(resolve_output_path(&project, &dir_str, "libx264"), Ok(path_in(&dir, "holiday_001.mp4")));
+        let project = project_with("{name}.{ext}", OverwritePolicy::Always);
+        assert_eq!(resolve_output_path(&project, &dir_str, "libx264"), Ok(path_in(&dir, "holiday.mp4")));
+        let project = project_with("{name}.{ext}", OverwritePolicy::Never);
+        assert!(resolve_output_path(&project, &dir_str, "libx264").is_err());
+
+        // A file path is used as given
+        assert_eq!(resolve_output_path(&project, "/tmp/x/out.mkv", "libx264"), Ok("/tmp/x/out.mkv".to_string()));
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn invalid_templates_are_refused() {
+        let dir = work_dir("invalid_template");
+        let dir_str = dir.to_string_lossy().to_string();
+        for template in ["sub/{name}.{ext}", "{name}", ".{ext}"] {
+            let project = project_with(template, OverwritePolicy::Rename);
+            assert!(resolve_output_path(&project, &dir_str, "libx264").is_err(), "{}", template);
+        }
+        assert_eq!(sanitize("a:b?c*d"), "a_b_c_d");
+        assert_eq!(civil_from_days(0), (1970, 1, 1));
+        assert_eq!(civil_from_days(19723), (2024, 1, 1));
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn a_disabled_first_clip_does_not_name_the_output() {
+        let mut project = project_with("{name}.{ext}", OverwritePolicy::Rename);
+        project.videos.insert(0, crate::models::VideoClip { path: "/media/intro.mov".to_string(), enabled: false, ..Default::default() });
+        assert_eq!(project_name(&project), "holiday");
+        assert_eq!(project_name(&Project::default()), "export");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn named_pipes_are_written_whatever_the_policy() {
+        let dir = work_dir("fifo");
+        let fifo = path_in(&dir, "out.fifo");
+        let c_path = std::ffi::CString::new(fifo.as_str()).unwrap();
+        // SAFETY: `c_path` is NUL-terminated
+        assert_eq!(unsafe { libc::mkfifo(c_path.as_ptr(), 0o600) }, 0);
+
+        for policy in [OverwritePolicy::Never, OverwritePolicy::Rename] {
+            assert_eq!(apply_overwrite_policy(&fifo, policy, &[]), Ok(fifo.clone()));
+            assert_eq!(overwrite_flag(policy, &fifo), "-y");
+        }
+        assert_eq!(overwrite_flag(OverwritePolicy::Never, "pipe:1"), "-y");
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
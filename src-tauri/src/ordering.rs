@@ -0,0 +1,151 @@
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::fs;
+use std::iter::Peekable;
+use std::path::Path;
+use std::str::Chars;
+
+use crate::ffmpeg::FFmpegProcessor;
+use crate::models::{Project, VideoClip};
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OrderCriterion {
+    CreationTime,
+    Filename,
+}
+
+// Days since 1970-01-01 for a proleptic Gregorian date
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let y = if month <= 2 { year - 1 } else { year };
+    let era = if y >= 0 { y } else { y - 399 } / 400;
+    let yoe = y - era * 400;
+    let mp = (month + 9) % 12;
+    let doy = (153 * mp + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146097 + doe - 719468
+}
+
+// Parses the "YYYY-MM-DDTHH:MM:SS[.ffffff]Z" form ffprobe reports in creation_time
+fn parse_creation_time(value: &str) -> Option<i64> {
+    let (date, time) = value.trim().split_once(['T', ' '])?;
+    let mut d = date.splitn(3, '-').map(|p| p.parse::<i64>().ok());
+    let (year, month, day) = (d.next()??, d.next()??, d.next()??);
+
+    let time = time.trim_end_matches('Z');
+    let mut t = time.splitn(3, ':');
+    let hours: i64 = t.next()?.parse().ok()?;
+    let minutes: i64 = t.next()?.parse().ok()?;
+    let seconds: i64 = t.next().unwrap_or("0").split('.').next()?.parse().ok()?;
+
+    Some(days_from_civil(year, month, day) * 86400 + hours * 3600 + minutes * 60 + seconds)
+}
+
+fn modified_time(path: &str) -> Option<i64> {
+    fs::metadata(path)
+        .and_then(|m| m.modified())
+        .ok()
+        .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+        .map(|d| d.as_secs() as i64)
+}
+
+fn file_name(clip: &VideoClip) -> String {
+    Path::new(&clip.path)
+        .file_name()
+        .map(|n| n.to_string_lossy().to_lowercase())
+        .unwrap_or_else(|| clip.path.to_lowercase())
+}
+
+fn take_number(it: &mut Peekable<Chars>) -> String {
+    let mut digits = String::new();
+    while let Some(c) = it.peek().copied().filter(|c| c.is_ascii_digit()) {
+        digits.push(c);
+        it.next();
+    }
+    digits
+}
+
+// "clip2" < "clip10": digit runs compare by value, everything else by text
+pub fn natural_cmp(a: &str, b: &str) -> Ordering {
+    let (mut a, mut b) = (a.chars().peekable(), b.chars().peekable());
+    loop {
+        match (a.peek().copied(), b.peek().copied()) {
+            (None, None) => return Ordering::Equal,
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(x), Some(y)) if x.is_ascii_digit() && y.is_ascii_digit() => {
+                let (na, nb) = (take_number(&mut a), take_number(&mut b));
+                let (ta, tb) = (na.trim_start_matches('0'), nb.trim_start_matches('0'));
+                let ord = ta.len().cmp(&tb.len()).then_with(|| ta.cmp(tb));
+                if ord != Ordering::Equal {
+                    return ord;
+                }
+            }
+            (Some(x), Some(y)) => {
+                if x != y {
+                    return x.cmp(&y);
+                }
+                a.next();
+                b.next();
+            }
+        }
+    }
+}
+
+// Tauri commands
+
+#[tauri::command]
+pub async fn auto_order_by(mut project: Project, criterion: OrderCriterion) -> Result<Project, String> {
+    match criterion {
+        OrderCriterion::Filename => {
+            project.videos.sort_by(|a, b| natural_cmp(&file_name(a), &file_name(b)));
+        }
+        OrderCriterion::CreationTime => {
+            // Files without creation metadata fall back to mtime, then to the filename
+            let mut keyed: Vec<(Option<i64>, String, VideoClip)> = project
+                .videos
+                .drain(..)
+                .map(|clip| {
+                    let time = FFmpegProcessor::probe_creation_time(&clip.path)
+                        .and_then(|t| parse_creation_time(&t))
+                        .or_else(|| modified_time(&clip.path));
+                    (time, file_name(&clip), clip)
+                })
+                .collect();
+            keyed.sort_by(|a, b| match (a.0, b.0) {
+                (Some(x), Some(y)) => x.cmp(&y).then_with(|| natural_cmp(&a.1, &b.1)),
+                (Some(_), None) => Ordering::Less,
+                (None, Some(_)) => Ordering::Greater,
+                (None, None) => natural_cmp(&a.1, &b.1),
+            });
+            project.videos = keyed.into_iter().map(|(_, _, clip)| clip).collect();
+        }
+    }
+    Ok(project)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn numbers_in_names_compare_by_value() {
+        assert_eq!(natural_cmp("clip2.mp4", "clip10.mp4"), Ordering::Less);
+        assert_eq!(natural_cmp("clip010", "clip10"), Ordering::Equal);
+        assert_eq!(natural_cmp("a", "a1"), Ordering::Less);
+        assert_eq!(natural_cmp("b1", "a2"), Ordering::Greater);
+
+        let mut names = vec!["img12", "img2", "img1b", "img1a"];
+        names.sort_by(|a, b| natural_cmp(a, b));
+        assert_eq!(names, ["img1a", "img1b", "img2", "img12"]);
+    }
+
+    #[test]
+    fn creation_times_are_parsed_as_utc() {
+        assert_eq!(parse_creation_time("1970-01-01T00:00:00.000000Z"), Some(0));
+        assert_eq!(parse_creation_time("2024-03-01 12:30:15"), Some(1709296215));
+        assert_eq!(parse_creation_time("2024-03-01T12:30Z"), Some(1709296200));
+        assert_eq!(parse_creation_time("garbage"), None);
+        assert_eq!(days_from_civil(2000, 3, 1), 11017);
+    }
+}
@@ -0,0 +1,169 @@
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::process::{Child, Command, ExitStatus};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+// How long ffmpeg gets to write its trailer after `q` before being killed
+const GRACEFUL_STOP_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CancelMode {
+    // Kill ffmpeg and delete the unfinished output
+    #[default]
+    Discard,
+    // Ask ffmpeg to stop (`q` on stdin) and keep the truncated file
+    KeepPartial,
+}
+
+// Holds the ffmpeg child of a long-running job (export, preview) so another
+// command can cancel it while the job waits for completion.
+// The child must be spawned with a piped stdin for `CancelMode::KeepPartial`.
+pub struct ChildSlot {
+    child: Mutex<Option<Child>>,
+    cancelled: AtomicBool,
+    keep_partial: AtomicBool,
+    stop_deadline: Mutex<Option<Instant>>,
+}
+
+impl ChildSlot {
+    pub fn new() -> Self {
+        Self {
+            child: Mutex::new(None),
+            cancelled: AtomicBool::new(false),
+            keep_partial: AtomicBool::new(false),
+            stop_deadline: Mutex::new(None),
+        }
+    }
+
+    // Must be called before spawning so a cancel issued during setup isn't lost
+    pub fn reset(&self) {
+        self.cancelled.store(false, Ordering::SeqCst);
+        self.keep_partial.store(false, Ordering::SeqCst);
+        *self.stop_deadline.lock().unwrap() = None;
+    }
+
+    // A cancel issued before the child existed always kills: there is nothing to keep yet
+    pub fn start(&self, child: Child) {
+        *self.child.lock().unwrap() = Some(child);
+        if self.is_cancelled() {
+            self.keep_partial.store(false, Ordering::SeqCst);
+            self.kill();
+        }
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst)
+    }
+
+    // True when the job was cancelled with `KeepPartial` and ffmpeg is (or was) finalizing
+    pub fn keeps_partial(&self) -> bool {
+        self.keep_partial.load(Ordering::SeqCst)
+    }
+
+    fn kill(&self) {
+        if let Some(child) = self.child.lock().unwrap().as_mut() {
+            let _ = child.kill();
+        }
+    }
+
+    pub fn cancel(&self) {
+        self.cancel_with(CancelMode::Discard);
+    }
+
+    pub fn cancel_with(&self, mode: CancelMode) {
+        self.cancelled.store(true, Ordering::SeqCst);
+        if mode == CancelMode::KeepPartial && self.request_stop() {
+            self.keep_partial.store(true, Ordering::SeqCst);
+            *self.stop_deadline.lock().unwrap() = Some(Instant::now() + GRACEFUL_STOP_TIMEOUT);
+        } else {
+            self.keep_partial.store(false, Ordering::SeqCst);
+            self.kill();
+        }
+    }
+
+    // Sends ffmpeg's interactive quit key; false if there is no child or no stdin
+    fn request_stop(&self) -> bool {
+        let mut guard = self.child.lock().unwrap();
+        let Some(stdin) = guard.as_mut().and_then(|c| c.stdin.as_mut()) else {
+            return false;
+        };
+        stdin.write_all(b"q").and_then(|_| stdin.flush()).is_ok()
+    }
+
+    // Returns None when the job was cancelled, including a graceful stop
+    pub fn wait(&self) -> Result<Option<ExitStatus>, String> {
+        loop {
+            let deadline = *self.stop_deadline.lock().unwrap();
+            if deadline.is_some_and(|d| Instant::now() >= d) {
+                self.kill();
+            }
+            {
+                let mut guard = self.child.lock().unwrap();
+                let Some(child) = guard.as_mut() else {
+                    return Err("Aucun processus en cours".to_string());
+                };
+                if let Some(status) = child.try_wait().map_err(|e| e.to_string())? {
+                    guard.take();
+                    return Ok(if self.is_cancelled() { None } else { Some(status) });
+                }
+            }
+            std::thread::sleep(Duration::from_millis(50));
+        }
+    }
+}
+
+pub const EXPORT_IN_PROGRESS: &str = "Un export est deja en cours";
+
+// Held for the whole of an export-like job; a second one is refused meanwhile
+pub struct ExportGuard<'a>(&'a AtomicBool);
+
+impl<'a> ExportGuard<'a> {
+    pub fn acquire(is_exporting: &'a AtomicBool) -> Result<Self, String> {
+        is_exporting
+            .compare_exchange(false, true, Ordering::SeqCst, Ordering::SeqCst)
+            .map_err(|_| EXPORT_IN_PROGRESS.to_string())?;
+        Ok(Self(is_exporting))
+    }
+}
+
+impl Drop for ExportGuard<'_> {
+    fn drop(&mut self) {
+        self.0.store(false, Ordering::SeqCst);
+    }
+}
+
+// Builds the process for an argv-style command. Low priority is nice 10 on Unix,
+// the below-normal class on Windows.
+pub fn new_command(cmd: &[String], low_priority: bool) -> Command {
+    let mut process = Command::new(&cmd[0]);
+    process.args(&cmd[1..]);
+    if low_priority {
+        lower_priority(&mut process);
+    }
+    process
+}
+
+#[cfg(unix)]
+fn lower_priority(process: &mut Command) {
+    use std::os::unix::process::CommandExt;
+    // SAFETY: nice() is async-signal-safe and only touches the child's own priority
+    unsafe {
+        process.pre_exec(|| {
+            libc::nice(10);
+            Ok(())
+        });
+    }
+}
+
+#[cfg(windows)]
+fn lower_priority(process: &mut Command) {
+    use std::os::windows::process::CommandExt;
+    const BELOW_NORMAL_PRIORITY_CLASS: u32 = 0x0000_4000;
+    process.creation_flags(BELOW_NORMAL_PRIORITY_CLASS);
+}
+
+#[cfg(not(any(unix, windows)))]
+fn lower_priority(_process: &mut Command) {}
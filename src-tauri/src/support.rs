@@ -0,0 +1,262 @@
+// ffmpeg run log and support bundle. With `Config::debug_logging` on, every
+// export and preview run is appended to a log in the config dir: the command,
+// how long it took, how it ended and what ffmpeg printed on stderr.
+
+use std::fs::{self, OpenOptions};
+use std::io::{Read, Write};
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tauri::State;
+
+use crate::models::{get_config, get_temp_dir};
+use crate::AppState;
+
+// Mirrors `Config::debug_logging`
+static LOGGING: AtomicBool = AtomicBool::new(false);
+
+// Mirrors `Config::keep_temp_on_error`
+static KEEP_FAILED: AtomicBool = AtomicBool::new(false);
+// Files left by the last failed run, until an export result picks them up
+static KEPT_FILES: Mutex<Vec<String>> = Mutex::new(Vec::new());
+// Folder of the last failed run, added to the support bundle
+static LAST_FAILED_DIR: Mutex<Option<PathBuf>> = Mutex::new(None);
+
+// The log is moved to `.1` past this size, replacing the previous one
+const MAX_LOG_BYTES: u64 = 5 * 1024 * 1024;
+const LOG_FILE: &str = "ffmpeg.log";
+
+pub fn set_logging(enabled: bool) {
+    LOGGING.store(enabled, Ordering::SeqCst);
+}
+
+pub fn logging_enabled() -> bool {
+    LOGGING.load(Ordering::SeqCst)
+}
+
+pub fn set_keep_failed(enabled: bool) {
+    KEEP_FAILED.store(enabled, Ordering::SeqCst);
+}
+
+pub fn keeps_failed() -> bool {
+    KEEP_FAILED.load(Ordering::SeqCst)
+}
+
+fn log_dir() -> PathBuf {
+    dirs::config_dir()
+        .or_else(dirs::home_dir)
+        .unwrap_or_default()
+        .join("video_musique")
+        .join("logs")
+}
+
+fn unix_seconds() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs()
+}
+
+// Shell-quoted, ready to paste into a terminal
+pub fn format_command(process: &Command) -> String {
+    std::iter::once(process.get_program())
+        .chain(process.get_args())
+        .map(|arg| {
+            let arg = arg.to_string_lossy();
+            if arg.is_empty() || arg.contains(|c: char| c.is_whitespace() || "'\"[];|&$".contains(c)) {
+                format!("'{}'", arg.replace('\'', "'\\''"))
+            } else {
+                arg.to_string()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+// Best effort, write errors are ignored
+pub fn log_run(command: &str, elapsed: Duration, outcome: &str, stderr: &str) {
+    if !logging_enabled() {
+        return;
+    }
+    let dir = log_dir();
+    if fs::create_dir_all(&dir).is_err() {
+        return;
+    }
+    let path = dir.join(LOG_FILE);
+    if fs::metadata(&path).is_ok_and(|m| m.len() > MAX_LOG_BYTES) {
+        let _ = fs::rename(&path, dir.join(format!("{}.1", LOG_FILE)));
+    }
+    let Ok(mut file) = OpenOptions::new().create(true).append(true).open(&path) else {
+        return;
+    };
+    let _ = writeln!(
+        file,
+        "=== {} | {:.1} s | {}\n$ {}\n{}\n",
+        unix_seconds(),
+        elapsed.as_secs_f64(),
+        outcome,
+        command,
+        stderr.trim_end()
+    );
+}
+
+// Writes the command and stderr of a failed run to its own folder in the temp dir
+pub fn keep_failed_run(command: &str, stderr: &str) {
+    let dir = get_temp_dir().join(format!("video_musique_failed_{}_{}", unix_seconds(), std::process::id()));
+    if fs::create_dir_all(&dir).is_err() {
+        return;
+    }
+    let mut kept = Vec::new();
+    for (name, text) in [("command.txt", command), ("stderr.txt", stderr)] {
+        let path = dir.join(name);
+        if fs::write(&path, text).is_ok() {
+            kept.push(path.to_string_lossy().to_string());
+        }
+    }
+    *KEPT_FILES.lock().unwrap() = kept;
+    *LAST_FAILED_DIR.lock().unwrap() = Some(dir);
+}
+
+pub fn take_kept_files() -> Vec<String> {
+    std::mem::take(&mut *KEPT_FILES.lock().unwrap())
+}
+
+// Drains a piped stderr on its own thread while ffmpeg runs
+pub fn capture_stderr(stderr: Option<std::process::ChildStderr>) -> std::thread::JoinHandle<String> {
+    std::thread::spawn(move || {
+        let mut text = String::new();
+        if let Some(mut stderr) = stderr {
+            let _ = stderr.read_to_string(&mut text);
+        }
+        text
+    })
+}
+
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = 0xFFFF_FFFFu32;
+    for &byte in data {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 { (crc >> 1) ^ 0xEDB8_8320 } else { crc >> 1 };
+        }
+    }
+    !crc
+}
+
+// Minimal zip writer: stored (uncompressed) entries, which every unzip tool reads.
+// Dates are left at 1980-01-01, the earliest a zip can hold.
+fn write_zip(entries: &[(String, Vec<u8>)]) -> Vec<u8> {
+    const DOS_DATE: u16 = (1 << 5) | 1;
+    let mut out = Vec::new();
+    let mut central = Vec::new();
+
+    for (name, data) in entries {
+        let offset = out.len() as u32;
+        let (crc, size, name_len) = (crc32(data), data.len() as u32, name.len() as u16);
+
+        out.extend(0x0403_4b50u32.to_le_bytes());
+        for field in [20u16, 0, 0, 0, DOS_DATE] {
+            out.extend(field.to_le_bytes());
+        }
+        for field in [crc, size, size] {
+            out.extend(field.to_le_bytes());
+        }
+        out.extend(name_len.to_le_bytes());
+        out.extend(0u16.to_le_bytes());
+        out.extend(name.as_bytes());
+        out.extend(data);
+
+        central.extend(0x0201_4b50u32.to_le_bytes());
+        for field in [20u16, 20, 0, 0, 0, DOS_DATE] {
+            central.extend(field.to_le_bytes());
+        }
+        for field in [crc, size, size] {
+            central.extend(field.to_le_bytes());
+        }
+        for field in [name_len, 0, 0, 0, 0] {
+            central.extend(field.to_le_bytes());
+        }
+        central.extend(0u32.to_le_bytes());
+        central.extend(offset.to_le_bytes());
+        central.extend(name.as_bytes());
+    }
+
+    let (central_offset, central_size, count) = (out.len() as u32, central.len() as u32, entries.len() as u16);
+    out.extend(central);
+    out.extend(0x0605_4b50u32.to_le_bytes());
+    for field in [0u16, 0, count, count] {
+        out.extend(field.to_le_bytes());
+    }
+    out.extend(central_size.to_le_bytes());
+    out.extend(central_offset.to_le_bytes());
+    out.extend(0u16.to_le_bytes());
+    out
+}
+
+fn tool_version(tool: &str) -> String {
+    Command::new(tool)
+        .arg("-version")
+        .stdin(Stdio::null())
+        .output()
+        .map(|o| String::from_utf8_lossy(&o.stdout).to_string())
+        .unwrap_or_else(|e| format!("{} introuvable: {}", tool, e))
+}
+
+// Tauri commands
+
+// Zips the ffmpeg log, the config, the recent probes, the last failed run kept
+// by `keep_temp_on_error` and the ffmpeg/ffprobe versions into the temp dir and
+// returns the file's path
+#[tauri::command]
+pub fn collect_support_bundle(state: State<'_, AppState>) -> Result<String, String> {
+    let config = serde_json::to_string_pretty(&get_config()).map_err(|e| e.to_string())?;
+    let probes = crate::ffmpeg::get_last_probe_log(state);
+    let probes = serde_json::to_string_pretty(&probes).map_err(|e| e.to_string())?;
+
+    let mut entries = vec![
+        ("config.json".to_string(), config.into_bytes()),
+        ("probe_log.json".to_string(), probes.into_bytes()),
+        ("ffmpeg_version.txt".to_string(), tool_version("ffmpeg").into_bytes()),
+        ("ffprobe_version.txt".to_string(), tool_version("ffprobe").into_bytes()),
+    ];
+    for name in [format!("{}.1", LOG_FILE), LOG_FILE.to_string()] {
+        if let Ok(log) = fs::read(log_dir().join(&name)) {
+            entries.push((name, log));
+        }
+    }
+    if let Some(dir) = LAST_FAILED_DIR.lock().unwrap().clone() {
+        for name in ["command.txt", "stderr.txt"] {
+            if let Ok(text) = fs::read(dir.join(name)) {
+                entries.push((format!("failed_run/{}", name), text));
+            }
+        }
+    }
+
+    let bundle = get_temp_dir().join(format!("video_musique_support_{}.zip", unix_seconds()));
+    fs::write(&bundle, write_zip(&entries)).map_err(|e| format!("Impossible d'ecrire le rapport: {}", e))?;
+    Ok(bundle.to_string_lossy().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn logged_commands_can_be_pasted_into_a_shell() {
+        let mut process = Command::new("ffmpeg");
+        process.args(["-i", "my clip.mp4", "-filter_complex", "[0:v]null[v]", "it's.mp4", ""]);
+        assert_eq!(format_command(&process), r#"ffmpeg -i 'my clip.mp4' -filter_complex '[0:v]null[v]' 'it'\''s.mp4' ''"#);
+    }
+
+    #[test]
+    fn bundles_are_stored_zips() {
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+        let zip = write_zip(&[("a.txt".to_string(), b"hello".to_vec()), ("b.log".to_string(), Vec::new())]);
+        assert!(zip.starts_with(&0x0403_4b50u32.to_le_bytes()));
+        // The end record points back to a central directory holding both entries
+        let end = &zip[zip.len() - 22..];
+        assert_eq!(end[..4], 0x0605_4b50u32.to_le_bytes());
+        assert_eq!(u16::from_le_bytes([end[10], end[11]]), 2);
+        let central_offset = u32::from_le_bytes([end[16], end[17], end[18], end[19]]) as usize;
+        assert_eq!(zip[central_offset..central_offset + 4], 0x0201_4b50u32.to_le_bytes());
+    }
+}
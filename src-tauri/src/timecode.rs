@@ -0,0 +1,103 @@
+// HH:MM:SS:FF timecodes for trims and markers. Frames are counted at the
+// clip's real rate and labelled at the nominal one (29.97 counts as 30), i.e.
+// non-drop-frame, like the timecode burned in by the exporter.
+
+use crate::ffmpeg::FFmpegProcessor;
+
+// What the exporter assumes when a file's rate can't be probed
+const DEFAULT_FPS: f64 = 25.0;
+
+fn nominal_fps(fps: f64) -> u64 {
+    (fps.round() as u64).max(1)
+}
+
+fn valid_fps(fps: f64) -> f64 {
+    if fps.is_finite() && fps > 0.0 { fps } else { DEFAULT_FPS }
+}
+
+// Rounded to the nearest frame; negative times show as zero
+pub fn seconds_to_timecode(seconds: f64, fps: f64) -> String {
+    let fps = valid_fps(fps);
+    let nominal = nominal_fps(fps);
+    let frames = (seconds.max(0.0) * fps).round() as u64;
+    let (total_seconds, frame) = (frames / nominal, frames % nominal);
+    format!(
+        "{:02}:{:02}:{:02}:{:02}",
+        total_seconds / 3600,
+        total_seconds / 60 % 60,
+        total_seconds % 60,
+        frame
+    )
+}
+
+// "HH:MM:SS:FF", or shorter from the right ("SS:FF", "MM:SS:FF"). `;` is taken
+// as a separator too, as drop-frame timecodes are written with one.
+pub fn timecode_to_seconds(timecode: &str, fps: f64) -> Result<f64, String> {
+    let fps = valid_fps(fps);
+    let nominal = nominal_fps(fps);
+    let invalid = || format!("Timecode invalide: {}", timecode);
+
+    let fields: Vec<u64> = timecode
+        .trim()
+        .split([':', ';'])
+        .map(|f| f.trim().parse::<u64>())
+        .collect::<Result<_, _>>()
+        .map_err(|_| invalid())?;
+    if !(2..=4).contains(&fields.len()) {
+        return Err(invalid());
+    }
+
+    let (frame, clock) = fields.split_last().ok_or_else(invalid)?;
+    if *frame >= nominal || clock.iter().skip(1).any(|v| *v >= 60) {
+        return Err(invalid());
+    }
+    let seconds = clock.iter().fold(0, |total, v| total * 60 + v);
+    Ok((seconds * nominal + frame) as f64 / fps)
+}
+
+fn file_fps(path: &str) -> f64 {
+    FFmpegProcessor::probe_video_properties(path).map(|p| p.fps).unwrap_or(DEFAULT_FPS)
+}
+
+// Tauri commands
+
+// The rate is probed from `path` unless `fps` is given
+#[tauri::command]
+pub fn format_timecode(seconds: f64, path: Option<String>, fps: Option<f64>) -> String {
+    let fps = fps.unwrap_or_else(|| path.as_deref().map(file_fps).unwrap_or(DEFAULT_FPS));
+    seconds_to_timecode(seconds, fps)
+}
+
+#[tauri::command]
+pub fn parse_timecode(timecode: String, path: Option<String>, fps: Option<f64>) -> Result<f64, String> {
+    let fps = fps.unwrap_or_else(|| path.as_deref().map(file_fps).unwrap_or(DEFAULT_FPS));
+    timecode_to_seconds(&timecode, fps)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn timecodes_round_trip_on_frames() {
+        assert_eq!(seconds_to_timecode(3725.48, 25.0), "01:02:05:12");
+        assert_eq!(timecode_to_seconds("01:02:05:12", 25.0), Ok(3725.48));
+        assert_eq!(timecode_to_seconds("05:12", 25.0), Ok(5.48));
+        // 29.97 is labelled as 30 frames a second
+        let seconds = timecode_to_seconds("00:00:10;15", 30000.0 / 1001.0).unwrap();
+        assert_eq!(seconds_to_timecode(seconds, 30000.0 / 1001.0), "00:00:10:15");
+        assert!(timecode_to_seconds("00:00:10:25", 25.0).is_err());
+        assert!(timecode_to_seconds("00:61:00:00", 25.0).is_err());
+        assert!(timecode_to_seconds("abc", 25.0).is_err());
+    }
+
+    #[test]
+    fn out_of_range_inputs_fall_back() {
+        assert_eq!(seconds_to_timecode(-2.0, 25.0), "00:00:00:00");
+        // An unknown rate is taken as the exporter's default
+        assert_eq!(seconds_to_timecode(1.5, 0.0), seconds_to_timecode(1.5, DEFAULT_FPS));
+        assert_eq!(seconds_to_timecode(0.999, 25.0), "00:00:01:00");
+        assert!(timecode_to_seconds("12", 25.0).is_err());
+        assert!(timecode_to_seconds("1:2:3:4:5", 25.0).is_err());
+    }
+}
@@ -0,0 +1,568 @@
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::DefaultHasher;
+use std::fs::File;
+use std::hash::{Hash, Hasher};
+use std::io::Read;
+use std::path::Path;
+
+use crate::ffmpeg::{audio_crossfade_durations, ClipProbes};
+use crate::models::{get_config, Project, VideoClip, H264_PROFILES, NVENC_TUNES, SUPPORTED_AUDIO_CODECS, SUPPORTED_CONTAINERS, X264_TUNES};
+
+// Durations in seconds, after trims and crossfades
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProjectStats {
+    pub total_video_duration: f64,
+    pub total_music_duration: f64,
+    pub clip_count: usize,
+    pub track_count: usize,
+    pub estimated_output_duration: f64,
+    // Clips whose size or frame rate differs from the first one
+    pub mismatched_clips: Vec<String>,
+}
+
+// Every enabled clip and active track, each file probed once
+fn project_probes(project: &Project) -> ClipProbes {
+    let clips = project.get_enabled_clips().1;
+    let tracks = project.get_active_tracks();
+    ClipProbes::of(clips.iter().map(|c| c.path.as_str()).chain(tracks.iter().map(|t| t.path.as_str())))
+}
+
+fn find_mismatched_clips(project: &Project, probes: &ClipProbes) -> Vec<String> {
+    let auto_rotate = project.settings.auto_rotate;
+    let clips = project.get_enabled_clips().1;
+    let mut props = clips
+        .iter()
+        .filter_map(|clip| probes.properties(&clip.path).map(|p| (clip, p)));
+    let Some((_, first)) = props.next() else {
+        return vec![];
+    };
+
+    props
+        .filter(|(_, p)| p.frame_size(auto_rotate) != first.frame_size(auto_rotate) || (p.fps - first.fps).abs() > 0.01)
+        .map(|(clip, _)| clip.name.clone())
+        .collect()
+}
+
+fn check_audio_crossfade(project: &Project, warnings: &mut Vec<String>) {
+    let tracks: Vec<_> = project.get_active_tracks().into_iter().cloned().collect();
+    let configured = project.settings.audio_crossfade;
+
+    for (j, duration) in audio_crossfade_durations(&tracks, configured).iter().enumerate() {
+        if *duration < configured {
+            warnings.push(format!(
+                "Le cross-fade audio ({}s) est trop long entre \"{}\" et \"{}\": il sera reduit a {:.1}s",
+                configured,
+                tracks[j].name,
+                tracks[j + 1].name,
+                duration
+            ));
+        }
+    }
+}
+
+fn check_interpolation(project: &Project, warnings: &mut Vec<String>) {
+    if project.settings.interpolation_mode.is_some() && project.settings.get_output_fps().is_some() {
+        warnings.push(
+            "L'interpolation de mouvement (minterpolate) est tres couteuse en CPU et ralentit fortement l'export".to_string(),
+        );
+    }
+}
+
+fn check_audio_codec(project: &Project, warnings: &mut Vec<String>) {
+    if let Some(ref codec) = project.settings.audio_codec {
+        if codec != "libvorbis" && !SUPPORTED_AUDIO_CODECS.contains(&codec.as_str()) {
+            warnings.push(format!(
+                "Codec audio non supporte \"{}\" (choix: {}): aac sera utilise",
+                codec,
+                SUPPORTED_AUDIO_CODECS.join(", ")
+            ));
+        }
+    }
+}
+
+fn check_container(project: &Project, warnings: &mut Vec<String>) {
+    let Some(ref container) = project.settings.container else {
+        return;
+    };
+    if !SUPPORTED_CONTAINERS.contains(&container.as_str()) {
+        warnings.push(format!(
+            "Conteneur non supporte \"{}\" (choix: {}): l'extension du fichier sera utilisee",
+            container,
+            SUPPORTED_CONTAINERS.join(", ")
+        ));
+        return;
+    }
+
+    let audio_codec = project.settings.audio_codec.as_deref();
+    match container.as_str() {
+        "webm" if audio_codec.is_some_and(|c| c != "libopus" && c != "libvorbis") => warnings.push(format!(
+            "Le conteneur webm n'accepte que Opus ou Vorbis: {} sera remplace par libopus",
+            audio_codec.unwrap_or_default()
+        )),
+        "mpegts" if project.settings.get_audio_codec() == "flac" => {
+            warnings.push("Le conteneur mpegts ne peut pas contenir de FLAC".to_string())
+        }
+        _ => {}
+    }
+}
+
+// Largest frame, in 16x16 macroblocks, each H.264 level allows (Table A-1)
+fn h264_level_max_macroblocks(level: i32) -> i32 {
+    match level {
+        10 => 99,
+        11..=20 => 396,
+        21 => 792,
+        22 | 30 => 1620,
+        31 => 3600,
+        32 => 5120,
+        40 | 41 => 8192,
+        42 => 8704,
+        50 => 22080,
+        _ => 36864,
+    }
+}
+
+fn check_h264_profile(project: &Project, probes: &ClipProbes, warnings: &mut Vec<String>) {
+    let settings = &project.settings;
+    if let Some(ref profile) = settings.h264_profile {
+        if settings.get_h264_profile().is_none() {
+            warnings.push(format!(
+                "Profil H.264 non supporte \"{}\" (choix: {}): il sera ignore",
+                profile,
+                H264_PROFILES.join(", ")
+            ));
+        }
+    }
+    if let Some(ref level) = settings.h264_level {
+        if settings.get_h264_level().is_none() {
+            warnings.push(format!("Niveau H.264 non supporte \"{}\" (de 1.0 a 5.2): il sera ignore", level));
+        }
+    }
+    if settings.get_h264_profile().is_none() && settings.get_h264_level().is_none() {
+        return;
+    }
+    if settings.get_container() == Some("webm") {
+        warnings.push("Le conteneur webm est encode en VP9: le profil et le niveau H.264 seront ignores".to_string());
+        return;
+    }
+
+    // Most hardware encoders refuse a frame too large for the level, x264 only warns
+    let Some(level) = settings.get_h264_level() else {
+        return;
+    };
+    let frame = settings.get_output_resolution().or_else(|| {
+        project
+            .get_enabled_clips()
+            .1
+            .first()
+            .and_then(|clip| probes.properties(&clip.path))
+            .map(|p| p.frame_size(settings.auto_rotate))
+    });
+    if let Some((w, h)) = frame {
+        let macroblocks = ((w + 15) / 16) * ((h + 15) / 16);
+        if macroblocks > h264_level_max_macroblocks(level) {
+            warnings.push(format!(
+                "Le niveau H.264 {}.{} est trop bas pour une image de {}x{}",
+                level / 10,
+                level % 10,
+                w,
+                h
+            ));
+        }
+    }
+}
+
+// The encoder is only settled at export time, so a tune one family lacks is
+// reported when the configured GPU choice could end up with that family
+fn check_tune(project: &Project, warnings: &mut Vec<String>) {
+    let Some(tune) = project.settings.tune.as_deref().map(str::trim) else {
+        return;
+    };
+    let use_gpu = get_config().use_gpu;
+    if project.settings.get_container() == Some("webm") {
+        warnings.push(format!("Le conteneur webm est encode en VP9: le reglage \"{}\" sera ignore", tune));
+    } else if !X264_TUNES.contains(&tune) && !NVENC_TUNES.contains(&tune) {
+        warnings.push(format!(
+            "Reglage d'encodeur non supporte \"{}\" (libx264: {}; NVENC: {}): il sera ignore",
+            tune,
+            X264_TUNES.join(", "),
+            NVENC_TUNES.join(", ")
+        ));
+    } else if !X264_TUNES.contains(&tune) {
+        warnings.push(format!("Le reglage \"{}\" ne s'applique qu'a NVENC: il sera ignore par les autres encodeurs", tune));
+    } else if use_gpu {
+        warnings.push(format!("Le reglage \"{}\" ne s'applique qu'a libx264: il sera ignore par les encodeurs GPU", tune));
+    }
+}
+
+fn check_channel_layouts(project: &Project, probes: &ClipProbes, warnings: &mut Vec<String>) {
+    let settings = &project.settings;
+    if settings.force_stereo || settings.mute_all {
+        return;
+    }
+    let enabled = project.get_enabled_clips().1;
+    let clips = enabled
+        .iter()
+        .filter(|c| settings.include_video_audio && !c.mute_audio)
+        .map(|c| &c.path);
+    let tracks = project.get_active_tracks().into_iter().filter(|_| settings.include_music).map(|t| &t.path);
+    let mut counts: Vec<i64> = clips.chain(tracks).filter_map(|p| probes.audio_channels(p)).collect();
+    counts.sort_unstable();
+    counts.dedup();
+
+    if counts.len() > 1 {
+        let list: Vec<String> = counts.iter().map(|c| c.to_string()).collect();
+        warnings.push(format!(
+            "Les sources audio ont des nombres de canaux differents ({}): activez force_stereo pour tout convertir en stereo",
+            list.join(", ")
+        ));
+    }
+}
+
+// Most channels each encoder takes; libmp3lame is stereo at most
+fn max_audio_channels(codec: &str) -> usize {
+    match codec {
+        "libmp3lame" => 2,
+        "ac3" => 6,
+        _ => 8,
+    }
+}
+
+fn check_audio_merge(project: &Project, warnings: &mut Vec<String>) {
+    let settings = &project.settings;
+    if !settings.audio_merge || !settings.include_music || settings.mute_all {
+        return;
+    }
+    let channels = project.get_active_tracks().len();
+    // webm always gets an Ogg codec, libopus unless libvorbis is asked for
+    let codec = match settings.get_container() {
+        Some("webm") => settings.audio_codec.as_deref().filter(|c| *c == "libvorbis").unwrap_or("libopus"),
+        _ => settings.get_audio_codec(),
+    };
+    if channels > max_audio_channels(codec) {
+        warnings.push(format!(
+            "La fusion des musiques donne {} canaux, mais {} n'en accepte que {}",
+            channels,
+            codec,
+            max_audio_channels(codec)
+        ));
+    }
+}
+
+fn check_poster(project: &Project, warnings: &mut Vec<String>) {
+    if let Some(ref poster) = project.settings.poster_image {
+        if !Path::new(poster).exists() {
+            warnings.push(format!("Image de couverture introuvable: {}", poster));
+        } else if matches!(project.settings.get_container(), Some("webm" | "mpegts")) {
+            warnings.push("Le conteneur choisi ne gere pas d'image de couverture: elle sera ignoree".to_string());
+        }
+    }
+}
+
+fn check_luts(project: &Project, warnings: &mut Vec<String>) {
+    let clips = project.get_enabled_clips().1;
+    let luts = clips.iter().filter_map(|v| v.lut.as_ref()).chain(project.settings.lut.as_ref());
+    for lut in luts {
+        if !Path::new(lut).exists() {
+            warnings.push(format!("LUT introuvable: {}", lut));
+        } else if !lut.to_lowercase().ends_with(".cube") {
+            warnings.push(format!("La LUT {} n'est pas un fichier .cube", lut));
+        }
+    }
+}
+
+fn check_export_range(project: &Project, warnings: &mut Vec<String>) {
+    if let Some((start, end)) = project.settings.export_range {
+        if project.get_export_range().is_none() {
+            warnings.push(format!(
+                "Plage d'export invalide ({:.1}s - {:.1}s) pour une timeline de {:.1}s: tout sera exporte",
+                start,
+                end,
+                project.get_video_duration()
+            ));
+        }
+    }
+}
+
+fn check_composition(project: &Project, warnings: &mut Vec<String>) {
+    if let Some(ref composition) = project.composition {
+        if composition.clips.len() != 2 {
+            warnings.push(format!(
+                "Une composition demande exactement 2 clips ({} fournis): la timeline sera exportee",
+                composition.clips.len()
+            ));
+        }
+    }
+}
+
+fn check_interlacing(project: &Project, probes: &ClipProbes, warnings: &mut Vec<String>) {
+    for clip in &project.get_enabled_clips().1 {
+        let interlaced = probes.properties(&clip.path).is_some_and(|p| p.is_interlaced());
+        if interlaced && !clip.deinterlace {
+            if project.settings.auto_deinterlace {
+                warnings.push(format!("\"{}\" est entrelace: il sera desentrelace", clip.name));
+            } else {
+                warnings.push(format!(
+                    "\"{}\" est entrelace et le desentrelacement automatique est desactive",
+                    clip.name
+                ));
+            }
+        }
+    }
+}
+
+fn check_anamorphic(project: &Project, probes: &ClipProbes, warnings: &mut Vec<String>) {
+    for clip in &project.get_enabled_clips().1 {
+        let Some(props) = probes.properties(&clip.path).filter(|p| p.is_anamorphic()) else {
+            continue;
+        };
+        let sar = props.sample_aspect_ratio.as_deref().unwrap_or_default();
+        if project.settings.preserve_sar {
+            warnings.push(format!("\"{}\" a des pixels non carres ({}): le ratio du premier clip sera conserve", clip.name, sar));
+        } else {
+            warnings.push(format!("\"{}\" a des pixels non carres ({}): il sera etire en pixels carres", clip.name, sar));
+        }
+    }
+}
+
+fn check_color_range(project: &Project, probes: &ClipProbes, warnings: &mut Vec<String>) {
+    let Some(target) = project.settings.color_range else {
+        return;
+    };
+    for clip in &project.get_enabled_clips().1 {
+        let Some(range) = probes.properties(&clip.path).and_then(|p| p.color_range) else {
+            continue;
+        };
+        if range != target {
+            warnings.push(format!(
+                "\"{}\" est en plage {}: elle sera convertie en plage {}",
+                clip.name,
+                range.label(),
+                target.label()
+            ));
+        }
+    }
+}
+
+// reverse / areverse keep every frame of the clip in memory before output
+const REVERSE_WARN_SECONDS: f64 = 30.0;
+
+fn check_reverse(project: &Project, warnings: &mut Vec<String>) {
+    for clip in project.get_enabled_clips().1.iter().filter(|c| c.reverse) {
+        let duration = clip.get_effective_duration();
+        if duration > REVERSE_WARN_SECONDS {
+            warnings.push(format!(
+                "\"{}\" est lu a l'envers sur {:.0}s: tout le clip est charge en memoire, reduisez-le a quelques secondes",
+                clip.name, duration
+            ));
+        }
+    }
+}
+
+// Bytes hashed to recognize the same file copied under another name
+const FINGERPRINT_BYTES: u64 = 1024 * 1024;
+
+// File size plus a hash of the first megabyte
+fn content_fingerprint(path: &str) -> Option<(u64, u64)> {
+    let file = File::open(path).ok()?;
+    let size = file.metadata().ok()?.len();
+    let mut head = Vec::new();
+    file.take(FINGERPRINT_BYTES).read_to_end(&mut head).ok()?;
+    let mut hasher = DefaultHasher::new();
+    head.hash(&mut hasher);
+    Some((size, hasher.finish()))
+}
+
+fn is_same_clip(a: &VideoClip, b: &VideoClip) -> bool {
+    if a.start != b.start || a.end != b.end {
+        return false;
+    }
+    let canonical = |p: &str| std::fs::canonicalize(p).unwrap_or_else(|_| Path::new(p).to_path_buf());
+    if canonical(&a.path) == canonical(&b.path) {
+        return true;
+    }
+    content_fingerprint(&a.path).is_some_and(|f| Some(f) == content_fingerprint(&b.path))
+}
+
+// Index in `videos` of every clip that repeats the enabled clip played right
+// before it (same media and cut); disabled clips in between don't count
+pub fn find_duplicate_adjacent_clips(project: &Project) -> Vec<usize> {
+    let (indices, clips) = project.get_enabled_clips();
+    clips
+        .windows(2)
+        .zip(indices.iter().skip(1))
+        .filter(|(pair, _)| is_same_clip(pair[0], pair[1]))
+        .map(|(_, i)| *i)
+        .collect()
+}
+
+fn check_duplicates(project: &Project, warnings: &mut Vec<String>) {
+    for i in find_duplicate_adjacent_clips(project) {
+        warnings.push(format!(
+            "Le clip {} (\"{}\") est identique au precedent: il sera joue deux fois de suite",
+            i + 1,
+            project.videos[i].name
+        ));
+    }
+}
+
+// Tauri commands
+
+#[tauri::command]
+pub async fn validate_project(project: Project) -> Vec<String> {
+    let probes = project_probes(&project);
+    let mut warnings = Vec::new();
+    check_audio_crossfade(&project, &mut warnings);
+    check_interpolation(&project, &mut warnings);
+    check_audio_codec(&project, &mut warnings);
+    check_container(&project, &mut warnings);
+    check_h264_profile(&project, &probes, &mut warnings);
+    check_tune(&project, &mut warnings);
+    check_channel_layouts(&project, &probes, &mut warnings);
+    check_audio_merge(&project, &mut warnings);
+    check_poster(&project, &mut warnings);
+    check_luts(&project, &mut warnings);
+    check_interlacing(&project, &probes, &mut warnings);
+    check_anamorphic(&project, &probes, &mut warnings);
+    check_color_range(&project, &probes, &mut warnings);
+    check_reverse(&project, &mut warnings);
+    check_duplicates(&project, &mut warnings);
+    check_export_range(&project, &mut warnings);
+    check_composition(&project, &mut warnings);
+    warnings
+}
+
+#[tauri::command]
+pub async fn get_project_stats(project: Project) -> ProjectStats {
+    let settings = &project.settings;
+    let total_video_duration = project.get_video_duration();
+    let total_music_duration = project.get_mixed_music_duration();
+
+    // Music left uncut keeps going after the last frame when amix runs to the longest input
+    let music_extends = settings.include_music
+        && !settings.mute_all
+        && !settings.cut_music_at_end
+        && settings.get_amix_duration() == "longest";
+    let estimated_output_duration = match project.get_export_range() {
+        Some((start, end)) => end - start,
+        None if music_extends => total_video_duration.max(total_music_duration),
+        None => total_video_duration,
+    };
+
+    ProjectStats {
+        total_video_duration,
+        total_music_duration,
+        clip_count: project.videos.iter().filter(|v| v.enabled).count(),
+        track_count: project.get_active_tracks().len(),
+        estimated_output_duration,
+        mismatched_clips: find_mismatched_clips(&project, &project_probes(&project)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::{clip_at, track_at};
+    use crate::models::AudioTrack;
+
+    #[test]
+    fn duplicates_around_a_disabled_clip_are_adjacent() {
+        let clip = |name: &str| clip_at(&format!("{}.mp4", name), 5.0);
+        let project = Project {
+            videos: vec![clip("a"), VideoClip { enabled: false, ..clip("b") }, clip("a"), clip("a")],
+            ..Project::default()
+        };
+        assert_eq!(find_duplicate_adjacent_clips(&project), vec![2, 3]);
+        assert!(find_duplicate_adjacent_clips(&Project::default()).is_empty());
+    }
+
+    fn warnings_of(check: fn(&Project, &mut Vec<String>), project: &Project) -> Vec<String> {
+        let mut warnings = Vec::new();
+        check(project, &mut warnings);
+        warnings
+    }
+
+    fn probed_warnings_of(check: fn(&Project, &ClipProbes, &mut Vec<String>), project: &Project) -> Vec<String> {
+        let mut warnings = Vec::new();
+        check(project, &project_probes(project), &mut warnings);
+        warnings
+    }
+
+    #[test]
+    fn audio_crossfades_longer_than_a_track_are_reported() {
+        let track = |name: &str, duration: f64| AudioTrack { name: name.to_string(), ..track_at(&format!("{}.m4a", name), duration) };
+        let mut project = Project { audio_tracks: vec![track("a", 5.0), track("b", 20.0), track("c", 30.0)], ..Project::default() };
+        project.settings.audio_crossfade = 10.0;
+
+        let warnings = warnings_of(check_audio_crossfade, &project);
+        assert_eq!(warnings.len(), 1, "{:?}", warnings);
+        assert!(warnings[0].contains("\"a\" et \"b\""), "{:?}", warnings);
+        project.settings.audio_crossfade = 4.0;
+        assert!(warnings_of(check_audio_crossfade, &project).is_empty());
+    }
+
+    #[test]
+    fn unusable_containers_and_codecs_are_reported() {
+        let mut project = Project::default();
+        project.settings.container = Some("avi".to_string());
+        assert!(warnings_of(check_container, &project)[0].contains("non supporte"));
+
+        project.settings.container = Some("webm".to_string());
+        project.settings.audio_codec = Some("aac".to_string());
+        assert!(warnings_of(check_container, &project)[0].contains("libopus"));
+        project.settings.audio_codec = Some("libvorbis".to_string());
+        assert!(warnings_of(check_container, &project).is_empty());
+
+        project.settings.container = Some("mpegts".to_string());
+        project.settings.audio_codec = Some("flac".to_string());
+        assert_eq!(warnings_of(check_container, &project).len(), 1);
+        project.settings.audio_codec = Some("pcm".to_string());
+        assert_eq!(warnings_of(check_audio_codec, &project).len(), 1);
+    }
+
+    #[test]
+    fn h264_levels_too_low_for_the_frame_are_reported() {
+        let mut project = Project::default();
+        project.settings.h264_profile = Some("ultra".to_string());
+        project.settings.h264_level = Some("9.9".to_string());
+        assert_eq!(probed_warnings_of(check_h264_profile, &project).len(), 2);
+
+        project.settings.h264_profile = Some("high".to_string());
+        project.settings.h264_level = Some("3.0".to_string());
+        project.settings.output_width = Some(1920);
+        project.settings.output_height = Some(1080);
+        let warnings = probed_warnings_of(check_h264_profile, &project);
+        assert!(warnings.len() == 1 && warnings[0].contains("3.0 est trop bas"), "{:?}", warnings);
+        project.settings.h264_level = Some("4.1".to_string());
+        assert!(probed_warnings_of(check_h264_profile, &project).is_empty());
+        assert_eq!(h264_level_max_macroblocks(41), 8192);
+
+        project.settings.container = Some("webm".to_string());
+        assert!(probed_warnings_of(check_h264_profile, &project)[0].contains("VP9"));
+    }
+
+    #[test]
+    fn tunes_outside_both_encoder_families_are_reported() {
+        let mut project = Project::default();
+        project.settings.tune = Some("cinema".to_string());
+        assert!(warnings_of(check_tune, &project)[0].contains("non supporte"));
+        project.settings.tune = Some("hq".to_string());
+        assert!(warnings_of(check_tune, &project)[0].contains("NVENC"));
+        project.settings.container = Some("webm".to_string());
+        assert!(warnings_of(check_tune, &project)[0].contains("VP9"));
+    }
+
+    #[test]
+    fn merged_tracks_beyond_the_codec_channels_are_reported() {
+        let track = |name: &str| track_at(&format!("{}.m4a", name), 10.0);
+        let mut project = Project { audio_tracks: vec![track("a"), track("b"), track("c")], ..Project::default() };
+        project.settings.audio_merge = true;
+        project.settings.audio_codec = Some("libmp3lame".to_string());
+        assert!(warnings_of(check_audio_merge, &project)[0].contains("3 canaux"));
+        project.settings.audio_codec = Some("aac".to_string());
+        assert!(warnings_of(check_audio_merge, &project).is_empty());
+        project.settings.audio_merge = false;
+        project.settings.audio_codec = Some("libmp3lame".to_string());
+        assert!(warnings_of(check_audio_merge, &project).is_empty());
+    }
+}
@@ -1,5 +1,6 @@
 import { useEffect } from 'react';
 import { listen } from '@tauri-apps/api/event';
+import { invoke } from '@tauri-apps/api/core';
 import { useStore } from './store/useStore';
 import Header from './components/Header';
 import VideoPanel from './components/VideoPanel';
@@ -9,12 +10,13 @@ import ExportPanel from './components/ExportPanel';
 import StatusBar from './components/StatusBar';
 
 function App() {
-  const { checkDependencies, detectGpu, setExportProgress, dependencies } = useStore();
+  const { checkDependencies, detectGpu, recoverAutosave, setExportProgress, dependencies } = useStore();
 
   useEffect(() => {
     // Initialize
     checkDependencies();
     detectGpu();
+    recoverAutosave();
 
     // Listen for export progress
     const unlisten = listen<number>('export-progress', (event) => {
@@ -24,7 +26,20 @@ function App() {
     return () => {
       unlisten.then((fn) => fn());
     };
-  }, [checkDependencies, detectGpu, setExportProgress]);
+  }, [checkDependencies, detectGpu, recoverAutosave, setExportProgress]);
+
+  // Autosave unsaved work every minute for crash recovery
+  useEffect(() => {
+    const timer = setInterval(() => {
+      const { videos, audioTracks, settings, hasUnsavedChanges } = useStore.getState();
+      if (!hasUnsavedChanges) return;
+      invoke('autosave_project', {
+        project: { videos, audio_tracks: audioTracks, settings },
+      }).catch((error) => console.error("Erreur d'autosauvegarde:", error));
+    }, 60000);
+
+    return () => clearInterval(timer);
+  }, []);
 
   // Check if FFmpeg is available
   if (dependencies && !dependencies.has_ffmpeg) {
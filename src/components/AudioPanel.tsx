@@ -12,6 +12,8 @@ function AudioPanel() {
     moveAudioTrack,
     clearAudioTracks,
     setStatusMessage,
+    getDirectory,
+    rememberDirectory,
   } = useStore();
 
   const handleAddTracks = async () => {
@@ -24,7 +26,12 @@ function AudioPanel() {
             extensions: SUPPORTED_AUDIO_EXTENSIONS.map((e) => e.slice(1)),
           },
         ],
+        defaultPath: await getDirectory('import'),
       });
+      const first = Array.isArray(paths) ? paths[0] : paths;
+      if (first) {
+        await rememberDirectory('import', first);
+      }
       if (paths && Array.isArray(paths)) {
         await addAudioTracks(paths);
       } else if (paths) {
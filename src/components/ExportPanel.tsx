@@ -1,7 +1,7 @@
 import { useState } from 'react';
 import { save } from '@tauri-apps/plugin-dialog';
 import { useStore } from '../store/useStore';
-import { getVideoDuration, formatDuration } from '../types';
+import { getVideoDuration, formatDuration, pathIn } from '../types';
 
 type OutputFormat = 'mkv' | 'mp4' | 'webm';
 
@@ -23,6 +23,8 @@ function ExportPanel() {
     generatePreview,
     playPreview,
     setStatusMessage,
+    getDirectory,
+    rememberDirectory,
   } = useStore();
 
   const [outputFormat, setOutputFormat] = useState<OutputFormat>('mkv');
@@ -34,10 +36,11 @@ function ExportPanel() {
     try {
       const path = await save({
         filters: [{ name: formatInfo[outputFormat].label, extensions: [outputFormat] }],
-        defaultPath: `export.${outputFormat}`,
+        defaultPath: pathIn(await getDirectory('export'), `export.${outputFormat}`),
       });
 
       if (path) {
+        await rememberDirectory('export', path);
         const result = await startExport(path);
         if (result.success) {
           setStatusMessage(
@@ -131,9 +134,14 @@ function ExportPanel() {
                   style={{ width: `${exportProgress}%` }}
                 />
               </div>
-              <button onClick={cancelExport} className="btn btn-danger w-full">
-                Annuler
-              </button>
+              <div className="flex gap-2">
+                <button onClick={() => cancelExport()} className="btn btn-danger flex-1">
+                  Annuler
+                </button>
+                <button onClick={() => cancelExport(true)} className="btn btn-secondary flex-1">
+                  Arreter et garder
+                </button>
+              </div>
             </div>
           )}
 
@@ -1,5 +1,6 @@
 import { open, save } from '@tauri-apps/plugin-dialog';
 import { useStore } from '../store/useStore';
+import { pathIn } from '../types';
 
 function Header() {
   const {
@@ -9,6 +10,8 @@ function Header() {
     currentProjectPath,
     hasUnsavedChanges,
     setStatusMessage,
+    getDirectory,
+    rememberDirectory,
   } = useStore();
 
   const handleNew = async () => {
@@ -25,8 +28,10 @@ function Header() {
     try {
       const path = await open({
         filters: [{ name: 'Projets Video-Musique', extensions: ['mixproj'] }],
+        defaultPath: await getDirectory('project'),
       });
       if (path) {
+        await rememberDirectory('project', path as string);
         await loadProject(path as string);
       }
     } catch (error) {
@@ -50,9 +55,10 @@ function Header() {
     try {
       const path = await save({
         filters: [{ name: 'Projets Video-Musique', extensions: ['mixproj'] }],
-        defaultPath: 'projet.mixproj',
+        defaultPath: pathIn(await getDirectory('project'), 'projet.mixproj'),
       });
       if (path) {
+        await rememberDirectory('project', path);
         await saveProject(path);
       }
     } catch (error) {
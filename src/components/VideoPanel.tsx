@@ -4,8 +4,17 @@ import { useStore } from '../store/useStore';
 import { formatDuration, getVideoDuration, SUPPORTED_VIDEO_EXTENSIONS } from '../types';
 
 function VideoPanel() {
-  const { videos, addVideos, removeVideo, moveVideo, clearVideos, settings, setStatusMessage } =
-    useStore();
+  const {
+    videos,
+    addVideos,
+    removeVideo,
+    moveVideo,
+    clearVideos,
+    settings,
+    setStatusMessage,
+    getDirectory,
+    rememberDirectory,
+  } = useStore();
 
   const handleAddVideos = async () => {
     try {
@@ -17,7 +26,12 @@ function VideoPanel() {
             extensions: SUPPORTED_VIDEO_EXTENSIONS.map((e) => e.slice(1)),
           },
         ],
+        defaultPath: await getDirectory('import'),
       });
+      const first = Array.isArray(paths) ? paths[0] : paths;
+      if (first) {
+        await rememberDirectory('import', first);
+      }
       if (paths && Array.isArray(paths)) {
         await addVideos(paths);
       } else if (paths) {
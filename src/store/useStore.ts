@@ -8,9 +8,13 @@ import {
   GpuInfo,
   Dependencies,
   ExportResult,
+  MediaKind,
+  Config,
+  DirectoryKind,
   DEFAULT_SETTINGS,
   createAudioTrack,
   createVideoClip,
+  parentDirectory,
 } from '../types';
 
 interface AppState {
@@ -51,7 +55,7 @@ interface AppState {
 
   // Actions - Export
   startExport: (outputPath: string) => Promise<ExportResult>;
-  cancelExport: () => void;
+  cancelExport: (keepPartial?: boolean) => void;
   setExportProgress: (progress: number) => void;
 
   // Actions - Preview
@@ -62,14 +66,35 @@ interface AppState {
   newProject: () => void;
   saveProject: (path: string) => Promise<void>;
   loadProject: (path: string) => Promise<void>;
+  recoverAutosave: () => Promise<void>;
   setCurrentProjectPath: (path: string | null) => void;
 
+  // Actions - Directories
+  getDirectory: (kind: DirectoryKind) => Promise<string>;
+  rememberDirectory: (kind: DirectoryKind, path: string) => Promise<void>;
+
   // Actions - System
   checkDependencies: () => Promise<void>;
   detectGpu: () => Promise<void>;
   setStatusMessage: (message: string) => void;
 }
 
+// Splits dropped files into the ones ffprobe recognises as an accepted kind and the rest
+async function filterMedia(paths: string[], accepted: MediaKind[]) {
+  const kinds = await Promise.all(paths.map((path) => invoke<MediaKind>('is_valid_media', { path })));
+  const valid = paths.filter((_, i) => accepted.includes(kinds[i]));
+  const rejected = paths.filter((_, i) => !accepted.includes(kinds[i]));
+  return { valid, rejected };
+}
+
+function rejectedMessage(rejected: string[]) {
+  const names = rejected.map((p) => p.split('/').pop() || p.split('\\').pop() || p).join(', ');
+  return `Fichier(s) non supporte(s) ignore(s): ${names}`;
+}
+
+// Offered once per launch, even when StrictMode runs the startup effect twice
+let recoveryOffered = false;
+
 export const useStore = create<AppState>((set, get) => ({
   // Initial state
   videos: [],
@@ -85,8 +110,11 @@ export const useStore = create<AppState>((set, get) => ({
   hasUnsavedChanges: false,
 
   // Videos
-  addVideos: async (paths) => {
+  addVideos: async (candidates) => {
     try {
+      const { valid: paths, rejected } = await filterMedia(candidates, ['video']);
+      if (rejected.length > 0) set({ statusMessage: rejectedMessage(rejected) });
+      if (paths.length === 0) return;
       const durations = await invoke<number[]>('get_durations_parallel', { paths });
       const newVideos = paths.map((path, i) => createVideoClip(path, durations[i]));
       set((state) => ({
@@ -117,8 +145,11 @@ export const useStore = create<AppState>((set, get) => ({
   clearVideos: () => set({ videos: [], hasUnsavedChanges: true }),
 
   // Audio
-  addAudioTracks: async (paths) => {
+  addAudioTracks: async (candidates) => {
     try {
+      const { valid: paths, rejected } = await filterMedia(candidates, ['audio', 'video']);
+      if (rejected.length > 0) set({ statusMessage: rejectedMessage(rejected) });
+      if (paths.length === 0) return;
       const durations = await invoke<number[]>('get_durations_parallel', { paths });
       const newTracks = paths.map((path, i) => createAudioTrack(path, durations[i]));
       set((state) => ({
@@ -192,9 +223,12 @@ export const useStore = create<AppState>((set, get) => ({
     }
   },
 
-  cancelExport: () => {
-    invoke('cancel_export');
-    set({ isExporting: false, statusMessage: 'Export annule' });
+  cancelExport: (keepPartial = false) => {
+    invoke('cancel_export', { mode: keepPartial ? 'keep_partial' : 'discard' });
+    set({
+      isExporting: false,
+      statusMessage: keepPartial ? 'Export arrete, fichier partiel conserve' : 'Export annule',
+    });
   },
 
   setExportProgress: (progress) => set({ exportProgress: progress }),
@@ -245,6 +279,7 @@ export const useStore = create<AppState>((set, get) => ({
 
     try {
       await invoke('save_project', { project, filePath: path });
+      await invoke('clear_autosaves');
       set({
         currentProjectPath: path,
         hasUnsavedChanges: false,
@@ -273,8 +308,59 @@ export const useStore = create<AppState>((set, get) => ({
     }
   },
 
+  // The newest autosave is left by a session that ended with unsaved work
+  recoverAutosave: async () => {
+    if (recoveryOffered) return;
+    recoveryOffered = true;
+    try {
+      const project = await invoke<Project | null>('recover_project');
+      if (!project) return;
+      const confirmed = window.confirm(
+        'Un projet non sauvegarde a ete retrouve. Voulez-vous le restaurer?'
+      );
+      if (!confirmed) {
+        await invoke('clear_autosaves');
+        return;
+      }
+      set({
+        videos: project.videos,
+        audioTracks: project.audio_tracks,
+        settings: project.settings,
+        currentProjectPath: null,
+        hasUnsavedChanges: true,
+        statusMessage: 'Projet restaure',
+      });
+    } catch (error) {
+      console.error('Erreur de restauration:', error);
+    }
+  },
+
   setCurrentProjectPath: (path) => set({ currentProjectPath: path }),
 
+  // Directories
+  getDirectory: async (kind) => {
+    try {
+      const config = await invoke<Config>('get_config');
+      const directories: Record<DirectoryKind, string> = {
+        import: config.import_directory,
+        export: config.export_directory,
+        project: config.project_directory,
+      };
+      return directories[kind] || config.last_directory;
+    } catch (error) {
+      console.error('Erreur de lecture de la configuration:', error);
+      return '';
+    }
+  },
+
+  rememberDirectory: async (kind, path) => {
+    try {
+      await invoke('remember_directory', { kind, directory: parentDirectory(path) });
+    } catch (error) {
+      console.error("Erreur d'enregistrement du dossier:", error);
+    }
+  },
+
   // System
   checkDependencies: async () => {
     try {
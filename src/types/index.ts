@@ -39,6 +39,11 @@ export interface GpuInfo {
   encoder: string | null;
 }
 
+export type MediaKind = 'video' | 'audio' | 'image' | 'unknown';
+
+// Which folder a file dialog opens in, see `remember_directory`
+export type DirectoryKind = 'import' | 'export' | 'project';
+
 export interface Dependencies {
   has_ffmpeg: boolean;
   has_ffprobe: boolean;
@@ -52,10 +57,16 @@ export interface ExportResult {
   encoder: string | null;
   gpu_accelerated: boolean;
   duration_seconds: number;
+  partial?: boolean;
+  output_path?: string | null;
+  warnings?: string[];
 }
 
 export interface Config {
   last_directory: string;
+  import_directory: string;
+  export_directory: string;
+  project_directory: string;
   theme: string;
   window_width: number;
   window_height: number;
@@ -136,3 +147,16 @@ export function formatDuration(seconds: number): string {
   }
   return `${m}:${s.toString().padStart(2, '0')}`;
 }
+
+// The folder holding a file picked in a dialog
+export function parentDirectory(path: string): string {
+  const i = Math.max(path.lastIndexOf('/'), path.lastIndexOf('\\'));
+  return i > 0 ? path.slice(0, i) : path.slice(0, i + 1) || path;
+}
+
+// A save dialog's default path: `name` inside `directory` when one is known
+export function pathIn(directory: string, name: string): string {
+  if (!directory) return name;
+  const separator = directory.includes('\\') ? '\\' : '/';
+  return directory.replace(/[\\/]+$/, '') + separator + name;
+}